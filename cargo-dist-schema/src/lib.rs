@@ -110,14 +110,354 @@ pub struct GithubMatrixEntry {
     /// Github Runner to user
     #[serde(skip_serializing_if = "Option::is_none")]
     pub runner: Option<String>,
+    /// Every runner label to use (starting with `runner`), if there's more than one
+    ///
+    /// This is for Github's `runs-on` array form, like `["self-hosted", "linux"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runner_labels: Option<Vec<String>>,
     /// Expression to execute to install cargo-dist
     #[serde(skip_serializing_if = "Option::is_none")]
     pub install_dist: Option<String>,
     /// Arguments to pass to cargo-dist
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dist_args: Option<String>,
+    /// Shell that steps on this runner should use by default (bash or pwsh)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// The command that builds this task's targets (like "cargo build"), if it builds any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// The cargo wrapper (like "cross") to build non-native targets with, if any need it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_tool: Option<String>,
+    /// Expression to execute to install the build_tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_build_tool: Option<String>,
+    /// Expression to execute to install the system packages this task's targets need
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_dependencies: Option<String>,
+    /// Key that scopes this task's build cache to what it builds (like its targets)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_key: Option<String>,
+    /// Name to show for this task's job in the Github Actions UI, if not the default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_name: Option<String>,
+    /// Environment variables to set for this task's targets, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
+    /// Which shard of this task's tests this entry runs (0-based), if they're sharded
+    ///
+    /// Only shard 0 builds and uploads the task's artifacts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard: Option<u32>,
+    /// How many shards this task's tests are split into, if they're sharded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard_count: Option<u32>,
+    /// Extra user-specified keys, passed through verbatim for use by custom workflow steps
+    #[serde(flatten)]
+    #[serde(default)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl GithubMatrixEntry {
+    /// Start building a GithubMatrixEntry
+    ///
+    /// Prefer this over filling in the fields yourself, because [`GithubMatrixEntryBuilder::build`][]
+    /// checks that the result is something the generated workflow can actually run.
+    pub fn builder() -> GithubMatrixEntryBuilder {
+        GithubMatrixEntryBuilder::default()
+    }
+
+    /// Every runner label this task runs on, in order
+    pub fn runs_on(&self) -> &[String] {
+        match (&self.runner_labels, &self.runner) {
+            (Some(labels), _) => labels,
+            (None, Some(runner)) => std::slice::from_ref(runner),
+            (None, None) => &[],
+        }
+    }
+}
+
+/// One or more Github Runner labels, like the value of a job's `runs-on`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum GithubRunnerLabels {
+    /// A single runner label
+    Label(String),
+    /// Several runner labels (like `["self-hosted", "linux"]`), for Github's `runs-on` array form
+    Labels(Vec<String>),
+}
+
+impl GithubRunnerLabels {
+    /// Every label, in order
+    pub fn labels(&self) -> &[String] {
+        match self {
+            GithubRunnerLabels::Label(label) => std::slice::from_ref(label),
+            GithubRunnerLabels::Labels(labels) => labels,
+        }
+    }
 }
 
+impl std::fmt::Display for GithubRunnerLabels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubRunnerLabels::Label(label) => write!(f, "{label}"),
+            GithubRunnerLabels::Labels(labels) => write!(f, "[{}]", labels.join(", ")),
+        }
+    }
+}
+
+/// Builder for a [`GithubMatrixEntry`][], see [`GithubMatrixEntry::builder`][]
+#[derive(Debug, Clone, Default)]
+pub struct GithubMatrixEntryBuilder {
+    runner: Option<String>,
+    extra_runner_labels: Vec<String>,
+    install_dist: Option<String>,
+    dist_args: Option<String>,
+    shell: Option<String>,
+    build_command: Option<String>,
+    build_tool: Option<(String, String)>,
+    install_dependencies: Option<String>,
+    cache_key: Option<String>,
+    job_name: Option<String>,
+    env: BTreeMap<String, String>,
+    shard: Option<(u32, u32)>,
+    extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl GithubMatrixEntryBuilder {
+    /// Set the Github Runner to use (required)
+    pub fn runner(mut self, runner: impl Into<String>) -> Self {
+        self.runner = Some(runner.into());
+        self
+    }
+    /// Add more runner labels after the main one (making `runs-on` an array of labels)
+    pub fn extra_runner_labels(
+        mut self,
+        labels: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.extra_runner_labels
+            .extend(labels.into_iter().map(Into::into));
+        self
+    }
+    /// Set the expression to execute to install cargo-dist (required)
+    pub fn install_dist(mut self, install_dist: impl Into<String>) -> Self {
+        self.install_dist = Some(install_dist.into());
+        self
+    }
+    /// Set the arguments to pass to cargo-dist (required)
+    pub fn dist_args(mut self, dist_args: impl Into<String>) -> Self {
+        self.dist_args = Some(dist_args.into());
+        self
+    }
+    /// Set the shell that steps on this runner should use by default (bash or pwsh)
+    pub fn shell(mut self, shell: impl Into<String>) -> Self {
+        self.shell = Some(shell.into());
+        self
+    }
+    /// Set the command that builds this task's targets (like "cargo build")
+    pub fn build_command(mut self, build_command: impl Into<String>) -> Self {
+        self.build_command = Some(build_command.into());
+        self
+    }
+    /// Build non-native targets with a cargo wrapper (like "cross"), installed by running `install`
+    pub fn build_tool(mut self, tool: impl Into<String>, install: impl Into<String>) -> Self {
+        self.build_tool = Some((tool.into(), install.into()));
+        self
+    }
+    /// Set the expression to execute to install system packages before building
+    pub fn install_dependencies(mut self, install_dependencies: impl Into<String>) -> Self {
+        self.install_dependencies = Some(install_dependencies.into());
+        self
+    }
+    /// Set the key that scopes this task's build cache
+    pub fn cache_key(mut self, cache_key: impl Into<String>) -> Self {
+        self.cache_key = Some(cache_key.into());
+        self
+    }
+    /// Set the name to show for this task's job in the Github Actions UI
+    pub fn job_name(mut self, job_name: impl Into<String>) -> Self {
+        self.job_name = Some(job_name.into());
+        self
+    }
+    /// Set an environment variable for this task's targets
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+    /// Make this entry shard `shard` (0-based) of a task whose tests are split into `shard_count`
+    pub fn shard(mut self, shard: u32, shard_count: u32) -> Self {
+        self.shard = Some((shard, shard_count));
+        self
+    }
+    /// Add an extra key, passed through verbatim for use by custom workflow steps
+    pub fn extra(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+    /// Add several extra keys, see [`GithubMatrixEntryBuilder::extra`][]
+    pub fn extras(mut self, extra: impl IntoIterator<Item = (String, serde_json::Value)>) -> Self {
+        self.extra.extend(extra);
+        self
+    }
+
+    /// Check the entry is well-formed and produce it
+    pub fn build(self) -> Result<GithubMatrixEntry, GithubMatrixEntryError> {
+        let GithubMatrixEntryBuilder {
+            runner,
+            extra_runner_labels,
+            install_dist,
+            dist_args,
+            shell,
+            build_command,
+            build_tool,
+            install_dependencies,
+            cache_key,
+            job_name,
+            env,
+            shard,
+            extra,
+        } = self;
+
+        let required = |value: Option<String>, field: &'static str| match value {
+            Some(value) if !value.trim().is_empty() => Ok(value),
+            _ => Err(GithubMatrixEntryError::MissingField { field }),
+        };
+        let runner = required(runner, "runner")?;
+        let runner_labels = if extra_runner_labels.is_empty() {
+            None
+        } else {
+            let mut labels = vec![runner.clone()];
+            for label in extra_runner_labels {
+                labels.push(required(Some(label), "runner")?);
+            }
+            Some(labels)
+        };
+        let install_dist = required(install_dist, "install_dist")?;
+        let dist_args = required(dist_args, "dist_args")?;
+
+        if let Some(shell) = &shell {
+            if shell != "bash" && shell != "pwsh" {
+                return Err(GithubMatrixEntryError::InvalidShell {
+                    shell: shell.clone(),
+                });
+            }
+        }
+
+        let (build_tool, install_build_tool) = match build_tool {
+            Some((tool, install)) => (
+                Some(required(Some(tool), "build_tool")?),
+                Some(required(Some(install), "install_build_tool")?),
+            ),
+            None => (None, None),
+        };
+
+        let (shard, shard_count) = match shard {
+            Some((shard, shard_count)) if shard >= shard_count => {
+                return Err(GithubMatrixEntryError::InvalidShard { shard, shard_count })
+            }
+            Some((shard, shard_count)) => (Some(shard), Some(shard_count)),
+            None => (None, None),
+        };
+
+        // extra gets flattened into the entry, so it can't reuse the names of real fields
+        if let Some(key) = extra
+            .keys()
+            .find(|key| GITHUB_MATRIX_ENTRY_FIELDS.contains(&key.as_str()))
+        {
+            return Err(GithubMatrixEntryError::ReservedExtraKey { key: key.clone() });
+        }
+
+        Ok(GithubMatrixEntry {
+            runner: Some(runner),
+            runner_labels,
+            install_dist: Some(install_dist),
+            dist_args: Some(dist_args),
+            shell,
+            build_command,
+            build_tool,
+            install_build_tool,
+            install_dependencies,
+            cache_key,
+            job_name,
+            env: (!env.is_empty()).then_some(env),
+            shard,
+            shard_count,
+            extra,
+        })
+    }
+}
+
+/// The keys a [`GithubMatrixEntry`][] serializes its own fields to
+const GITHUB_MATRIX_ENTRY_FIELDS: &[&str] = &[
+    "runner",
+    "runner_labels",
+    "install_dist",
+    "dist_args",
+    "shell",
+    "build_command",
+    "build_tool",
+    "install_build_tool",
+    "install_dependencies",
+    "cache_key",
+    "job_name",
+    "env",
+    "shard",
+    "shard_count",
+];
+
+/// Reasons [`GithubMatrixEntryBuilder::build`][] can reject an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GithubMatrixEntryError {
+    /// A required field wasn't set (or was empty)
+    MissingField {
+        /// The field that's missing
+        field: &'static str,
+    },
+    /// The shell isn't one the workflow knows how to use
+    InvalidShell {
+        /// The shell that was given
+        shell: String,
+    },
+    /// The shard doesn't fit in the shard count
+    InvalidShard {
+        /// The (0-based) shard
+        shard: u32,
+        /// How many shards there are
+        shard_count: u32,
+    },
+    /// An extra key would clobber one of the entry's own fields
+    ReservedExtraKey {
+        /// The key that was given
+        key: String,
+    },
+}
+
+impl std::fmt::Display for GithubMatrixEntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubMatrixEntryError::MissingField { field } => {
+                write!(f, "github matrix entry is missing {field}")
+            }
+            GithubMatrixEntryError::InvalidShell { shell } => {
+                write!(
+                    f,
+                    "github matrix entry has shell {shell}, but only bash and pwsh are supported"
+                )
+            }
+            GithubMatrixEntryError::InvalidShard { shard, shard_count } => {
+                write!(f, "github matrix entry is shard {shard}, but there are only {shard_count} shards (shards are 0-based)")
+            }
+            GithubMatrixEntryError::ReservedExtraKey { key } => {
+                write!(f, "github matrix entry has extra key {key}, but that's the name of one of its fields")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GithubMatrixEntryError {}
+
 /// Type of job to run on pull request
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 #[non_exhaustive]
@@ -210,6 +550,10 @@ pub struct Artifact {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub install_hint: Option<String>,
+    /// A string describing how to verify the signature of this artifact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub verify_hint: Option<String>,
     /// A brief description of what this artifact is
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
@@ -218,6 +562,39 @@ pub struct Artifact {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub checksum: Option<String>,
+    /// The size of the artifact in bytes
+    ///
+    /// This is only known once the artifact has actually been built, so it will be
+    /// missing from manifests that just plan out a build. Github CI fills it in for
+    /// the dist-manifest.json it publishes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Where the artifact was built, for auditing
+    ///
+    /// This is only filled in by CI when it publishes the artifact, so it will be
+    /// missing from manifests that cargo-dist prints itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// The URL the artifact can be downloaded from once it's published
+    ///
+    /// This is missing if cargo-dist doesn't know where artifacts get published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub download_url: Option<String>,
+}
+
+/// Where an [`Artifact`][] was built, as recorded by the CI run that published it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Provenance {
+    /// The id of the CI workflow run that built the artifact
+    pub workflow_run_id: String,
+    /// The git commit the artifact was built from
+    pub commit_sha: String,
+    /// The identity of the workflow that built the artifact
+    /// (like `https://github.com/owner/repo/.github/workflows/release.yml@refs/tags/v1.0.0`)
+    pub builder_id: String,
 }
 
 /// An asset contained in an artifact (executable, license, etc.)
@@ -278,6 +655,9 @@ pub enum ArtifactKind {
     /// A checksum of another artifact
     #[serde(rename = "checksum")]
     Checksum,
+    /// A record of the CI build matrix that produced a release
+    #[serde(rename = "build-matrix")]
+    BuildMatrix,
     /// Unknown to this version of cargo-dist-schema
     ///
     /// This is a fallback for forward/backward-compat
@@ -427,3 +807,95 @@ fn emit() {
     let json_schema = serde_json::to_string_pretty(&schema).unwrap();
     insta::assert_snapshot!(json_schema);
 }
+
+#[cfg(test)]
+fn matrix_entry() -> GithubMatrixEntryBuilder {
+    GithubMatrixEntry::builder()
+        .runner("ubuntu-20.04")
+        .install_dist("curl --proto '=https' --tlsv1.2 -LsSf https://example.com/installer.sh | sh")
+        .dist_args("--artifacts=local --target=x86_64-unknown-linux-gnu")
+}
+
+#[test]
+fn matrix_entry_build() {
+    let entry = matrix_entry()
+        .extra_runner_labels(["linux"])
+        .shell("bash")
+        .extra("package", "axolotlsay")
+        .build()
+        .unwrap();
+    assert_eq!(entry.runner.as_deref(), Some("ubuntu-20.04"));
+    assert_eq!(entry.runs_on(), ["ubuntu-20.04", "linux"]);
+    assert_eq!(entry.shell.as_deref(), Some("bash"));
+    assert_eq!(entry.env, None);
+    assert_eq!(entry.extra["package"], "axolotlsay");
+}
+
+#[test]
+fn matrix_entry_missing_field() {
+    let err = GithubMatrixEntry::builder()
+        .install_dist("cargo install cargo-dist")
+        .dist_args("--artifacts=local")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::MissingField { field: "runner" }
+    );
+
+    // Blank values don't count
+    let err = matrix_entry().dist_args("  ").build().unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::MissingField { field: "dist_args" }
+    );
+    let err = matrix_entry()
+        .extra_runner_labels([""])
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::MissingField { field: "runner" }
+    );
+}
+
+#[test]
+fn matrix_entry_invalid_shell() {
+    let err = matrix_entry().shell("zsh").build().unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::InvalidShell {
+            shell: "zsh".to_owned()
+        }
+    );
+    matrix_entry().shell("pwsh").build().unwrap();
+}
+
+#[test]
+fn matrix_entry_shard() {
+    let entry = matrix_entry().shard(2, 3).build().unwrap();
+    assert_eq!((entry.shard, entry.shard_count), (Some(2), Some(3)));
+
+    let err = matrix_entry().shard(3, 3).build().unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::InvalidShard {
+            shard: 3,
+            shard_count: 3
+        }
+    );
+}
+
+#[test]
+fn matrix_entry_reserved_extra_key() {
+    let err = matrix_entry()
+        .extra("runner", "macos-11")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        GithubMatrixEntryError::ReservedExtraKey {
+            key: "runner".to_owned()
+        }
+    );
+}
@@ -0,0 +1,245 @@
+//! CI script generation for Azure Pipelines
+//!
+//! This mirrors the Gitlab backend: targets get grouped onto machines by the same
+//! logic as Github CI, but the result is a single azure-pipelines.yml that publishes
+//! to an Azure Artifacts feed (as a Universal Package).
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_dist_schema::PrRunMode;
+use serde::Serialize;
+use tracing::warn;
+
+use super::{
+    github::{
+        is_macos_github_runner, GithubRunner, GITHUB_MACOS_ARM64_RUNNER, GITHUB_WINDOWS_RUNNER,
+    },
+    CiPostProcessor,
+};
+use crate::{
+    backend::templates::TEMPLATE_CI_AZURE,
+    config::{CiStyle, GlobalBuildMode, RunnerOs},
+    errors::DistResult,
+    DistGraph, GeneratedFile, StaleFile,
+};
+
+const AZURE_PIPELINES_FILE: &str = "azure-pipelines.yml";
+
+/// The vmImage for Azure's hosted linux agents
+const AZURE_LINUX_IMAGE: &str = "ubuntu-latest";
+/// The vmImage for Azure's hosted macos agents (which are x64, but build arm64 macos too)
+const AZURE_MACOS_IMAGE: &str = "macOS-latest";
+/// The vmImage for Azure's hosted windows agents
+const AZURE_WINDOWS_IMAGE: &str = "windows-latest";
+
+/// Info about running cargo-dist in Azure Pipelines
+#[derive(Debug, Serialize)]
+pub struct AzureCiInfo {
+    /// Version of cargo-dist to install
+    pub dist_version: String,
+    /// expression to use for installing cargo-dist in the plan job
+    pub install_dist_sh: String,
+    /// The vmImage the plan job runs on
+    pub linux_image: String,
+    /// The jobs that build local artifacts
+    pub local_jobs: Vec<AzureJob>,
+    /// The job that builds global artifacts (if any)
+    pub global_job: Option<AzureJob>,
+    /// What kind of job to run on pull requests
+    pub pr_run_mode: PrRunMode,
+    /// Whether to skip checking whether the azure-pipelines.yml is up to date
+    pub allow_dirty: bool,
+}
+
+/// A job that runs `cargo dist build` on some Azure hosted agent
+#[derive(Debug, Serialize)]
+pub struct AzureJob {
+    /// The name of the job (and of the pipeline artifact it publishes)
+    ///
+    /// Azure only allows letters, numbers, and underscores here.
+    pub name: String,
+    /// The name to show for the job in the Azure UI
+    pub display_name: String,
+    /// The vmImage of the hosted agent pool to run on
+    pub vm_image: String,
+    /// Whether the agent uses powershell instead of bash
+    pub windows: bool,
+    /// expression to use for installing cargo-dist
+    pub install_dist: String,
+    /// Arguments to pass to `cargo dist build`
+    pub dist_args: String,
+}
+
+impl AzureCiInfo {
+    /// Compute the Azure Pipelines stuff
+    pub fn new(dist: &DistGraph) -> DistResult<AzureCiInfo> {
+        // If they don't specify a cargo-dist version, use this one
+        let self_dist_version = super::SELF_DIST_VERSION.parse().unwrap();
+        let dist_version = dist
+            .desired_cargo_dist_version
+            .as_ref()
+            .unwrap_or(&self_dist_version);
+        let install_dist_for =
+            |os| super::install_dist_for_os(dist_version, os, &dist.install_dist_methods);
+        let install_dist_sh = install_dist_for(RunnerOs::Linux);
+
+        if !dist.create_release {
+            warn!("create-release = false isn't supported by Azure Pipelines, artifacts will still be published to Azure Artifacts");
+        }
+        if !dist.publish_jobs.is_empty() {
+            warn!("publish-jobs aren't supported by Azure Pipelines yet, they'll only run in Github CI");
+        }
+
+        // Like Gitlab's, the jobs are fixed when the pipeline is generated (not
+        // planned per-tag), so they have to cover every configured target
+        let needs_global_build = dist.global_build != GlobalBuildMode::Never;
+        let mut local_jobs = vec![];
+        for build in super::fixed_local_builds(dist, "Azure Pipelines")? {
+            let machine = AzureMachine::for_github_runner(build.runner);
+            let display_name = build.job_name(machine.name);
+            let install_dist = install_dist_for(machine.os);
+            local_jobs.push(machine.job(display_name, install_dist, build.dist_args));
+        }
+
+        // Global artifacts can be built anywhere, and linux is the cheapest place
+        let global_job = needs_global_build.then(|| {
+            AzureMachine::linux().job(
+                "build-global".to_owned(),
+                install_dist_sh.clone(),
+                "--artifacts=global".to_owned(),
+            )
+        });
+
+        Ok(AzureCiInfo {
+            dist_version: dist_version.to_string(),
+            install_dist_sh,
+            linux_image: AZURE_LINUX_IMAGE.to_owned(),
+            local_jobs,
+            global_job,
+            pr_run_mode: dist.pr_run_mode.clone(),
+            allow_dirty: dist.allow_dirty.contains(&CiStyle::Azure),
+        })
+    }
+
+    fn azure_pipelines_path(&self, output_dir: &Utf8Path) -> Utf8PathBuf {
+        output_dir.join(AZURE_PIPELINES_FILE)
+    }
+
+    /// Generate the requested configuration and returns it as a string.
+    ///
+    /// Any post_processors get to edit the result (in order) before it's returned.
+    pub fn generate_azure_ci(
+        &self,
+        dist: &DistGraph,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<String, miette::Report> {
+        super::render_ci_file(dist, TEMPLATE_CI_AZURE, self, post_processors)
+    }
+
+    /// Write azure-pipelines.yml to disk, rooted at output_dir (usually the workspace dir)
+    pub fn write_to_disk(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<GeneratedFile>, miette::Report> {
+        let ci_file = self.azure_pipelines_path(output_dir);
+        let rendered = self.generate_azure_ci(dist, post_processors)?;
+        super::write_ci_file(&ci_file, &rendered, "Azure Pipelines")
+    }
+
+    /// Check whether the new configuration differs from the config on disk
+    /// without actually writing the result.
+    ///
+    /// The config on disk is looked up relative to output_dir (usually the workspace dir).
+    pub fn check_azure_ci(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<(), miette::Report> {
+        let stale = self.stale_azure_files(dist, output_dir, post_processors)?;
+        crate::check_stale_files(stale)?;
+        Ok(())
+    }
+
+    /// Get every file we'd generate that differs from what's on disk
+    /// (empty if allow-dirty is set for Azure Pipelines).
+    ///
+    /// The files on disk are looked up relative to output_dir (usually the workspace dir).
+    pub fn stale_azure_files(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<StaleFile>, miette::Report> {
+        let file = self.azure_pipelines_path(output_dir);
+        let rendered = self.generate_azure_ci(dist, post_processors)?;
+        Ok(super::stale_ci_file(&file, &rendered, self.allow_dirty))
+    }
+}
+
+/// The Azure hosted agent that's closest to one of the Github Runners we'd pick
+struct AzureMachine {
+    /// Short name of the platform, for job names
+    name: &'static str,
+    /// Which install-dist-methods entry applies here
+    os: RunnerOs,
+    vm_image: &'static str,
+    windows: bool,
+}
+
+impl AzureMachine {
+    /// Map a Github Runner to the Azure hosted agent that's closest to it
+    fn for_github_runner(runner: GithubRunner) -> Self {
+        if is_macos_github_runner(runner) {
+            AzureMachine {
+                name: if runner == GITHUB_MACOS_ARM64_RUNNER {
+                    "macos-arm64"
+                } else {
+                    "macos"
+                },
+                os: RunnerOs::Macos,
+                vm_image: AZURE_MACOS_IMAGE,
+                windows: false,
+            }
+        } else if runner == GITHUB_WINDOWS_RUNNER {
+            AzureMachine {
+                name: "windows",
+                os: RunnerOs::Windows,
+                vm_image: AZURE_WINDOWS_IMAGE,
+                windows: true,
+            }
+        } else {
+            Self::linux()
+        }
+    }
+
+    fn linux() -> Self {
+        AzureMachine {
+            name: "linux",
+            os: RunnerOs::Linux,
+            vm_image: AZURE_LINUX_IMAGE,
+            windows: false,
+        }
+    }
+
+    /// Make a job that builds dist_args on this machine
+    fn job(&self, display_name: String, install_dist: String, dist_args: String) -> AzureJob {
+        AzureJob {
+            name: azure_identifier(&display_name),
+            display_name,
+            vm_image: self.vm_image.to_owned(),
+            windows: self.windows,
+            install_dist,
+            dist_args,
+        }
+    }
+}
+
+/// Turn a name into something Azure accepts as a job/stage name
+/// (letters, numbers, and underscores)
+fn azure_identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,265 @@
+//! CI script generation for CircleCI
+//!
+//! This mirrors the Gitlab backend: targets get grouped onto machines by the same
+//! logic as Github CI, but the result is a single .circleci/config.yml whose jobs
+//! run on CircleCI executors and publish to a Github Release (with the gh cli).
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_dist_schema::PrRunMode;
+use serde::Serialize;
+use tracing::warn;
+
+use super::{
+    github::{
+        is_macos_github_runner, GithubRunner, GITHUB_MACOS_ARM64_RUNNER, GITHUB_WINDOWS_RUNNER,
+    },
+    CiPostProcessor,
+};
+use crate::{
+    backend::templates::TEMPLATE_CI_CIRCLECI,
+    config::{CiStyle, GlobalBuildMode, RunnerOs},
+    errors::DistResult,
+    DistGraph, GeneratedFile, StaleFile,
+};
+
+const CIRCLECI_DIR: &str = ".circleci";
+const CIRCLECI_FILE: &str = "config.yml";
+
+/// The docker image linux jobs run in
+const CIRCLECI_LINUX_IMAGE: &str = "cimg/base:current";
+/// The resource class for linux jobs
+const CIRCLECI_LINUX_RESOURCE_CLASS: &str = "medium";
+/// The Xcode version macos jobs run with
+const CIRCLECI_MACOS_XCODE: &str = "15.1.0";
+/// The resource class for macos jobs (Apple Silicon, which builds x64 macos too)
+const CIRCLECI_MACOS_RESOURCE_CLASS: &str = "macos.m1.medium.gen1";
+/// The machine image windows jobs run on
+const CIRCLECI_WINDOWS_IMAGE: &str = "windows-server-2022-gui:current";
+/// The resource class for windows jobs
+const CIRCLECI_WINDOWS_RESOURCE_CLASS: &str = "windows.medium";
+
+/// Info about running cargo-dist in CircleCI
+#[derive(Debug, Serialize)]
+pub struct CircleCiInfo {
+    /// Version of cargo-dist to install
+    pub dist_version: String,
+    /// The job that runs `cargo dist plan`
+    pub plan_job: CircleCiJob,
+    /// The jobs that build local artifacts
+    pub local_jobs: Vec<CircleCiJob>,
+    /// The job that builds global artifacts (if any)
+    pub global_job: Option<CircleCiJob>,
+    /// What kind of job to run on (non-tag) branch pushes
+    pub pr_run_mode: PrRunMode,
+    /// whether to create the release or upload to an existing one
+    pub create_release: bool,
+    /// Whether to skip checking whether the .circleci/config.yml is up to date
+    pub allow_dirty: bool,
+}
+
+/// A job that runs cargo-dist on some CircleCI executor
+#[derive(Debug, Serialize)]
+pub struct CircleCiJob {
+    /// The name of the job
+    pub name: String,
+    /// The executor to run on (already as YAML, indented to sit under the job)
+    pub executor: String,
+    /// The resource class of the executor
+    pub resource_class: String,
+    /// Whether the executor uses powershell instead of bash
+    pub windows: bool,
+    /// Commands that make sure the executor has rust (and jq, for bash executors)
+    pub setup: Vec<String>,
+    /// expression to use for installing cargo-dist
+    pub install_dist: String,
+    /// Arguments to pass to `cargo dist build`
+    pub dist_args: String,
+}
+
+impl CircleCiInfo {
+    /// Compute the CircleCI stuff
+    pub fn new(dist: &DistGraph) -> DistResult<CircleCiInfo> {
+        // If they don't specify a cargo-dist version, use this one
+        let self_dist_version = super::SELF_DIST_VERSION.parse().unwrap();
+        let dist_version = dist
+            .desired_cargo_dist_version
+            .as_ref()
+            .unwrap_or(&self_dist_version);
+        let install_dist_for =
+            |os| super::install_dist_for_os(dist_version, os, &dist.install_dist_methods);
+
+        if !dist.publish_jobs.is_empty() {
+            warn!("publish-jobs aren't supported by CircleCI yet, they'll only run in Github CI");
+        }
+
+        // Like Gitlab's, the jobs are fixed when the config is generated (not
+        // planned per-tag), so they have to cover every configured target
+        let needs_global_build = dist.global_build != GlobalBuildMode::Never;
+        let mut local_jobs = vec![];
+        for build in super::fixed_local_builds(dist, "CircleCI")? {
+            let machine = CircleCiMachine::for_github_runner(build.runner);
+            let name = build.job_name(machine.name);
+            let install_dist = install_dist_for(machine.os);
+            local_jobs.push(machine.job(name, install_dist, build.dist_args));
+        }
+
+        // Planning and global artifacts can happen anywhere, and linux is the cheapest place
+        let linux = CircleCiMachine::linux();
+        let install_dist_sh = install_dist_for(RunnerOs::Linux);
+        let plan_job = linux.job("plan".to_owned(), install_dist_sh.clone(), String::new());
+        let global_job = needs_global_build.then(|| {
+            linux.job(
+                "build-global".to_owned(),
+                install_dist_sh,
+                "--artifacts=global".to_owned(),
+            )
+        });
+
+        Ok(CircleCiInfo {
+            dist_version: dist_version.to_string(),
+            plan_job,
+            local_jobs,
+            global_job,
+            pr_run_mode: dist.pr_run_mode.clone(),
+            create_release: dist.create_release,
+            allow_dirty: dist.allow_dirty.contains(&CiStyle::CircleCi),
+        })
+    }
+
+    fn circleci_path(&self, output_dir: &Utf8Path) -> Utf8PathBuf {
+        output_dir.join(CIRCLECI_DIR).join(CIRCLECI_FILE)
+    }
+
+    /// Generate the requested configuration and returns it as a string.
+    ///
+    /// Any post_processors get to edit the result (in order) before it's returned.
+    pub fn generate_circleci(
+        &self,
+        dist: &DistGraph,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<String, miette::Report> {
+        super::render_ci_file(dist, TEMPLATE_CI_CIRCLECI, self, post_processors)
+    }
+
+    /// Write .circleci/config.yml to disk, rooted at output_dir (usually the workspace dir)
+    pub fn write_to_disk(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<GeneratedFile>, miette::Report> {
+        let ci_file = self.circleci_path(output_dir);
+        let rendered = self.generate_circleci(dist, post_processors)?;
+        super::write_ci_file(&ci_file, &rendered, "CircleCI")
+    }
+
+    /// Check whether the new configuration differs from the config on disk
+    /// without actually writing the result.
+    ///
+    /// The config on disk is looked up relative to output_dir (usually the workspace dir).
+    pub fn check_circleci(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<(), miette::Report> {
+        let stale = self.stale_circleci_files(dist, output_dir, post_processors)?;
+        crate::check_stale_files(stale)?;
+        Ok(())
+    }
+
+    /// Get every file we'd generate that differs from what's on disk
+    /// (empty if allow-dirty is set for CircleCI).
+    ///
+    /// The files on disk are looked up relative to output_dir (usually the workspace dir).
+    pub fn stale_circleci_files(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<StaleFile>, miette::Report> {
+        let file = self.circleci_path(output_dir);
+        let rendered = self.generate_circleci(dist, post_processors)?;
+        Ok(super::stale_ci_file(&file, &rendered, self.allow_dirty))
+    }
+}
+
+/// The CircleCI executor that's closest to one of the Github Runners we'd pick
+struct CircleCiMachine {
+    /// Short name of the platform, for job names
+    name: &'static str,
+    /// Which install-dist-methods entry applies here
+    os: RunnerOs,
+    executor: String,
+    resource_class: &'static str,
+    windows: bool,
+    setup: Vec<String>,
+}
+
+impl CircleCiMachine {
+    /// Map a Github Runner to the CircleCI executor that's closest to it
+    fn for_github_runner(runner: GithubRunner) -> Self {
+        if is_macos_github_runner(runner) {
+            CircleCiMachine {
+                name: if runner == GITHUB_MACOS_ARM64_RUNNER {
+                    "macos-arm64"
+                } else {
+                    "macos"
+                },
+                os: RunnerOs::Macos,
+                executor: format!("macos:\n      xcode: \"{CIRCLECI_MACOS_XCODE}\""),
+                resource_class: CIRCLECI_MACOS_RESOURCE_CLASS,
+                windows: false,
+                setup: vec![
+                    Self::RUSTUP_SH.to_owned(),
+                    r#"echo 'export PATH="$HOME/.cargo/bin:$PATH"' >> "$BASH_ENV""#.to_owned(),
+                    "command -v jq > /dev/null || brew install jq".to_owned(),
+                ],
+            }
+        } else if runner == GITHUB_WINDOWS_RUNNER {
+            CircleCiMachine {
+                name: "windows",
+                os: RunnerOs::Windows,
+                executor: format!(
+                    "machine:\n      image: \"{CIRCLECI_WINDOWS_IMAGE}\"\n    shell: powershell.exe -ExecutionPolicy Bypass"
+                ),
+                resource_class: CIRCLECI_WINDOWS_RESOURCE_CLASS,
+                windows: true,
+                setup: vec![
+                    "if (!(Get-Command cargo -ErrorAction SilentlyContinue)) { Invoke-WebRequest https://win.rustup.rs/x86_64 -OutFile rustup-init.exe; ./rustup-init.exe -y --profile minimal }".to_owned(),
+                ],
+            }
+        } else {
+            Self::linux()
+        }
+    }
+
+    const RUSTUP_SH: &'static str = "command -v cargo > /dev/null || curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y --profile minimal";
+
+    fn linux() -> Self {
+        CircleCiMachine {
+            name: "linux",
+            os: RunnerOs::Linux,
+            executor: format!("docker:\n      - image: \"{CIRCLECI_LINUX_IMAGE}\""),
+            resource_class: CIRCLECI_LINUX_RESOURCE_CLASS,
+            windows: false,
+            setup: vec![
+                Self::RUSTUP_SH.to_owned(),
+                r#"echo 'export PATH="$HOME/.cargo/bin:$PATH"' >> "$BASH_ENV""#.to_owned(),
+            ],
+        }
+    }
+
+    /// Make a job that runs dist_args on this machine
+    fn job(&self, name: String, install_dist: String, dist_args: String) -> CircleCiJob {
+        CircleCiJob {
+            name,
+            executor: self.executor.clone(),
+            resource_class: self.resource_class.to_owned(),
+            windows: self.windows,
+            setup: self.setup.clone(),
+            install_dist,
+            dist_args,
+        }
+    }
+}
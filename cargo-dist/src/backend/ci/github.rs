@@ -5,28 +5,44 @@
 // FIXME(#283): migrate this to minijinja (steal logic from oranda to load a whole dir)
 
 use axoasset::LocalAsset;
-use cargo_dist_schema::{GithubMatrix, GithubMatrixEntry};
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_dist_schema::{GithubMatrix, GithubMatrixEntry, GithubRunnerLabels};
+use itertools::Itertools;
+use semver::Version;
 use serde::Serialize;
-use tracing::warn;
+use tracing::{info, warn};
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
 
+use super::CiPostProcessor;
 use crate::{
-    backend::templates::TEMPLATE_CI_GITHUB,
-    config::CiStyle,
+    backend::templates::{TEMPLATE_CI_GITHUB, TEMPLATE_CI_GITHUB_BUILD_ACTION},
+    config::{
+        ArtifactTransport, BuildOverride, CiStyle, ConcurrencyMode, DistArchive, GlobalBuildMode,
+        InstallDistMethod, InstallerStyle, JobTimeouts, PublishStyle, RunnerOs, SystemDependencies,
+    },
     errors::{DistError, DistResult},
-    DistGraph, SortedMap, SortedSet, TargetTriple,
+    DistGraph, GeneratedFile, SortedMap, SortedSet, StaleFile, TargetTriple,
 };
 
 const GITHUB_CI_DIR: &str = ".github/workflows/";
 const GITHUB_CI_FILE: &str = "release.yml";
+const GITHUB_BUILD_ACTION_DIR: &str = ".github/actions/cargo-dist-build/";
+const GITHUB_BUILD_ACTION_FILE: &str = "action.yml";
 
 /// Info about running cargo-dist in Github CI
 #[derive(Debug, Serialize)]
 pub struct GithubCiInfo {
+    /// The name of the workflow
+    pub workflow_name: String,
+    /// The workflow files to generate (just release.yml, unless it's split per app)
+    pub workflows: Vec<GithubWorkflow>,
     /// Version of rust toolchain to install (deprecated)
     pub rust_version: Option<String>,
-    /// expression to use for installing cargo-dist via shell script
+    /// Version of cargo-dist to install
+    pub dist_version: String,
+    /// expression to use for installing cargo-dist on linux runners (via sh)
     pub install_dist_sh: String,
-    /// expression to use for installing cargo-dist via powershell script
+    /// expression to use for installing cargo-dist on windows runners (via powershell)
     pub install_dist_ps1: String,
     /// Whether to fail-fast
     pub fail_fast: bool,
@@ -36,19 +52,401 @@ pub struct GithubCiInfo {
     pub pr_run_mode: cargo_dist_schema::PrRunMode,
     /// global task
     pub global_task: Option<GithubMatrixEntry>,
-    /// homebrew tap
-    pub tap: Option<String>,
+    /// whether everything the global task builds is optional, so it's allowed to fail
+    pub global_task_optional: bool,
+    /// task that runs the tests before anything gets built (if run-tests is set,
+    /// and the tests aren't sharded across the build tasks instead)
+    pub test_task: Option<GithubTestTask>,
+    /// how the build tasks split up the tests, if they're sharded (see test-shards)
+    pub test_shards: Option<GithubTestShards>,
+    /// installers that each get their own task that's allowed to fail
+    pub optional_installers: Vec<String>,
+    /// extra inputs (like retention-days) for each job's upload-artifact steps, by job name
+    pub artifact_uploads: SortedMap<String, SortedMap<String, u32>>,
+    /// homebrew taps to publish to (each gets its own job)
+    pub homebrew_taps: Vec<GithubHomebrewTap>,
     /// publish jobs
     pub publish_jobs: Vec<String>,
+    /// conditions under which each publish job should run (already guarded so that
+    /// pull requests never run them)
+    pub publish_job_conditions: SortedMap<String, String>,
+    /// names of the secrets each publish job uses
+    pub publish_job_secrets: SortedMap<String, String>,
+    /// packages the crates-io publish job publishes, in the order to publish them
+    pub crates_io_packages: Vec<String>,
+    /// cargo registries the crates-io publish job publishes to (each gets its own job)
+    pub cargo_registries: Vec<GithubCargoRegistry>,
     /// whether to create the release or assume an existing one
     pub create_release: bool,
+    /// whether to add a table of every artifact to the release body
+    pub release_notes_table: bool,
+    /// how to limit the workflow to one run per ref at a time ("queue" or "cancel"), if at all
+    pub concurrency: Option<String>,
+    /// steps to run at the start of every job that runs cargo-dist, already as YAML
+    pub setup_steps: Option<String>,
+    /// whether any local task builds its targets with a cargo wrapper (like cross)
+    pub build_tools: bool,
+    /// whether any local task runs on a list of runner labels (instead of just one)
+    pub runner_label_lists: bool,
+    /// whether local tasks have names to show in the Github Actions UI
+    pub build_job_names: bool,
+    /// whether any local task installs system packages before building
+    pub install_dependencies: bool,
+    /// whether the local tasks cache their builds with rust-cache
+    pub cache: bool,
+    /// the prefix-key to give rust-cache, if any
+    pub cache_prefix_key: Option<String>,
+    /// the extra key to give rust-cache (an expression hashing cache-key-files), if any
+    pub cache_key: Option<String>,
+    /// whether this is a reusable workflow (`on: workflow_call`) instead of running on tag pushes
+    pub reusable_workflow: bool,
+    /// whether the workflow can also be run by hand (`on: workflow_dispatch`)
+    pub workflow_dispatch: bool,
+    /// the composite build action to generate alongside the workflow, if any
+    pub build_action: Option<GithubBuildAction>,
+    /// the Github Actions the workflow uses
+    pub actions: GithubActions,
+    /// the name that publish jobs commit as
+    pub git_author_name: String,
+    /// the email that publish jobs commit as
+    pub git_author_email: String,
+    /// whether to delete a prerelease's existing assets before uploading new ones
+    pub prune_prerelease_assets: bool,
+    /// extra condition (`&& github.repository == '...'`) on everything that publishes, so that
+    /// forks don't publish (empty if they're allowed to)
+    pub publishing_repo_guard: String,
+    /// the CARGO_TARGET_DIR to build in, if not the default
+    pub cargo_target_dir: Option<String>,
+    /// whether to delete unneeded preinstalled SDKs on Linux runners before building
+    pub free_disk_space: bool,
+    /// whether to create a draft release in the plan job that build jobs upload to
+    pub early_draft_release: bool,
+    /// whether to sign every artifact with cosign before uploading it
+    pub cosign: bool,
+    /// whether to generate build provenance attestations for every artifact
+    pub attestations: bool,
+    /// the permissions each job's GITHUB_TOKEN gets, by job name (then scope)
+    pub permissions: SortedMap<String, SortedMap<String, String>>,
+    /// environment variables for the whole workflow
+    pub env: SortedMap<String, String>,
+    /// whether any local task sets environment variables for its targets
+    pub target_env: bool,
+    /// secrets that local tasks' environment variables reference
+    pub target_env_secrets: SortedSet<String>,
+    /// step that publish jobs run to fetch secrets, if any, already as YAML
+    pub fetch_secrets_step: Option<String>,
     /// whether to ignore on-disk changes to the configuration
     pub allow_dirty: bool,
+    /// whether deprecated runners are an error instead of a warning
+    pub deny_deprecated_runners: bool,
+    /// Everything we warned about while computing this, for library users to show
+    /// in their own UI (these are also logged)
+    #[serde(skip)]
+    pub warnings: Vec<GithubCiWarning>,
+    /// how many minutes each kind of job may run before it's cancelled
+    pub timeout_minutes: JobTimeouts,
+    /// where jobs hand off artifacts, if not Github's artifact storage
+    pub artifact_store: Option<GithubArtifactStore>,
+    /// the secrets to Authenticode-sign Windows artifacts with, if enabled
+    pub windows_signing: Option<GithubWindowsSigning>,
+    /// whether to record where each artifact was built in the published dist-manifest.json
+    pub manifest_provenance: bool,
+}
+
+/// Something questionable [`GithubCiInfo::new`][] noticed about the config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GithubCiWarning {
+    /// A runner the config picked uses an image that's end-of-life (or soon will be)
+    ///
+    /// This is only logged when the CI is written, and is an error instead
+    /// if deny-deprecated-runners is set.
+    DeprecatedRunner {
+        /// The deprecated runner
+        runner: String,
+        /// What to use instead
+        replacement: String,
+    },
+    /// merge-tasks put several macos targets on one runner, which will bottleneck the release
+    MacosBottleneck {
+        /// The runner the targets share
+        runner: String,
+        /// The macos targets
+        targets: Vec<TargetTriple>,
+    },
+    /// arm64 macos targets will be cross-compiled on an Intel macos runner
+    MacosCrossCompile {
+        /// The Intel runner
+        runner: String,
+        /// The arm64 macos targets
+        targets: Vec<TargetTriple>,
+    },
+    /// A per-target setting has an entry for a target that nothing builds
+    UnbuiltTarget {
+        /// The setting with the entry (like `"dependency-overrides"`)
+        setting: String,
+        /// The target nothing builds
+        target: TargetTriple,
+    },
+    /// Some other config is ignored or won't do what it looks like it will
+    Config(String),
+}
+
+impl std::fmt::Display for GithubCiWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubCiWarning::DeprecatedRunner {
+                runner,
+                replacement,
+            } => {
+                write!(f, "Github CI will use the deprecated runner image {runner}, consider using {replacement} instead")
+            }
+            GithubCiWarning::MacosBottleneck { runner, targets } => {
+                write!(f, "merge-tasks = true will build {} on the same {runner} runner one after the other, which will bottleneck your release on macos builds (unset merge-tasks to give each target its own runner)", targets.iter().join(", "))
+            }
+            GithubCiWarning::MacosCrossCompile { runner, targets } => {
+                write!(f, "{} will be cross-compiled on the Intel macos runner {runner} (rustup adds the target, but build scripts and C dependencies have to support cross-compiling; consider giving them an arm64 runner like {GITHUB_MACOS_ARM64_RUNNER} with github-custom-runners)", targets.iter().join(", "))
+            }
+            GithubCiWarning::UnbuiltTarget { setting, target } => {
+                write!(f, "{setting} has an entry for {target}, but nothing is being built for that target")
+            }
+            GithubCiWarning::Config(message) => message.fmt(f),
+        }
+    }
+}
+
+/// Log a warning and keep it for [`GithubCiInfo::warnings`][]
+fn push_warning(warnings: &mut Vec<GithubCiWarning>, warning: GithubCiWarning) {
+    warn!("{warning}");
+    warnings.push(warning);
+}
+
+/// Info for the composite Github Action that builds with cargo-dist
+#[derive(Debug, Serialize)]
+pub struct GithubBuildAction {
+    /// expression to use for installing cargo-dist on linux runners
+    pub install_dist_linux: String,
+    /// expression to use for installing cargo-dist on x64 macos runners
+    pub install_dist_macos: String,
+    /// expression to use for installing cargo-dist on arm64 macos runners
+    pub install_dist_macos_arm64: String,
+    /// expression to use for installing cargo-dist on windows runners
+    pub install_dist_windows: String,
+}
+
+/// The Github Actions that Github CI uses, as `owner/name@version`
+///
+/// These have github-action-renames applied, so they might not be the usual actions.
+/// Actions in github-action-pins are `owner/name@sha # version` instead, the format
+/// Dependabot understands when it updates pinned actions.
+#[derive(Debug, Serialize)]
+pub struct GithubActions {
+    /// actions/checkout
+    pub checkout: String,
+    /// actions/upload-artifact
+    pub upload_artifact: String,
+    /// actions/download-artifact
+    pub download_artifact: String,
+    /// swatinem/rust-cache
+    pub rust_cache: String,
+    /// actions/upload-pages-artifact
+    pub upload_pages_artifact: String,
+    /// actions/deploy-pages
+    pub deploy_pages: String,
+    /// ncipollo/release-action
+    pub release_action: String,
+    /// sigstore/cosign-installer
+    pub cosign_installer: String,
+    /// actions/attest-build-provenance
+    pub attest_build_provenance: String,
+}
+
+impl GithubActions {
+    /// Get the actions to use, with the given renames and pins applied
+    fn new(renames: &SortedMap<String, String>, pins: &SortedMap<String, String>) -> Self {
+        let action = |name: &str, version: &str| -> String {
+            let renamed = rename_github_action(renames, name);
+            if let Some(sha) = pins.get(name) {
+                format!("{renamed}@{sha} # {version}")
+            } else {
+                format!("{renamed}@{version}")
+            }
+        };
+        GithubActions {
+            checkout: action("actions/checkout", "v3"),
+            upload_artifact: action("actions/upload-artifact", "v3"),
+            download_artifact: action("actions/download-artifact", "v3"),
+            rust_cache: action("swatinem/rust-cache", "v2"),
+            upload_pages_artifact: action("actions/upload-pages-artifact", "v2"),
+            deploy_pages: action("actions/deploy-pages", "v2"),
+            release_action: action("ncipollo/release-action", "v1"),
+            cosign_installer: action("sigstore/cosign-installer", "v3"),
+            attest_build_provenance: action("actions/attest-build-provenance", "v1"),
+        }
+    }
+}
+
+/// The job that runs the tests once before Github CI builds anything
+#[derive(Debug, Serialize)]
+pub struct GithubTestTask {
+    /// the github runner
+    pub runner: String,
+    /// expression to run to install the system packages the tests need, if any
+    pub install_dependencies: Option<String>,
+    /// the command that runs the tests
+    pub test_command: String,
+}
+
+/// How each build task runs its share of the tests when they're sharded
+///
+/// Every task gets an entry in the build matrix per shard, and only shard 0
+/// builds and uploads the task's artifacts.
+#[derive(Debug, Serialize)]
+pub struct GithubTestShards {
+    /// how many shards each build task's tests are split into
+    pub count: u32,
+    /// the command that installs cargo-nextest (which does the splitting)
+    pub install_nextest: String,
+    /// the command that runs a shard's tests, using `CARGO_DIST_SHARD` and `CARGO_DIST_SHARD_COUNT`
+    pub test_command: String,
+}
+
+/// A Homebrew tap the Github CI publishes to
+#[derive(Debug, Serialize)]
+pub struct GithubHomebrewTap {
+    /// name of the job that publishes to it
+    pub job_name: String,
+    /// the tap's repo, in owner/name format
+    pub repo: String,
+    /// whether it gets a formula or a cask
+    pub kind: String,
+    /// the name of the secret with the token to push to it with
+    pub token_secret: String,
+}
+
+/// One of the workflow files the Github CI is generated as
+#[derive(Debug, Serialize)]
+pub struct GithubWorkflow {
+    /// the file name, in .github/workflows/
+    pub file: String,
+    /// the name of the workflow
+    pub name: String,
+    /// the app it releases, if it was split off for just one app
+    pub app_name: Option<String>,
+    /// the prefixes of the tags that trigger it (empty if any version-like tag does)
+    pub tag_prefixes: Vec<String>,
+    /// patterns for the tags that trigger it
+    pub tag_patterns: Vec<String>,
+    /// whether pull requests trigger it
+    pub pull_request: bool,
+}
+
+/// What a workflow file gets rendered with: all the CI info, plus which workflow it is
+#[derive(Serialize)]
+struct GithubWorkflowContext<'a> {
+    #[serde(flatten)]
+    info: &'a GithubCiInfo,
+    workflow: &'a GithubWorkflow,
+}
+
+/// A cargo registry the Github CI publishes crates to
+#[derive(Debug, Serialize)]
+pub struct GithubCargoRegistry {
+    /// name of the job that publishes to it
+    pub job_name: String,
+    /// how to describe it in the workflow ("crates.io" or "the <name> registry")
+    pub description: String,
+    /// the registry to pass to `cargo publish --registry` (None for crates.io)
+    pub registry: Option<String>,
+    /// the environment variable cargo reads the token from
+    pub token_env: String,
+    /// the secret with the token
+    pub token_secret: String,
+    /// the environment variable cargo reads the index from, and the index, if we set it
+    pub index: Option<(String, String)>,
+}
+
+/// Commands that hand artifacts between jobs somewhere other than Github's
+/// artifact storage (see [`ArtifactTransport`][])
+#[derive(Debug, Serialize)]
+pub struct GithubArtifactStore {
+    /// bash that stores every path listed (one per line) in `$ARTIFACT_PATHS`
+    pub upload: String,
+    /// bash that fetches every stored artifact into the `$ARTIFACT_DEST` dir
+    pub download: String,
+}
+
+impl GithubArtifactStore {
+    /// Get the commands for a transport (None for Github's artifact storage)
+    fn new(transport: &ArtifactTransport) -> Option<Self> {
+        // Every job in a run shares a dir, and runs never see each other's artifacts
+        let (store, fetch) = match transport {
+            ArtifactTransport::Github => return None,
+            ArtifactTransport::S3 { bucket, prefix } => {
+                let prefix = prefix
+                    .as_deref()
+                    .map(|prefix| prefix.trim_matches('/'))
+                    .filter(|prefix| !prefix.is_empty())
+                    .map(|prefix| format!("{prefix}/"))
+                    .unwrap_or_default();
+                let url = format!("s3://{bucket}/{prefix}$GITHUB_RUN_ID");
+                (
+                    format!("aws s3 cp \"$path\" \"{url}/\""),
+                    format!("aws s3 cp --recursive \"{url}/\" \"$ARTIFACT_DEST/\""),
+                )
+            }
+            ArtifactTransport::Path { path } => {
+                let dir = format!("{}/$GITHUB_RUN_ID", path.trim_end_matches('/'));
+                (
+                    format!("mkdir -p \"{dir}\" && cp \"$path\" \"{dir}/\""),
+                    format!("cp -R \"{dir}/.\" \"$ARTIFACT_DEST/\""),
+                )
+            }
+        };
+        Some(Self {
+            upload: format!("echo \"$ARTIFACT_PATHS\" | while IFS= read -r path; do if [ -n \"$path\" ]; then {store}; fi; done"),
+            download: format!("mkdir -p \"$ARTIFACT_DEST\" && {fetch}"),
+        })
+    }
+}
+
+/// The Github secrets that Windows build tasks Authenticode-sign their artifacts with
+#[derive(Debug, Serialize)]
+pub struct GithubWindowsSigning {
+    /// name of the secret holding the base64-encoded .pfx certificate
+    pub certificate_secret: String,
+    /// name of the secret holding the certificate's password
+    pub password_secret: String,
+}
+
+/// A record of exactly what Github CI builds for an announcement (build-matrix.json)
+#[derive(Debug, Serialize)]
+pub struct GithubBuildMatrixRecord<'a> {
+    /// Version of cargo-dist that CI installs
+    pub cargo_dist_version: &'a str,
+    /// Version of rust toolchain that CI installs (deprecated)
+    pub rust_version: Option<&'a str>,
+    /// The tag being announced
+    pub announcement_tag: Option<&'a str>,
+    /// The apps being released
+    pub releases: Vec<GithubBuildMatrixRelease<'a>>,
+    /// The task that builds global artifacts
+    pub global_task: Option<&'a GithubMatrixEntry>,
+    /// The tasks that build local artifacts
+    pub artifacts_matrix: &'a GithubMatrix,
+}
+
+/// An app being released, as recorded in a [`GithubBuildMatrixRecord`][]
+#[derive(Debug, Serialize)]
+pub struct GithubBuildMatrixRelease<'a> {
+    /// The name of the app
+    pub app_name: &'a str,
+    /// The version of the app
+    pub app_version: String,
 }
 
 impl GithubCiInfo {
     /// Compute the Github CI stuff
-    pub fn new(dist: &DistGraph) -> GithubCiInfo {
+    pub fn new(dist: &DistGraph) -> DistResult<GithubCiInfo> {
         // Legacy deprecated support
         let rust_version = dist.desired_rust_toolchain.clone();
 
@@ -60,120 +458,1227 @@ impl GithubCiInfo {
             .unwrap_or(&self_dist_version);
         let fail_fast = dist.fail_fast;
         let create_release = dist.create_release;
+        let release_notes_table = dist.release_notes_table;
+        let concurrency = concurrency_for(dist);
+        let setup_steps =
+            (!dist.github_setup_steps.is_empty()).then(|| steps_to_yaml(&dist.github_setup_steps));
+        let deny_deprecated_runners = dist.deny_deprecated_runners;
+        let mut warnings = vec![];
+        let early_draft_release = dist.early_draft_release && create_release;
+        if dist.early_draft_release && !create_release {
+            push_warning(&mut warnings, GithubCiWarning::Config("early-draft-release is set, but create-release = false means there's already a draft, so it's being ignored".to_owned()));
+        }
+        let publishing_repo = if dist.skip_forks {
+            if dist.github_repo.is_none() {
+                push_warning(&mut warnings, GithubCiWarning::Config("skip-forks is set, but we don't know your Github repository, so forks will still try to publish".to_owned()));
+            }
+            dist.github_repo.as_deref()
+        } else {
+            None
+        };
+        let publishing_repo_guard = publishing_repo
+            .map(|repo| format!(" && github.repository == '{repo}'"))
+            .unwrap_or_default();
+        let fetch_secrets_step = dist
+            .fetch_secrets_command
+            .as_deref()
+            .map(fetch_secrets_step);
 
         // Figure out what builds we need to do
-        let mut needs_global_build = false;
-        let mut local_targets = SortedSet::new();
-        for release in &dist.releases {
-            if !release.global_artifacts.is_empty() {
-                needs_global_build = true;
-            }
-            local_targets.extend(release.targets.iter());
+        let local_targets = dist
+            .releases
+            .iter()
+            .flat_map(|release| release.targets.iter())
+            .collect::<SortedSet<_>>();
+        let needs_global_build = needs_global_build(dist, &mut warnings);
+        // Otherwise we'd generate a workflow that never builds anything (but releases
+        // of libraries legitimately have nothing to build)
+        let has_bins = dist.releases.iter().any(|release| !release.bins.is_empty());
+        if has_bins && local_targets.is_empty() && !needs_global_build {
+            return Err(DistError::GithubCiNothingToBuild);
         }
 
-        // Get the platform-specific installation methods
-        let install_dist_sh = super::install_dist_sh_for_version(dist_version);
-        let install_dist_ps1 = super::install_dist_ps1_for_version(dist_version);
+        // Hosts with a pinned archive of cargo-dist install from that instead
+        let mut install_dist_for_host = SortedMap::new();
+        let mut install_dist_on = |host: &str| -> String {
+            install_dist_for_host
+                .entry(host.to_owned())
+                .or_insert_with(|| {
+                    install_dist_for_github_host(
+                        host,
+                        dist_version,
+                        &dist.install_dist_methods,
+                        &dist.cargo_dist_archives,
+                    )
+                })
+                .clone()
+        };
+        // The plan job (and the global task) just use the linux runner's method
+        let install_dist_linux = install_dist_on(github_runner_host(GITHUB_LINUX_RUNNER));
+        // The build action could run anywhere, so it needs every runner's method
+        let build_action = dist.github_build_action.then(|| GithubBuildAction {
+            install_dist_linux: install_dist_linux.clone(),
+            install_dist_macos: install_dist_on(github_runner_host(GITHUB_MACOS_RUNNER)),
+            install_dist_macos_arm64: install_dist_on(github_runner_host(
+                GITHUB_MACOS_ARM64_RUNNER,
+            )),
+            install_dist_windows: install_dist_on(github_runner_host(GITHUB_WINDOWS_RUNNER)),
+        });
 
-        // Build up the task matrix for building Artifacts
-        let mut tasks = vec![];
+        let (global_dist_args, global_task_optional, optional_installers) =
+            split_optional_installers(dist, needs_global_build, &mut warnings);
 
         // If we have Global Artifacts, we need one task for that. If we've done a Good Job
         // then these artifacts should be possible to build on *any* platform. Linux is usually
         // fast/cheap, so that's a reasonable choice.s
         let global_task = if needs_global_build {
-            Some(GithubMatrixEntry {
-                runner: Some(GITHUB_LINUX_RUNNER.into()),
-                dist_args: Some("--artifacts=global".into()),
-                install_dist: Some(install_dist_sh.clone()),
-            })
+            Some(
+                GithubMatrixEntry::builder()
+                    .runner(GITHUB_LINUX_RUNNER)
+                    .dist_args(global_dist_args)
+                    .install_dist(install_dist_linux.clone())
+                    .shell(github_shell_for_runner(GITHUB_LINUX_RUNNER))
+                    .build()?,
+            )
         } else {
             None
         };
 
+        let (test_task, test_shards) = test_jobs(dist, &mut warnings);
+
         let pr_run_mode = dist.pr_run_mode.clone();
+        let pull_request = !matches!(pr_run_mode, cargo_dist_schema::PrRunMode::Skip);
         let allow_dirty = dist.allow_dirty.contains(&CiStyle::Github);
 
-        let tap = dist.tap.clone();
+        let homebrew_taps = homebrew_taps(dist);
         let publish_jobs = dist.publish_jobs.iter().map(|j| j.to_string()).collect();
+        let publish_job_conditions = dist
+            .publish_job_conditions
+            .iter()
+            .map(|(job, condition)| (job.to_string(), guard_from_pull_requests(condition)))
+            .collect();
+        let publish_job_secrets = publish_job_secrets(dist);
+        let cargo_registries = cargo_registries(dist, &publish_job_secrets);
 
-        // Figure out what Local Artifact tasks we need
-        let local_runs = if dist.merge_tasks {
-            distribute_targets_to_runners_merged(local_targets)
-        } else {
-            distribute_targets_to_runners_split(local_targets)
-        };
-        for (runner, targets) in local_runs {
-            use std::fmt::Write;
-            let install_dist =
-                install_dist_for_github_runner(runner, &install_dist_sh, &install_dist_ps1);
-            let mut dist_args = String::from("--artifacts=local");
-            for target in targets {
-                write!(dist_args, " --target={target}").unwrap();
-            }
-            tasks.push(GithubMatrixEntry {
-                runner: Some(runner.to_owned()),
-                dist_args: Some(dist_args),
-                install_dist: Some(install_dist.to_owned()),
-            });
+        check_per_target_settings(dist, &local_targets, &mut warnings);
+        let builds_windows = local_targets
+            .iter()
+            .any(|target| target.contains("windows"));
+
+        let tasks = local_build_tasks(
+            dist,
+            local_targets,
+            test_shards.as_ref(),
+            &mut install_dist_on,
+            &mut warnings,
+        )?;
+        let build_tools = tasks.iter().any(|task| task.build_tool.is_some());
+        let build_job_names = dist.github_build_job_name.is_some();
+        let runner_label_lists = tasks.iter().any(|task| task.runner_labels.is_some());
+        let install_dependencies = tasks.iter().any(|task| task.install_dependencies.is_some());
+        let target_env = tasks.iter().any(|task| task.env.is_some());
+        // Matrix entries aren't expressions, so the workflow has to hand these over itself
+        let target_env_secrets = tasks
+            .iter()
+            .filter_map(|task| task.env.as_ref())
+            .flat_map(|env| env.values())
+            .flat_map(|value| github_secret_references(value))
+            .map(|secret| secret.to_owned())
+            .collect::<SortedSet<_>>();
+
+        let workflows = workflows(dist, pull_request, &mut warnings);
+
+        // Reusable workflows get their tag from their caller, so they can't be dispatched
+        let workflow_dispatch = dist.github_workflow_dispatch && !dist.github_reusable_workflow;
+        if dist.github_workflow_dispatch && dist.github_reusable_workflow {
+            push_warning(&mut warnings, GithubCiWarning::Config("github-workflow-dispatch = true is ignored because github-reusable-workflow = true (run the workflow that calls it by hand instead)".to_owned()));
         }
 
-        GithubCiInfo {
+        let cache_key = cache_key(dist, &mut warnings);
+        let actions = github_actions(dist, &mut warnings);
+        let artifact_uploads = artifact_uploads(dist, &mut warnings);
+        let artifact_store = GithubArtifactStore::new(&dist.artifact_transport);
+        if artifact_store.is_some() && artifact_uploads.values().any(|inputs| !inputs.is_empty()) {
+            push_warning(&mut warnings, GithubCiWarning::Config("artifact-uploads and artifact-upload-overrides only apply to Github's artifact storage, so they're ignored with this artifact-transport".to_owned()));
+        }
+        let windows_signing = windows_signing(dist, builds_windows, &mut warnings);
+
+        let mut info = GithubCiInfo {
             rust_version,
-            install_dist_sh,
-            install_dist_ps1,
+            workflow_name: dist.workflow_name.clone(),
+            workflows,
+            dist_version: dist_version.to_string(),
+            install_dist_sh: install_dist_linux,
+            install_dist_ps1: super::install_dist_for_os(
+                dist_version,
+                RunnerOs::Windows,
+                &dist.install_dist_methods,
+            ),
             fail_fast,
-            tap,
+            homebrew_taps,
             publish_jobs,
+            publish_job_conditions,
+            publish_job_secrets,
+            crates_io_packages: dist.crates_io_packages.clone(),
+            cargo_registries,
             artifacts_matrix: GithubMatrix { include: tasks },
             pr_run_mode,
             global_task,
+            global_task_optional,
+            test_task,
+            test_shards,
+            optional_installers,
+            artifact_uploads,
             create_release,
+            release_notes_table,
+            concurrency,
+            setup_steps,
+            build_tools,
+            runner_label_lists,
+            build_job_names,
+            env: dist.github_env.clone(),
+            target_env,
+            target_env_secrets,
+            install_dependencies,
+            cache: dist.cache,
+            cache_prefix_key: dist.cache_prefix_key.clone(),
+            cache_key,
+            reusable_workflow: dist.github_reusable_workflow,
+            workflow_dispatch,
+            build_action,
+            actions,
+            git_author_name: dist.git_author_name.clone(),
+            git_author_email: dist.git_author_email.clone(),
+            prune_prerelease_assets: dist.prune_prerelease_assets,
+            publishing_repo_guard,
+            cargo_target_dir: dist.cargo_target_dir.clone(),
+            free_disk_space: dist.free_disk_space,
+            early_draft_release,
+            cosign: dist.cosign,
+            attestations: dist.github_attestations,
+            permissions: SortedMap::new(),
+            fetch_secrets_step,
             allow_dirty,
+            deny_deprecated_runners,
+            warnings: vec![],
+            timeout_minutes: dist.timeout_minutes,
+            artifact_store,
+            windows_signing,
+            manifest_provenance: dist.manifest_provenance,
+        };
+        info.permissions = info.job_permissions(dist, &mut warnings);
+        warnings.extend(info.deprecated_runner_warnings());
+        info.warnings = warnings;
+        Ok(info)
+    }
+
+    /// Work out the permissions each job needs, then apply github-permissions
+    fn job_permissions(
+        &self,
+        dist: &DistGraph,
+        warnings: &mut Vec<GithubCiWarning>,
+    ) -> SortedMap<String, SortedMap<String, String>> {
+        // Each job only gets the permissions it needs, rather than the whole
+        // workflow getting to write to everything
+        let mut permissions = SortedMap::<String, SortedMap<String, String>>::new();
+        let mut grant = |job: &str, scope: &str, level: &str| {
+            permissions
+                .entry(job.to_owned())
+                .or_default()
+                .insert(scope.to_owned(), level.to_owned());
+        };
+        let fetches_secrets = self.fetch_secrets_step.is_some();
+        // With an early draft release, everything that builds uploads to it
+        let build_access = if self.early_draft_release {
+            "write"
+        } else {
+            "read"
+        };
+        grant("plan", "contents", build_access);
+        if self.test_task.is_some() {
+            grant("test", "contents", "read");
+        }
+        grant("upload-local-artifacts", "contents", build_access);
+        if self.global_task.is_some() {
+            grant("upload-global-artifacts", "contents", build_access);
+        }
+        if !self.optional_installers.is_empty() {
+            grant("upload-optional-installers", "contents", build_access);
+        }
+        grant("should-publish", "contents", "read");
+        if self.global_task.is_some() {
+            if dist.publish_jobs.contains(&PublishStyle::Homebrew) {
+                // The tap is pushed to with its own token
+                for tap in &self.homebrew_taps {
+                    grant(&tap.job_name, "contents", "read");
+                    if fetches_secrets {
+                        grant(&tap.job_name, "id-token", "write");
+                    }
+                }
+            }
+            if dist.publish_jobs.contains(&PublishStyle::Docs) {
+                grant("publish-docs", "pages", "write");
+                grant("publish-docs", "id-token", "write");
+            }
+        }
+        grant("publish-release", "contents", "write");
+        if self.cosign || self.attestations || fetches_secrets {
+            grant("publish-release", "id-token", "write");
         }
+        if self.attestations {
+            grant("publish-release", "attestations", "write");
+        }
+        for registry in &self.cargo_registries {
+            grant(&registry.job_name, "contents", "read");
+            if fetches_secrets {
+                grant(&registry.job_name, "id-token", "write");
+            }
+        }
+        for (job, scopes) in &dist.github_permissions {
+            let Some(job_permissions) = permissions.get_mut(job) else {
+                let jobs = permissions
+                    .keys()
+                    .map(|j| &**j)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                push_warning(warnings, GithubCiWarning::Config(format!("github-permissions has an entry for {job}, but the workflow has no job by that name (it should be one of {jobs})")));
+                continue;
+            };
+            for (scope, level) in scopes {
+                if !["read", "write", "none"].contains(&&**level) {
+                    push_warning(warnings, GithubCiWarning::Config(format!("github-permissions gives {job} \"{level}\" access to {scope}, but the level should be one of read, write, none")));
+                }
+                job_permissions.insert(scope.clone(), level.clone());
+            }
+        }
+        permissions
     }
 
-    fn github_ci_path(&self, dist: &DistGraph) -> camino::Utf8PathBuf {
-        let ci_dir = dist.workspace_dir.join(GITHUB_CI_DIR);
-        ci_dir.join(GITHUB_CI_FILE)
+    /// Find the runners we selected that are known to be deprecated
+    ///
+    /// These only get logged (or become errors) when the CI is written. Our default
+    /// runners count too, since github-custom-runners can replace them.
+    fn deprecated_runner_warnings(&self) -> Vec<GithubCiWarning> {
+        self.global_task
+            .iter()
+            .chain(&self.artifacts_matrix.include)
+            .flat_map(|task| task.runs_on())
+            .map(|runner| &**runner)
+            .collect::<SortedSet<_>>()
+            .into_iter()
+            .filter_map(|runner| {
+                let replacement = deprecated_github_runner_replacement(runner)?;
+                Some(GithubCiWarning::DeprecatedRunner {
+                    runner: runner.to_owned(),
+                    replacement: replacement.to_owned(),
+                })
+            })
+            .collect()
     }
 
-    /// Generate the requested configuration and returns it as a string.
-    pub fn generate_github_ci(&self, dist: &DistGraph) -> DistResult<String> {
+    /// Get a record of exactly what this CI will build for the current announcement
+    pub fn build_matrix_record<'a>(&'a self, dist: &'a DistGraph) -> GithubBuildMatrixRecord<'a> {
+        GithubBuildMatrixRecord {
+            cargo_dist_version: &self.dist_version,
+            rust_version: self.rust_version.as_deref(),
+            announcement_tag: dist.announcement_tag.as_deref(),
+            releases: dist
+                .releases
+                .iter()
+                .map(|release| GithubBuildMatrixRelease {
+                    app_name: &release.app_name,
+                    app_version: release.version.to_string(),
+                })
+                .collect(),
+            global_task: self.global_task.as_ref(),
+            artifacts_matrix: &self.artifacts_matrix,
+        }
+    }
+
+    fn github_ci_path(&self, output_dir: &Utf8Path, workflow: &GithubWorkflow) -> Utf8PathBuf {
+        let ci_dir = output_dir.join(GITHUB_CI_DIR);
+        ci_dir.join(&workflow.file)
+    }
+
+    fn github_build_action_path(&self, output_dir: &Utf8Path) -> Utf8PathBuf {
+        let action_dir = output_dir.join(GITHUB_BUILD_ACTION_DIR);
+        action_dir.join(GITHUB_BUILD_ACTION_FILE)
+    }
+
+    /// Generate the composite build action (if enabled) and return it as a string.
+    pub fn generate_github_build_action(&self, dist: &DistGraph) -> DistResult<Option<String>> {
+        if self.build_action.is_none() {
+            return Ok(None);
+        }
         let rendered = dist
             .templates
-            .render_file_to_clean_string(TEMPLATE_CI_GITHUB, self)?;
+            .render_file_to_clean_string(TEMPLATE_CI_GITHUB_BUILD_ACTION, self)?;
+        Ok(Some(rendered))
+    }
+
+    /// Generate the requested configuration for one of the workflows and returns it as a string.
+    ///
+    /// Any post_processors get to edit the result (in order) before it's returned.
+    pub fn generate_github_ci(
+        &self,
+        dist: &DistGraph,
+        workflow: &GithubWorkflow,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<String, miette::Report> {
+        let context = GithubWorkflowContext {
+            info: self,
+            workflow,
+        };
+        let mut rendered = dist
+            .templates
+            .render_file_to_clean_string(TEMPLATE_CI_GITHUB, &context)?;
+        for post_processor in post_processors {
+            rendered = post_processor.post_process(rendered)?;
+        }
+        validate_github_ci(&rendered)?;
 
         Ok(rendered)
     }
 
-    /// Write release.yml to disk
-    pub fn write_to_disk(&self, dist: &DistGraph) -> Result<(), miette::Report> {
-        let ci_file = self.github_ci_path(dist);
-        let rendered = self.generate_github_ci(dist)?;
+    /// Write release.yml (or each app's workflow, if they're split) and the build action
+    /// (if enabled) to disk, rooted at output_dir (usually the workspace dir)
+    pub fn write_to_disk(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<GeneratedFile>, miette::Report> {
+        self.check_deprecated_runners()?;
+        let mut generated = vec![];
+        for workflow in &self.workflows {
+            let ci_file = self.github_ci_path(output_dir, workflow);
+            let rendered = self.generate_github_ci(dist, workflow, post_processors)?;
+
+            LocalAsset::write_new_all(&rendered, &ci_file)?;
+            info!(path = %ci_file, "generated Github CI");
 
-        LocalAsset::write_new_all(&rendered, &ci_file)?;
-        eprintln!("generated Github CI to {}", ci_file);
+            generated.push(GeneratedFile {
+                desc: "Github CI".to_owned(),
+                path: ci_file.to_string(),
+            });
+        }
+
+        if let Some(rendered) = self.generate_github_build_action(dist)? {
+            let action_file = self.github_build_action_path(output_dir);
+            LocalAsset::write_new_all(&rendered, &action_file)?;
+            info!(path = %action_file, "generated Github build action");
+            generated.push(GeneratedFile {
+                desc: "Github build action".to_owned(),
+                path: action_file.to_string(),
+            });
+        }
 
+        Ok(generated)
+    }
+
+    /// Check whether any of the runners we selected are known to be deprecated
+    ///
+    /// This warns by default, but errors if deny-deprecated-runners is set.
+    fn check_deprecated_runners(&self) -> DistResult<()> {
+        for warning in &self.warnings {
+            let GithubCiWarning::DeprecatedRunner {
+                runner,
+                replacement,
+            } = warning
+            else {
+                continue;
+            };
+            if self.deny_deprecated_runners {
+                return Err(DistError::DeprecatedGithubRunner {
+                    runner: runner.to_owned(),
+                    replacement: replacement.to_owned(),
+                });
+            }
+            warn!("{warning}");
+        }
         Ok(())
     }
 
     /// Check whether the new configuration differs from the config on disk
-    /// writhout actually writing the result.
-    pub fn check_github_ci(&self, dist: &DistGraph) -> DistResult<()> {
-        let ci_file = self.github_ci_path(dist);
-
-        let rendered = self.generate_github_ci(dist)?;
-        // FIXME: should we catch all errors, or only LocalAssetNotFound?
-        let existing = LocalAsset::load_string(&ci_file).unwrap_or("".to_owned());
-        if rendered != existing && !self.allow_dirty {
-            Err(DistError::CheckFileMismatch {
-                file: ci_file.to_string(),
-            })
+    /// without actually writing the result.
+    ///
+    /// The config on disk is looked up relative to output_dir (usually the workspace dir).
+    pub fn check_github_ci(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<(), miette::Report> {
+        let stale = self.stale_github_files(dist, output_dir, post_processors)?;
+        crate::check_stale_files(stale)?;
+        Ok(())
+    }
+
+    /// Get every file we'd generate that differs from what's on disk
+    /// (empty if allow-dirty is set for Github CI).
+    ///
+    /// The files on disk are looked up relative to output_dir (usually the workspace dir).
+    pub fn stale_github_files(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<StaleFile>, miette::Report> {
+        let mut expected = vec![];
+        for workflow in &self.workflows {
+            expected.push((
+                self.github_ci_path(output_dir, workflow),
+                self.generate_github_ci(dist, workflow, post_processors)?,
+            ));
+        }
+        if let Some(rendered) = self.generate_github_build_action(dist)? {
+            expected.push((self.github_build_action_path(output_dir), rendered));
+        }
+        if self.allow_dirty {
+            return Ok(vec![]);
+        }
+
+        Ok(expected
+            .into_iter()
+            .filter_map(|(file, rendered)| StaleFile::check(&file, &rendered))
+            .collect())
+    }
+}
+
+/// Pick the concurrency mode of the release workflow
+///
+/// Queueing up runs is always safe, so do it by default. Reusable workflows are
+/// the exception: they see their caller's github.workflow, so if the caller has
+/// the same concurrency group the two would deadlock waiting on each other
+fn concurrency_for(dist: &DistGraph) -> Option<String> {
+    match dist.concurrency {
+        Some(ConcurrencyMode::Off) => None,
+        Some(mode) => Some(mode),
+        None if dist.github_reusable_workflow => None,
+        None => Some(ConcurrencyMode::Queue),
+    }
+    .map(|c| c.to_string())
+}
+
+/// Whether the CI needs a task to build global artifacts (per global-build)
+fn needs_global_build(dist: &DistGraph, warnings: &mut Vec<GithubCiWarning>) -> bool {
+    let has_global_artifacts = dist
+        .releases
+        .iter()
+        .any(|release| !release.global_artifacts.is_empty());
+    // The docs get built by the global task too
+    let needs_global_build =
+        has_global_artifacts || dist.publish_jobs.contains(&PublishStyle::Docs);
+    match dist.global_build {
+        GlobalBuildMode::Auto => needs_global_build,
+        GlobalBuildMode::Always => true,
+        GlobalBuildMode::Never => {
+            if needs_global_build {
+                push_warning(warnings, GithubCiWarning::Config("global-build = false was set, but some releases have global artifacts (like installers) that won't get built".to_owned()));
+            }
+            false
+        }
+    }
+}
+
+/// Split the installers between the global task and the optional installers' tasks
+///
+/// Returns the global task's dist args, whether the global task is itself optional,
+/// and the installers that get tasks of their own.
+///
+/// Optional installers get their own tasks that are allowed to fail, so the
+/// global task only builds the required ones. This is decided by the config
+/// (not what this run is building) so that it's the same for every invocation.
+fn split_optional_installers(
+    dist: &DistGraph,
+    needs_global_build: bool,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> (String, bool, Vec<String>) {
+    for installer in &dist.optional_installers {
+        if !dist.all_installers.contains(installer) {
+            push_warning(
+                warnings,
+                GithubCiWarning::Config(format!(
+                    "optional-installers has {installer}, but no package builds that installer"
+                )),
+            );
+        }
+    }
+    if dist.optional_installers.contains(&InstallerStyle::Homebrew)
+        && dist.publish_jobs.contains(&PublishStyle::Homebrew)
+    {
+        push_warning(warnings, GithubCiWarning::Config("the homebrew installer is optional, but the homebrew publish job will still fail without it".to_owned()));
+    }
+    let (mut optional_installers, required_installers): (Vec<&InstallerStyle>, Vec<_>) = dist
+        .all_installers
+        .iter()
+        .partition(|installer| dist.optional_installers.contains(installer));
+    if !needs_global_build {
+        optional_installers.clear();
+    }
+    let mut global_dist_args = String::from("--artifacts=global");
+    let mut global_task_optional = false;
+    if !optional_installers.is_empty() {
+        if required_installers.is_empty() {
+            // There's no way to ask for "no installers", so the global task
+            // builds the optional ones itself, and gets to fail instead
+            global_task_optional = true;
+            optional_installers.clear();
         } else {
-            Ok(())
+            use std::fmt::Write;
+            for installer in required_installers {
+                write!(global_dist_args, " --installer={installer}").unwrap();
+            }
+        }
+    }
+    let optional_installers = optional_installers.iter().map(|i| i.to_string()).collect();
+    (global_dist_args, global_task_optional, optional_installers)
+}
+
+/// Work out how run-tests runs: in a test job of its own, or sharded across the build tasks
+fn test_jobs(
+    dist: &DistGraph,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> (Option<GithubTestTask>, Option<GithubTestShards>) {
+    let lock_flag = dist
+        .cargo_lock_mode
+        .map(|lock_mode| format!(" --{lock_mode}"))
+        .unwrap_or_default();
+    // Sharded tests run on every build task's runner, split up with nextest (since
+    // cargo test can't), whose partitions are 1-based
+    let test_shards = (dist.run_tests && dist.test_shards > 1).then(|| GithubTestShards {
+        count: dist.test_shards,
+        install_nextest: GITHUB_INSTALL_NEXTEST.to_owned(),
+        test_command: format!("cargo nextest run --workspace --partition count:$((CARGO_DIST_SHARD + 1))/$CARGO_DIST_SHARD_COUNT{lock_flag}"),
+    });
+    // Otherwise tests only need to pass once, so they get a single Linux runner like
+    // the global task
+    let test_task = (dist.run_tests && test_shards.is_none()).then(|| {
+        let host = TargetTriple::from(GITHUB_LINUX_HOST);
+        GithubTestTask {
+            runner: GITHUB_LINUX_RUNNER.to_owned(),
+            install_dependencies: install_dependencies_for_github_runner(
+                GITHUB_LINUX_RUNNER,
+                &[&host],
+                dist,
+            ),
+            test_command: format!("cargo test --workspace{lock_flag}"),
         }
+    });
+    if dist.test_shards > 1 && !dist.run_tests {
+        push_warning(
+            warnings,
+            GithubCiWarning::Config(
+                "test-shards is set, but run-tests isn't, so there are no tests to shard"
+                    .to_owned(),
+            ),
+        );
+    }
+    (test_task, test_shards)
+}
+
+/// Get the Homebrew taps to publish to, each with its own job
+fn homebrew_taps(dist: &DistGraph) -> Vec<GithubHomebrewTap> {
+    // The first tap of each kind keeps the plain job name, so adding more taps
+    // doesn't rename existing jobs
+    let homebrew_secret = dist
+        .publish_job_secrets
+        .get(&PublishStyle::Homebrew)
+        .map(|s| &**s)
+        .unwrap_or(default_secret_for_publish_job(PublishStyle::Homebrew));
+    let mut homebrew_taps = Vec::<GithubHomebrewTap>::new();
+    for tap in &dist.taps {
+        let kind = tap.kind.to_string();
+        let count = homebrew_taps.iter().filter(|t| t.kind == kind).count();
+        let job_name = if count == 0 {
+            format!("publish-homebrew-{kind}")
+        } else {
+            format!("publish-homebrew-{kind}-{}", count + 1)
+        };
+        homebrew_taps.push(GithubHomebrewTap {
+            job_name,
+            repo: tap.repo.clone(),
+            kind,
+            token_secret: tap
+                .token_secret
+                .clone()
+                .unwrap_or_else(|| homebrew_secret.to_owned()),
+        });
     }
+    homebrew_taps
+}
+
+/// Get the secret each publish job authenticates with (per publish-job-secrets)
+fn publish_job_secrets(dist: &DistGraph) -> SortedMap<String, String> {
+    dist.publish_jobs
+        .iter()
+        .map(|job| {
+            let secret = dist
+                .publish_job_secrets
+                .get(job)
+                .map(|s| &**s)
+                .unwrap_or(default_secret_for_publish_job(*job));
+            (job.to_string(), secret.to_owned())
+        })
+        .collect()
+}
+
+/// Get the cargo registries to publish to, each with its own job
+fn cargo_registries(
+    dist: &DistGraph,
+    publish_job_secrets: &SortedMap<String, String>,
+) -> Vec<GithubCargoRegistry> {
+    if !dist.publish_jobs.contains(&PublishStyle::CratesIo) || dist.crates_io_packages.is_empty() {
+        return vec![];
+    }
+    // crates.io keeps the plain job name and token, so listing more registries
+    // doesn't change its job
+    dist.cargo_registries
+        .iter()
+        .map(|registry| {
+            let env_prefix = registry.env_prefix();
+            if registry.is_crates_io() {
+                let token_secret = registry.token_secret.clone().unwrap_or_else(|| {
+                    publish_job_secrets[&PublishStyle::CratesIo.to_string()].clone()
+                });
+                GithubCargoRegistry {
+                    job_name: "publish-crates-io".to_owned(),
+                    description: "crates.io".to_owned(),
+                    registry: None,
+                    token_env: "CARGO_REGISTRY_TOKEN".to_owned(),
+                    token_secret,
+                    index: None,
+                }
+            } else {
+                let token_env = format!("{env_prefix}_TOKEN");
+                GithubCargoRegistry {
+                    job_name: format!("publish-crates-{}", registry.name),
+                    description: format!("the {} registry", registry.name),
+                    registry: Some(registry.name.clone()),
+                    token_secret: registry
+                        .token_secret
+                        .clone()
+                        .unwrap_or_else(|| token_env.clone()),
+                    token_env,
+                    index: registry
+                        .index
+                        .clone()
+                        .map(|index| (format!("{env_prefix}_INDEX"), index)),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Warn about per-target settings for targets we don't build, and github-target-env
+/// values that Github won't evaluate
+fn check_per_target_settings(
+    dist: &DistGraph,
+    local_targets: &SortedSet<&TargetTriple>,
+    warnings: &mut Vec<GithubCiWarning>,
+) {
+    let per_target_settings = [
+        (
+            "github-custom-runners",
+            dist.github_custom_runners.keys().collect::<Vec<_>>(),
+        ),
+        (
+            "github-fallback-runners",
+            dist.github_fallback_runners.keys().collect(),
+        ),
+        (
+            "dependency-overrides",
+            dist.dependency_overrides.keys().collect(),
+        ),
+        ("build-overrides", dist.build_overrides.keys().collect()),
+        ("github-target-env", dist.github_target_env.keys().collect()),
+        (
+            "cargo-target-config",
+            dist.cargo_target_config.keys().collect(),
+        ),
+    ];
+    for (setting, targets) in per_target_settings {
+        for target in targets {
+            if !local_targets.contains(target) {
+                push_warning(
+                    warnings,
+                    GithubCiWarning::UnbuiltTarget {
+                        setting: setting.to_owned(),
+                        target: target.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    for (target, env) in &dist.github_target_env {
+        for (name, value) in env {
+            if value.matches("${{").count() != github_secret_references(value).len() {
+                push_warning(warnings, GithubCiWarning::Config(format!("github-target-env sets {name} for {target} with an expression that isn't a secret, which won't be evaluated (only ${{{{ secrets.NAME }}}} works there, github-env can use any expression)")));
+            }
+        }
+    }
+}
+
+/// Build up the task matrix for building Local Artifacts
+///
+/// With test shards, every task gets an entry per shard.
+fn local_build_tasks(
+    dist: &DistGraph,
+    local_targets: SortedSet<&TargetTriple>,
+    test_shards: Option<&GithubTestShards>,
+    install_dist_on: &mut impl FnMut(&str) -> String,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> DistResult<Vec<GithubMatrixEntry>> {
+    let mut tasks = vec![];
+    let local_runs = if dist.merge_tasks {
+        distribute_targets_to_runners_merged(
+            local_targets,
+            &dist.github_custom_runners,
+            &dist.github_fallback_runners,
+        )?
+    } else {
+        distribute_targets_to_runners_split(
+            local_targets,
+            &dist.github_custom_runners,
+            &dist.github_fallback_runners,
+        )?
+    };
+    let local_runs = split_runs_by_build_overrides(local_runs, &dist.build_overrides);
+    for ((runner, custom_runner, fallback_runners), targets) in local_runs {
+        use std::fmt::Write;
+        // The task runs on the custom runner if there is one (then any fallbacks)
+        let mut runner_labels = match custom_runner {
+            Some(custom_runner) => custom_runner.labels().iter().map(|l| &**l).collect(),
+            None => vec![runner],
+        };
+        runner_labels.extend(fallback_runners.iter().map(|l| &**l));
+        let runner_label = runner_labels.join(", ");
+        if dist.merge_tasks {
+            let macos_targets = targets
+                .iter()
+                .filter(|t| t.contains("apple-darwin"))
+                .copied()
+                .collect::<Vec<_>>();
+            if macos_targets.len() > 1 {
+                push_warning(
+                    warnings,
+                    GithubCiWarning::MacosBottleneck {
+                        runner: runner_label.clone(),
+                        targets: macos_targets.into_iter().cloned().collect(),
+                    },
+                );
+            }
+        }
+        let cross_targets = macos_targets_cross_compiled_on_intel(
+            (runner, custom_runner, fallback_runners),
+            &targets,
+        );
+        if !cross_targets.is_empty() {
+            push_warning(
+                warnings,
+                GithubCiWarning::MacosCrossCompile {
+                    runner: runner_label.clone(),
+                    targets: cross_targets.into_iter().cloned().collect(),
+                },
+            );
+        }
+        // We don't know what a custom runner is, so trust it to be the (first) target,
+        // like we do when picking a build tool
+        let host = match custom_runner {
+            Some(_) => targets[0].as_str(),
+            None => github_runner_host(runner),
+        };
+        let install_dist = install_dist_on(host);
+        let mut dist_args = String::from("--artifacts=local");
+        for target in &targets {
+            write!(dist_args, " --target={target}").unwrap();
+        }
+        // Every target in a run has the same build-overrides (if any)
+        if let Some(build_override) = dist.build_overrides.get(targets[0]) {
+            for arg in build_override.dist_args() {
+                write!(dist_args, " {arg}").unwrap();
+            }
+        }
+        let build_tool =
+            build_tool_for_github_runner((runner, custom_runner, fallback_runners), &targets);
+        let build_command = match build_tool {
+            Some((tool, _)) => format!("{tool} build"),
+            None => GITHUB_BUILD_COMMAND.to_owned(),
+        };
+        let mut entry = GithubMatrixEntry::builder()
+            .runner(runner_labels[0])
+            .extra_runner_labels(runner_labels[1..].iter().copied())
+            .dist_args(dist_args)
+            .install_dist(install_dist)
+            .shell(github_shell_for_runner(runner))
+            .build_command(build_command)
+            .extras(dist.github_matrix_extra.clone());
+        if let Some((tool, install)) = build_tool {
+            entry = entry.build_tool(tool, install);
+        }
+        // Targets that share a runner share an environment, so the first one wins
+        let mut env = SortedMap::<&str, (&str, &TargetTriple)>::new();
+        for target in &targets {
+            for (name, value) in dist.github_target_env.get(*target).into_iter().flatten() {
+                match env.get(&**name) {
+                    Some((other, first)) if *other != value => {
+                        push_warning(warnings, GithubCiWarning::Config(format!("github-target-env sets {name} differently for {first} and {target}, but they build on the same {runner_label} runner (so {first}'s value is used)")));
+                    }
+                    Some(_) => {}
+                    None => {
+                        env.insert(name, (value, target));
+                    }
+                }
+            }
+        }
+        for (name, (value, _)) in env {
+            entry = entry.env(name, value);
+        }
+        if let Some(install) = install_dependencies_for_github_runner(runner, &targets, dist) {
+            entry = entry.install_dependencies(install);
+        }
+        if dist.cache {
+            // Tasks only share a cache if they build the same targets
+            entry = entry.cache_key(targets.iter().join("-"));
+        }
+        let job_name = dist.github_build_job_name.as_ref().map(|job_name| {
+            job_name
+                .replace("{targets}", &targets.iter().join(", "))
+                .replace("{runner}", &runner_label)
+        });
+        let Some(test_shards) = test_shards else {
+            if let Some(job_name) = job_name {
+                entry = entry.job_name(job_name);
+            }
+            tasks.push(entry.build()?);
+            continue;
+        };
+        for shard in 0..test_shards.count {
+            let mut shard_entry = entry.clone().shard(shard, test_shards.count);
+            if let Some(job_name) = &job_name {
+                shard_entry = shard_entry.job_name(format!(
+                    "{job_name} (shard {}/{})",
+                    shard + 1,
+                    test_shards.count
+                ));
+            }
+            tasks.push(shard_entry.build()?);
+        }
+    }
+    if let Some(job_name) = &dist.github_build_job_name {
+        if tasks.len() > 1 && !job_name.contains("{targets}") {
+            push_warning(warnings, GithubCiWarning::Config(format!("github-build-job-name \"{job_name}\" doesn't include {{targets}}, so build jobs may be hard to tell apart")));
+        }
+    }
+    Ok(tasks)
+}
+
+/// Work out the workflow files to generate, and the tags that trigger each
+fn workflows(
+    dist: &DistGraph,
+    pull_request: bool,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> Vec<GithubWorkflow> {
+    // Each app gets a workflow that only its own tags trigger. Pull requests check
+    // every app, so only the first workflow needs to run for them.
+    let split_workflows = dist.github_split_workflows && dist.releases.len() > 1;
+    if split_workflows && !dist.github_reusable_workflow {
+        return dist
+            .releases
+            .iter()
+            .enumerate()
+            .map(|(idx, release)| {
+                let app_name = &release.app_name;
+                GithubWorkflow {
+                    file: format!("release-{app_name}.yml"),
+                    name: format!("{} {app_name}", dist.workflow_name),
+                    app_name: Some(app_name.clone()),
+                    tag_prefixes: vec![release.tag_prefix.clone()],
+                    tag_patterns: tag_patterns_for_prefix(&release.tag_prefix),
+                    pull_request: pull_request && idx == 0,
+                }
+            })
+            .collect();
+    }
+    if split_workflows {
+        push_warning(warnings, GithubCiWarning::Config("github-split-workflows = true is ignored because github-reusable-workflow = true (a caller can't pick between several workflows)".to_owned()));
+    }
+    // Once any app has its own tag-prefix, only tags for an app trigger a release
+    // (a lone app keeps accepting any version-like tag)
+    let has_tag_prefixes = dist.releases.len() > 1
+        && dist
+            .releases
+            .iter()
+            .any(|release| release.tag_prefix != release.app_name);
+    let tag_prefixes = if has_tag_prefixes {
+        dist.releases
+            .iter()
+            .map(|release| release.tag_prefix.clone())
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+    let tag_patterns = if tag_prefixes.is_empty() {
+        vec!["**[0-9]+.[0-9]+.[0-9]+*".to_owned()]
+    } else {
+        tag_prefixes
+            .iter()
+            .flat_map(|prefix| tag_patterns_for_prefix(prefix))
+            .collect()
+    };
+    vec![GithubWorkflow {
+        file: GITHUB_CI_FILE.to_owned(),
+        name: dist.workflow_name.clone(),
+        app_name: None,
+        tag_prefixes,
+        tag_patterns,
+        pull_request,
+    }]
+}
+
+/// Get the expression for the part of the cache key that cache-key-files adds
+fn cache_key(dist: &DistGraph, warnings: &mut Vec<GithubCiWarning>) -> Option<String> {
+    if !dist.cache && (dist.cache_prefix_key.is_some() || !dist.cache_key_files.is_empty()) {
+        push_warning(
+            warnings,
+            GithubCiWarning::Config(
+                "cache-prefix-key and cache-key-files are ignored because cache = false".to_owned(),
+            ),
+        );
+    }
+
+    // Github expressions escape ' in strings by doubling it
+    (!dist.cache_key_files.is_empty()).then(|| {
+        let files = dist
+            .cache_key_files
+            .iter()
+            .map(|file| format!("'{}'", file.replace('\'', "''")))
+            .join(", ");
+        format!("${{{{ hashFiles({files}) }}}}")
+    })
+}
+
+/// Get the actions the workflow uses, after github-action-renames and github-action-pins
+fn github_actions(dist: &DistGraph, warnings: &mut Vec<GithubCiWarning>) -> GithubActions {
+    for from in dist.github_action_renames.keys() {
+        let used = GITHUB_ACTIONS.iter().any(|action| {
+            action == from || action.split_once('/').map(|(owner, _)| owner) == Some(from)
+        });
+        if !used {
+            push_warning(warnings, GithubCiWarning::Config(format!("github-action-renames has an entry for {from}, but Github CI doesn't use that action")));
+        }
+    }
+    for (action, sha) in &dist.github_action_pins {
+        if !GITHUB_ACTIONS.contains(&&**action) {
+            push_warning(warnings, GithubCiWarning::Config(format!("github-action-pins has an entry for {action}, but Github CI doesn't use that action")));
+        }
+        let is_sha = sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit());
+        if !is_sha {
+            push_warning(warnings, GithubCiWarning::Config(format!("github-action-pins pins {action} to {sha}, which isn't a full commit SHA (only those can't be moved)")));
+        }
+    }
+    GithubActions::new(&dist.github_action_renames, &dist.github_action_pins)
+}
+
+/// Get the inputs each job that uploads artifacts passes to upload-artifact
+fn artifact_uploads(
+    dist: &DistGraph,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> SortedMap<String, SortedMap<String, u32>> {
+    for job in dist.artifact_upload_overrides.keys() {
+        if !ARTIFACT_UPLOAD_JOBS.contains(&&**job) {
+            push_warning(warnings, GithubCiWarning::Config(format!("artifact-upload-overrides has an entry for {job}, but that job doesn't upload artifacts (it should be one of {})", ARTIFACT_UPLOAD_JOBS.join(", "))));
+        }
+    }
+    ARTIFACT_UPLOAD_JOBS
+        .iter()
+        .map(|job| {
+            let global = &dist.artifact_uploads;
+            let overrides = dist.artifact_upload_overrides.get(*job);
+            let retention_days = overrides
+                .and_then(|o| o.retention_days)
+                .or(global.retention_days);
+            let compression_level = overrides
+                .and_then(|o| o.compression_level)
+                .or(global.compression_level);
+            let mut inputs = SortedMap::new();
+            if let Some(days) = retention_days {
+                inputs.insert("retention-days".to_owned(), days);
+            }
+            if let Some(level) = compression_level {
+                inputs.insert("compression-level".to_owned(), u32::from(level));
+            }
+            (job.to_string(), inputs)
+        })
+        .collect()
+}
+
+/// Get the secrets to sign Windows artifacts with, if windows-signing is enabled
+fn windows_signing(
+    dist: &DistGraph,
+    builds_windows: bool,
+    warnings: &mut Vec<GithubCiWarning>,
+) -> Option<GithubWindowsSigning> {
+    let windows_signing = dist.windows_signing.then(|| GithubWindowsSigning {
+        certificate_secret: dist
+            .windows_signing_certificate_secret
+            .clone()
+            .unwrap_or_else(|| GITHUB_WINDOWS_SIGNING_CERTIFICATE_SECRET.to_owned()),
+        password_secret: dist
+            .windows_signing_password_secret
+            .clone()
+            .unwrap_or_else(|| GITHUB_WINDOWS_SIGNING_PASSWORD_SECRET.to_owned()),
+    });
+    if windows_signing.is_some() && !builds_windows {
+        push_warning(warnings, GithubCiWarning::Config("windows-signing is enabled, but no Windows targets are built, so nothing will be signed".to_owned()));
+    }
+    windows_signing
+}
+
+/// Render user-provided steps as the YAML for a list of steps, indented to fit in a job
+fn steps_to_yaml(steps: &[SortedMap<String, serde_json::Value>]) -> String {
+    fn json_to_yaml(value: &serde_json::Value) -> Yaml {
+        match value {
+            serde_json::Value::Null => Yaml::Null,
+            serde_json::Value::Bool(val) => Yaml::Boolean(*val),
+            serde_json::Value::Number(val) => match val.as_i64() {
+                Some(int) => Yaml::Integer(int),
+                None => Yaml::Real(val.to_string()),
+            },
+            serde_json::Value::String(val) => Yaml::String(val.clone()),
+            serde_json::Value::Array(vals) => Yaml::Array(vals.iter().map(json_to_yaml).collect()),
+            serde_json::Value::Object(vals) => Yaml::Hash(
+                vals.iter()
+                    .map(|(key, val)| (Yaml::String(key.clone()), json_to_yaml(val)))
+                    .collect(),
+            ),
+        }
+    }
+
+    let steps = Yaml::Array(
+        steps
+            .iter()
+            .map(|step| {
+                Yaml::Hash(
+                    step.iter()
+                        .map(|(key, val)| (Yaml::String(key.clone()), json_to_yaml(val)))
+                        .collect(),
+                )
+            })
+            .collect(),
+    );
+    let mut output = String::new();
+    YamlEmitter::new(&mut output)
+        .dump(&steps)
+        .expect("failed to write setup steps as YAML");
+
+    // Drop the document marker the emitter starts with, and indent to match the job
+    output
+        .lines()
+        .skip_while(|line| line.starts_with("---"))
+        .map(|line| format!("      {line}"))
+        .join("\n")
+}
+
+/// Make the step that runs fetch-secrets-command and adds what it prints to the job's env
+///
+/// This is formatted by hand (instead of with [`steps_to_yaml`][]) so that the command
+/// stays readable in a block scalar.
+fn fetch_secrets_step(command: &str) -> String {
+    let command = command
+        .trim()
+        .lines()
+        .map(|line| format!("            {line}").trim_end().to_owned())
+        .join("\n");
+    format!(
+        r#"      - name: Fetch secrets
+        run: |
+          secrets="$(
+{command}
+          )"
+          while IFS= read -r line; do
+            # Only NAME=value lines are secrets, anything else is just chatter
+            case "$line" in
+              [A-Za-z_]*=*) ;;
+              *) continue ;;
+            esac
+            echo "::add-mask::${{line#*=}}"
+            echo "$line" >> "$GITHUB_ENV"
+          done <<< "$secrets""#
+    )
+}
+
+/// Check that the generated CI at least looks like a Github workflow, so that
+/// template bugs get caught here instead of when Github tries to run it
+fn validate_github_ci(rendered: &str) -> DistResult<()> {
+    let invalid = |reason: String| DistError::GithubCiInvalidWorkflow { reason };
+
+    let docs = YamlLoader::load_from_str(rendered).map_err(|details| {
+        // Markers count chars, but spans want bytes
+        let span = rendered
+            .char_indices()
+            .nth(details.marker().index())
+            .map(|(offset, _)| miette::SourceSpan::from((offset, 0)));
+        DistError::GithubCiInvalidYaml {
+            source: rendered.to_owned(),
+            span,
+            details,
+        }
+    })?;
+    let [workflow] = &docs[..] else {
+        return Err(invalid("expected exactly one YAML document".to_owned()));
+    };
+    let workflow = workflow
+        .as_hash()
+        .ok_or_else(|| invalid("the workflow isn't a mapping".to_owned()))?;
+    if !workflow.contains_key(&Yaml::from_str("on")) {
+        return Err(invalid("missing the 'on' key".to_owned()));
+    }
+    let jobs = workflow
+        .get(&Yaml::from_str("jobs"))
+        .and_then(|jobs| jobs.as_hash())
+        .ok_or_else(|| invalid("missing the 'jobs' mapping".to_owned()))?;
+    if jobs.is_empty() {
+        return Err(invalid("there are no jobs".to_owned()));
+    }
+
+    for (name, job) in jobs {
+        let name = name
+            .as_str()
+            .ok_or_else(|| invalid("a job name isn't a string".to_owned()))?;
+        let job = job
+            .as_hash()
+            .ok_or_else(|| invalid(format!("job '{name}' isn't a mapping")))?;
+        let runs_on = job.get(&Yaml::from_str("runs-on"));
+        if runs_on.is_none() && !job.contains_key(&Yaml::from_str("uses")) {
+            return Err(invalid(format!(
+                "job '{name}' has neither 'runs-on' nor 'uses'"
+            )));
+        }
+        let has_steps = job
+            .get(&Yaml::from_str("steps"))
+            .and_then(|steps| steps.as_vec())
+            .is_some();
+        if runs_on.is_some() && !has_steps {
+            return Err(invalid(format!("job '{name}' has no 'steps' list")));
+        }
+        // Every job we depend on should actually exist
+        let needs = match job.get(&Yaml::from_str("needs")) {
+            Some(Yaml::String(need)) => vec![need.as_str()],
+            Some(Yaml::Array(needs)) => needs.iter().filter_map(|need| need.as_str()).collect(),
+            _ => vec![],
+        };
+        for need in needs {
+            if !jobs.contains_key(&Yaml::from_str(need)) {
+                return Err(invalid(format!(
+                    "job '{name}' needs '{need}', which doesn't exist"
+                )));
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// Given a set of targets we want to build local artifacts for, map them to Github Runners
@@ -181,56 +1686,141 @@ impl GithubCiInfo {
 ///
 /// This optimizes for machine-hours, at the cost of latency and fault-isolation.
 ///
-/// Typically this will result in both x64 macos and arm64 macos getting shoved onto
-/// the same runner, making the entire release process get bottlenecked on the twice-as-long
-/// macos builds. It also makes it impossible to have one macos build fail and the other
-/// succeed (uploading itself to the draft release).
+/// Targets are only ever merged onto a runner that can build all of them natively,
+/// so x64 macos and arm64 macos still end up on separate (intel and arm64) runners.
+/// Any other macos targets that share a runner bottleneck the release on the
+/// one-after-the-other macos builds, and can't fail independently of each other.
 ///
 /// In priniciple it does remove some duplicated setup work, so this is ostensibly "cheaper".
-fn distribute_targets_to_runners_merged(
-    targets: SortedSet<&TargetTriple>,
-) -> std::vec::IntoIter<(GithubRunner, Vec<&TargetTriple>)> {
-    let mut groups = SortedMap::<GithubRunner, Vec<&TargetTriple>>::new();
+///
+/// Targets with a custom runner only get merged with other targets on that same custom runner
+/// (and the same fallback runners). If that's an Intel macos runner, any arm64 macos targets
+/// on it get cross-compiled, see [`macos_targets_cross_compiled_on_intel`][].
+pub(crate) fn distribute_targets_to_runners_merged<'a>(
+    targets: SortedSet<&'a TargetTriple>,
+    custom_runners: &'a SortedMap<TargetTriple, GithubRunnerLabels>,
+    fallback_runners: &'a SortedMap<TargetTriple, Vec<String>>,
+) -> DistResult<std::vec::IntoIter<(GithubRunnerChoice<'a>, Vec<&'a TargetTriple>)>> {
+    let mut groups = SortedMap::<GithubRunnerChoice, Vec<&TargetTriple>>::new();
     for target in targets {
-        let runner = github_runner_for_target(target);
-        let runner = runner.unwrap_or_else(|| {
-            let default = GITHUB_LINUX_RUNNER;
-            warn!("not sure which github runner should be used for {target}, assuming {default}");
-            default
-        });
-        groups.entry(runner).or_default().push(target);
+        let custom_runner = custom_runners.get(target);
+        let fallbacks = fallback_runners
+            .get(target)
+            .map(|r| &**r)
+            .unwrap_or_default();
+        let runner = github_runner_for_target_or_custom(target, custom_runner)?;
+        groups
+            .entry((runner, custom_runner, fallbacks))
+            .or_default()
+            .push(target);
     }
     // This extra into_iter+collect is needed to make this have the same
     // return type as distribute_targets_to_runners_split
-    groups.into_iter().collect::<Vec<_>>().into_iter()
+    Ok(groups.into_iter().collect::<Vec<_>>().into_iter())
+}
+
+/// Split up runs whose targets have different build-overrides, since one
+/// `cargo dist build` can only be given one set of feature flags
+pub(super) fn split_runs_by_build_overrides<'a>(
+    runs: impl Iterator<Item = (GithubRunnerChoice<'a>, Vec<&'a TargetTriple>)>,
+    build_overrides: &SortedMap<TargetTriple, BuildOverride>,
+) -> Vec<(GithubRunnerChoice<'a>, Vec<&'a TargetTriple>)> {
+    let mut split = vec![];
+    for (runner, targets) in runs {
+        let mut groups = Vec::<(Option<&BuildOverride>, Vec<&TargetTriple>)>::new();
+        for target in targets {
+            let build_override = build_overrides.get(target);
+            match groups
+                .iter_mut()
+                .find(|(other, _)| *other == build_override)
+            {
+                Some((_, group)) => group.push(target),
+                None => groups.push((build_override, vec![target])),
+            }
+        }
+        split.extend(groups.into_iter().map(|(_, targets)| (runner, targets)));
+    }
+    split
 }
 
 /// Given a set of targets we want to build local artifacts for, map them to Github Runners
 /// while preferring each target gets its own runner for latency and fault-isolation.
-fn distribute_targets_to_runners_split(
-    targets: SortedSet<&TargetTriple>,
-) -> std::vec::IntoIter<(GithubRunner, Vec<&TargetTriple>)> {
+pub(super) fn distribute_targets_to_runners_split<'a>(
+    targets: SortedSet<&'a TargetTriple>,
+    custom_runners: &'a SortedMap<TargetTriple, GithubRunnerLabels>,
+    fallback_runners: &'a SortedMap<TargetTriple, Vec<String>>,
+) -> DistResult<std::vec::IntoIter<(GithubRunnerChoice<'a>, Vec<&'a TargetTriple>)>> {
     let mut groups = vec![];
     for target in targets {
-        let runner = github_runner_for_target(target);
-        let runner = runner.unwrap_or_else(|| {
-            let default = GITHUB_LINUX_RUNNER;
-            warn!("not sure which github runner should be used for {target}, assuming {default}");
-            default
-        });
-        groups.push((runner, vec![target]));
+        let custom_runner = custom_runners.get(target);
+        let fallbacks = fallback_runners
+            .get(target)
+            .map(|r| &**r)
+            .unwrap_or_default();
+        let runner = github_runner_for_target_or_custom(target, custom_runner)?;
+        groups.push(((runner, custom_runner, fallbacks), vec![target]));
     }
-    groups.into_iter()
+    Ok(groups.into_iter())
 }
 
 /// A string representing a Github Runner
-type GithubRunner = &'static str;
+pub(super) type GithubRunner = &'static str;
+/// The Github Runner we picked for some targets, the label of the custom
+/// runner (from github-custom-runners) that should build them instead, if any,
+/// and the labels to fall back to after that (from github-fallback-runners)
+pub(super) type GithubRunnerChoice<'a> =
+    (GithubRunner, Option<&'a GithubRunnerLabels>, &'a [String]);
 /// The Github Runner to use for Linux
-const GITHUB_LINUX_RUNNER: &str = "ubuntu-20.04";
-/// The Github Runner to use for macos
-const GITHUB_MACOS_RUNNER: &str = "macos-11";
+pub(super) const GITHUB_LINUX_RUNNER: &str = "ubuntu-20.04";
+/// The Github Runner to use for x64 macos
+pub(super) const GITHUB_MACOS_RUNNER: &str = "macos-11";
+/// The Github Runner to use for arm64 macos (Apple Silicon)
+pub(super) const GITHUB_MACOS_ARM64_RUNNER: &str = "macos-14";
 /// The Github Runner to use for windows
-const GITHUB_WINDOWS_RUNNER: &str = "windows-2019";
+pub(super) const GITHUB_WINDOWS_RUNNER: &str = "windows-2019";
+
+/// The command that builds local artifacts' targets on Github Runners
+///
+/// Targets a runner can build natively are built with plain cargo (with rustup adding
+/// the target if needed), see [`build_tool_for_github_runner`][] for the rest.
+const GITHUB_BUILD_COMMAND: &str = "cargo build";
+/// The cargo wrapper that builds Linux targets the Linux runner can't build natively
+const GITHUB_CROSS_BUILD_TOOL: &str = "cross";
+/// The command that installs [`GITHUB_CROSS_BUILD_TOOL`][]
+const GITHUB_INSTALL_CROSS: &str = "cargo install cross --locked";
+/// The command that installs cargo-nextest, for running sharded tests
+///
+/// This is pinned (and locked) so the release pipeline can't pick up a new nextest by surprise.
+const GITHUB_INSTALL_NEXTEST: &str = "cargo install cargo-nextest --version 0.9.67 --locked";
+/// The Github Runner images that are Intel macs (the rest of the macos ones are arm64)
+const GITHUB_INTEL_MACOS_RUNNERS: &[GithubRunner] = &[
+    "macos-11",
+    "macos-12",
+    "macos-13",
+    "macos-12-large",
+    "macos-13-large",
+    "macos-14-large",
+    "macos-latest-large",
+];
+/// The arch of the default Github Runners
+pub(super) const GITHUB_RUNNER_ARCH: &str = "x86_64";
+/// The target triple of the Linux Github Runner
+const GITHUB_LINUX_HOST: &str = "x86_64-unknown-linux-gnu";
+
+/// Github Runner images that are (or will soon be) end-of-life, and what to use instead
+const DEPRECATED_GITHUB_RUNNERS: &[(GithubRunner, GithubRunner)] = &[
+    ("ubuntu-20.04", "ubuntu-22.04"),
+    ("macos-11", "macos-13"),
+    ("macos-12", "macos-13"),
+];
+
+/// Get the suggested replacement for a Github Runner, if it's deprecated
+fn deprecated_github_runner_replacement(runner: &str) -> Option<GithubRunner> {
+    DEPRECATED_GITHUB_RUNNERS
+        .iter()
+        .find(|(deprecated, _)| *deprecated == runner)
+        .map(|(_, replacement)| *replacement)
+}
 
 /// Get the appropriate Github Runner for building a target
 fn github_runner_for_target(target: &TargetTriple) -> Option<GithubRunner> {
@@ -240,7 +1830,12 @@ fn github_runner_for_target(target: &TargetTriple) -> Option<GithubRunner> {
     if target.contains("linux") {
         Some(GITHUB_LINUX_RUNNER)
     } else if target.contains("apple") {
-        Some(GITHUB_MACOS_RUNNER)
+        // Apple Silicon gets to build natively on an arm64 runner
+        if target.starts_with("aarch64") {
+            Some(GITHUB_MACOS_ARM64_RUNNER)
+        } else {
+            Some(GITHUB_MACOS_RUNNER)
+        }
     } else if target.contains("windows") {
         Some(GITHUB_WINDOWS_RUNNER)
     } else {
@@ -248,17 +1843,270 @@ fn github_runner_for_target(target: &TargetTriple) -> Option<GithubRunner> {
     }
 }
 
-/// Select the cargo-dist installer approach for a given Github Runner
-fn install_dist_for_github_runner<'a>(
+/// Get the arm64 macos targets that a run would have to cross-compile, because
+/// it's on a (custom) Intel macos runner
+///
+/// cargo-dist has rustup add the target, so this works for plain Rust, but anything
+/// that builds C code or runs the binaries it just built may not be ready for it.
+pub(crate) fn macos_targets_cross_compiled_on_intel<'a>(
+    runner: GithubRunnerChoice,
+    targets: &[&'a TargetTriple],
+) -> Vec<&'a TargetTriple> {
+    let (runner, custom_runner, _) = runner;
+    let on_intel = match custom_runner {
+        Some(custom_runner) => custom_runner
+            .labels()
+            .iter()
+            .any(|label| GITHUB_INTEL_MACOS_RUNNERS.contains(&&**label)),
+        None => GITHUB_INTEL_MACOS_RUNNERS.contains(&runner),
+    };
+    if !on_intel {
+        return vec![];
+    }
+    targets
+        .iter()
+        .filter(|target| target.starts_with("aarch64") && target.contains("apple-darwin"))
+        .copied()
+        .collect()
+}
+
+/// Get the names of the secrets a value references (as `${{ secrets.NAME }}`)
+fn github_secret_references(value: &str) -> Vec<&str> {
+    value
+        .split("${{")
+        .skip(1)
+        .filter_map(|expr| expr.split_once("}}"))
+        .filter_map(|(expr, _)| expr.trim().strip_prefix("secrets."))
+        .filter(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .collect()
+}
+
+/// Whether a Github Runner is one of the macos runners
+pub(super) fn is_macos_github_runner(runner: GithubRunner) -> bool {
+    runner == GITHUB_MACOS_RUNNER || runner == GITHUB_MACOS_ARM64_RUNNER
+}
+
+/// Get the shell that steps on a Github Runner should default to
+///
+/// We pick these explicitly so custom steps behave the same on every runner,
+/// instead of relying on Github's defaults (which vary by OS).
+fn github_shell_for_runner(runner: GithubRunner) -> &'static str {
+    if runner == GITHUB_WINDOWS_RUNNER {
+        "pwsh"
+    } else {
+        "bash"
+    }
+}
+
+/// The secret windows-signing reads the certificate from if the user doesn't pick one
+const GITHUB_WINDOWS_SIGNING_CERTIFICATE_SECRET: &str = "WINDOWS_CERTIFICATE";
+/// The secret windows-signing reads the certificate's password from if the user doesn't pick one
+const GITHUB_WINDOWS_SIGNING_PASSWORD_SECRET: &str = "WINDOWS_CERTIFICATE_PASSWORD";
+
+/// Every job in Github CI that uploads artifacts for later jobs
+const ARTIFACT_UPLOAD_JOBS: &[&str] = &[
+    "plan",
+    "upload-local-artifacts",
+    "upload-global-artifacts",
+    "upload-optional-installers",
+];
+
+/// Every Github Action that Github CI might use (without versions)
+const GITHUB_ACTIONS: &[&str] = &[
+    "actions/checkout",
+    "actions/upload-artifact",
+    "actions/download-artifact",
+    "swatinem/rust-cache",
+    "actions/upload-pages-artifact",
+    "actions/deploy-pages",
+    "ncipollo/release-action",
+    "sigstore/cosign-installer",
+    "actions/attest-build-provenance",
+];
+
+/// Apply github-action-renames to the name of a Github Action (like `actions/checkout`)
+///
+/// A rename of the whole action takes precedence over a rename of its owner.
+fn rename_github_action(renames: &SortedMap<String, String>, name: &str) -> String {
+    if let Some(renamed) = renames.get(name) {
+        return renamed.clone();
+    }
+    if let Some((owner, rest)) = name.split_once('/') {
+        if let Some(renamed_owner) = renames.get(owner) {
+            return format!("{renamed_owner}/{rest}");
+        }
+    }
+    name.to_owned()
+}
+
+/// Add a guard to a publish job's condition so that it never runs for a pull request
+///
+/// Conditions can be a bare expression or wrapped in `${{ }}`. One that's anything more
+/// complicated is left alone, as should-publish is guarded anyway.
+fn guard_from_pull_requests(condition: &str) -> String {
+    let condition = condition.trim();
+    let inner = match condition
+        .strip_prefix("${{")
+        .and_then(|c| c.strip_suffix("}}"))
+    {
+        Some(inner) => inner.trim(),
+        None => condition,
+    };
+    if inner.contains("${{") || inner.contains("}}") {
+        return condition.to_owned();
+    }
+    format!("${{{{ github.event_name != 'pull_request' && ({inner}) }}}}")
+}
+
+/// Get the patterns for the tags that release the app with the given tag prefix
+/// (like "my-app-v1.0.0" or "my-app/1.0.0")
+fn tag_patterns_for_prefix(prefix: &str) -> Vec<String> {
+    vec![
+        format!("{prefix}[/-][0-9]+.[0-9]+.[0-9]+*"),
+        format!("{prefix}[/-]v[0-9]+.[0-9]+.[0-9]+*"),
+    ]
+}
+
+/// Get the name of the secret a publish job uses if the user doesn't pick one
+fn default_secret_for_publish_job(job: PublishStyle) -> &'static str {
+    match job {
+        PublishStyle::Homebrew => "HOMEBREW_TAP_TOKEN",
+        PublishStyle::Docs => "GITHUB_TOKEN",
+        PublishStyle::CratesIo => "CARGO_REGISTRY_TOKEN",
+    }
+}
+
+/// Get the Github Runner for a target, or error if we don't know one
+///
+/// If the target has a custom runner, unknown targets are allowed and treated like
+/// Linux (which only decides how cargo-dist gets installed on the custom runner).
+fn github_runner_for_target_or_custom(
+    target: &TargetTriple,
+    custom_runner: Option<&GithubRunnerLabels>,
+) -> DistResult<GithubRunner> {
+    if let Some(runner) = github_runner_for_target(target) {
+        info!(target_triple = %target, runner, "selected Github runner for target");
+        Ok(runner)
+    } else if let Some(custom_runner) = custom_runner {
+        let default = GITHUB_LINUX_RUNNER;
+        info!(
+            target_triple = %target,
+            runner = %custom_runner,
+            "using custom Github runner for unknown target, assuming it's like {default}"
+        );
+        Ok(default)
+    } else {
+        Err(DistError::UnknownRunnerForTarget {
+            target: target.clone(),
+        })
+    }
+}
+
+/// Select the cargo wrapper (and the command that installs it) that a Github Runner
+/// needs to build some targets, if it can't build all of them natively
+///
+/// cargo-dist only uses the wrapper for targets that aren't the runner's host.
+fn build_tool_for_github_runner(
+    runner: GithubRunnerChoice,
+    targets: &[&TargetTriple],
+) -> Option<(&'static str, &'static str)> {
+    // We don't know what arch a custom runner is, so trust it to build natively
+    let (runner, None, _) = runner else {
+        return None;
+    };
+    let needs_cross = runner == GITHUB_LINUX_RUNNER
+        && targets
+            .iter()
+            .any(|target| target.contains("linux") && !target.starts_with(GITHUB_RUNNER_ARCH));
+    needs_cross.then_some((GITHUB_CROSS_BUILD_TOOL, GITHUB_INSTALL_CROSS))
+}
+
+/// Get the command that installs the system packages some targets need on a Github Runner
+///
+/// Each target uses its entry in dependency-overrides for the runner's package manager
+/// if the entry sets it, and the global dependencies otherwise.
+fn install_dependencies_for_github_runner(
     runner: GithubRunner,
-    install_sh: &'a str,
-    install_ps1: &'a str,
-) -> &'a str {
-    if runner == GITHUB_LINUX_RUNNER || runner == GITHUB_MACOS_RUNNER {
-        install_sh
+    targets: &[&TargetTriple],
+    dist: &DistGraph,
+) -> Option<String> {
+    let packages_for = |deps: &SystemDependencies| {
+        if runner == GITHUB_LINUX_RUNNER {
+            deps.apt.clone()
+        } else if is_macos_github_runner(runner) {
+            deps.brew.clone()
+        } else {
+            deps.chocolatey.clone()
+        }
+    };
+    let packages = targets
+        .iter()
+        .flat_map(|target| {
+            dist.dependency_overrides
+                .get(*target)
+                .and_then(packages_for)
+                .or_else(|| packages_for(&dist.dependencies))
+                .unwrap_or_default()
+        })
+        .collect::<SortedSet<_>>();
+    if packages.is_empty() {
+        return None;
+    }
+
+    let packages = packages.iter().join(" ");
+    let install = if runner == GITHUB_LINUX_RUNNER {
+        format!("sudo apt-get update && sudo apt-get install -y {packages}")
+    } else if is_macos_github_runner(runner) {
+        format!("brew install {packages}")
+    } else {
+        format!("choco install -y {packages}")
+    };
+    Some(install)
+}
+
+/// Get the target triple of the machine one of the default Github Runners runs on
+fn github_runner_host(runner: GithubRunner) -> &'static str {
+    if runner == GITHUB_MACOS_RUNNER {
+        "x86_64-apple-darwin"
+    } else if runner == GITHUB_MACOS_ARM64_RUNNER {
+        "aarch64-apple-darwin"
     } else if runner == GITHUB_WINDOWS_RUNNER {
-        install_ps1
+        "x86_64-pc-windows-msvc"
+    } else {
+        GITHUB_LINUX_HOST
+    }
+}
+
+/// Select the cargo-dist installer approach for a runner whose host is the given target
+///
+/// This goes by the host's OS (and its entry in cargo-dist-archives, if any), so it
+/// works the same for custom runners as it does for the default ones. Minimal hosts
+/// (like musl ones) get commands that don't need curl to be installed.
+fn install_dist_for_github_host(
+    host: &str,
+    dist_version: &Version,
+    methods: &SortedMap<RunnerOs, InstallDistMethod>,
+    archives: &SortedMap<TargetTriple, DistArchive>,
+) -> String {
+    let os = if host.contains("windows") {
+        RunnerOs::Windows
+    } else if host.contains("apple") {
+        RunnerOs::Macos
     } else {
-        unreachable!("internal error: unknown github runner!?")
+        RunnerOs::Linux
+    };
+    let tools = super::RunnerTools::for_host(host);
+
+    match archives.get(host) {
+        Some(archive) if os == RunnerOs::Windows => super::install_dist_ps1_for_archive(archive),
+        Some(archive) => super::install_dist_sh_for_archive(archive, tools),
+        None => {
+            if !archives.is_empty() {
+                warn!("cargo-dist-archives has no entry for {host}, so runners for it will use install-dist-methods instead");
+            }
+            super::install_dist_for_runner(dist_version, os, tools, methods)
+        }
     }
 }
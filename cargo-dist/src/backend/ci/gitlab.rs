@@ -0,0 +1,274 @@
+//! CI script generation for Gitlab
+//!
+//! This mirrors the Github backend: targets get grouped onto machines by the same
+//! logic, but the result is a single .gitlab-ci.yml pipeline that publishes to
+//! a Gitlab Release.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_dist_schema::PrRunMode;
+use serde::Serialize;
+use tracing::warn;
+
+use super::{
+    github::{
+        is_macos_github_runner, GithubRunner, GITHUB_LINUX_RUNNER, GITHUB_MACOS_ARM64_RUNNER,
+        GITHUB_WINDOWS_RUNNER,
+    },
+    CiPostProcessor,
+};
+use crate::{
+    backend::templates::TEMPLATE_CI_GITLAB,
+    config::{CiStyle, GlobalBuildMode, RunnerOs},
+    errors::DistResult,
+    DistGraph, GeneratedFile, StaleFile,
+};
+
+const GITLAB_CI_FILE: &str = ".gitlab-ci.yml";
+
+/// The image linux jobs run in (Gitlab's shared runners are docker-based)
+const GITLAB_LINUX_IMAGE: &str = "rust:latest";
+/// The runner tag for Gitlab's hosted macos runners
+const GITLAB_MACOS_TAG: &str = "saas-macos-medium-m1";
+/// The image Gitlab's hosted macos runners should use
+const GITLAB_MACOS_IMAGE: &str = "macos-14-xcode-15";
+/// The runner tag for Gitlab's hosted windows runners
+const GITLAB_WINDOWS_TAG: &str = "saas-windows-medium-amd64";
+
+/// Info about running cargo-dist in Gitlab CI
+#[derive(Debug, Serialize)]
+pub struct GitlabCiInfo {
+    /// Version of cargo-dist to install
+    pub dist_version: String,
+    /// expression to use for installing cargo-dist in the plan job
+    pub install_dist_sh: String,
+    /// The image the plan job runs in
+    pub linux_image: String,
+    /// Commands that set up the plan job's image
+    pub linux_setup: Vec<String>,
+    /// The jobs that build local artifacts
+    pub local_jobs: Vec<GitlabJob>,
+    /// The job that builds global artifacts (if any)
+    pub global_job: Option<GitlabJob>,
+    /// What kind of job to run on merge requests
+    pub pr_run_mode: PrRunMode,
+    /// Whether to skip checking whether the .gitlab-ci.yml is up to date
+    pub allow_dirty: bool,
+}
+
+/// A job that runs `cargo dist build` on some Gitlab runner
+#[derive(Debug, Serialize)]
+pub struct GitlabJob {
+    /// The name of the job
+    pub name: String,
+    /// The docker image (or machine image) to run on, if the runner takes one
+    pub image: Option<String>,
+    /// The tags that pick the runner (empty means Gitlab's default linux runners)
+    pub tags: Vec<String>,
+    /// Whether the runner uses powershell instead of sh
+    pub windows: bool,
+    /// Commands that make sure the runner has rust (and jq, for sh runners)
+    pub setup: Vec<String>,
+    /// expression to use for installing cargo-dist
+    pub install_dist: String,
+    /// Arguments to pass to `cargo dist build`
+    pub dist_args: String,
+}
+
+impl GitlabCiInfo {
+    /// Compute the Gitlab CI stuff
+    pub fn new(dist: &DistGraph) -> DistResult<GitlabCiInfo> {
+        // If they don't specify a cargo-dist version, use this one
+        let self_dist_version = super::SELF_DIST_VERSION.parse().unwrap();
+        let dist_version = dist
+            .desired_cargo_dist_version
+            .as_ref()
+            .unwrap_or(&self_dist_version);
+        let install_dist_for =
+            |os| super::install_dist_for_os(dist_version, os, &dist.install_dist_methods);
+        let install_dist_sh = install_dist_for(RunnerOs::Linux);
+
+        if !dist.create_release {
+            warn!("create-release = false isn't supported by Gitlab CI, a Gitlab Release will still be created");
+        }
+        if !dist.publish_jobs.is_empty() {
+            warn!("publish-jobs aren't supported by Gitlab CI yet, they'll only run in Github CI");
+        }
+
+        // Figure out what builds we need to do. Unlike Github CI, the jobs are fixed
+        // when the pipeline is generated (not planned per-tag), so they have to cover
+        // every configured target, and the global build always runs unless disabled.
+        let needs_global_build = dist.global_build != GlobalBuildMode::Never;
+        let mut local_jobs = vec![];
+        for build in super::fixed_local_builds(dist, "Gitlab CI")? {
+            let machine = GitlabMachine::for_github_runner(build.runner);
+            let name = build.job_name(machine.name);
+            let install_dist = install_dist_for(machine.os);
+            local_jobs.push(machine.job(name, install_dist, build.dist_args));
+        }
+
+        // Global artifacts can be built anywhere, and linux is the cheapest place
+        let linux = GitlabMachine::for_github_runner(GITHUB_LINUX_RUNNER);
+        let global_job = needs_global_build.then(|| {
+            linux.job(
+                "build-global".to_owned(),
+                install_dist_sh.clone(),
+                "--artifacts=global".to_owned(),
+            )
+        });
+
+        Ok(GitlabCiInfo {
+            dist_version: dist_version.to_string(),
+            install_dist_sh,
+            linux_image: GITLAB_LINUX_IMAGE.to_owned(),
+            linux_setup: linux.setup,
+            local_jobs,
+            global_job,
+            pr_run_mode: dist.pr_run_mode.clone(),
+            allow_dirty: dist.allow_dirty.contains(&CiStyle::Gitlab),
+        })
+    }
+
+    fn gitlab_ci_path(&self, output_dir: &Utf8Path) -> Utf8PathBuf {
+        output_dir.join(GITLAB_CI_FILE)
+    }
+
+    /// Generate the requested configuration and returns it as a string.
+    ///
+    /// Any post_processors get to edit the result (in order) before it's returned.
+    pub fn generate_gitlab_ci(
+        &self,
+        dist: &DistGraph,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<String, miette::Report> {
+        super::render_ci_file(dist, TEMPLATE_CI_GITLAB, self, post_processors)
+    }
+
+    /// Write .gitlab-ci.yml to disk, rooted at output_dir (usually the workspace dir)
+    pub fn write_to_disk(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<GeneratedFile>, miette::Report> {
+        let ci_file = self.gitlab_ci_path(output_dir);
+        let rendered = self.generate_gitlab_ci(dist, post_processors)?;
+        super::write_ci_file(&ci_file, &rendered, "Gitlab CI")
+    }
+
+    /// Check whether the new configuration differs from the config on disk
+    /// without actually writing the result.
+    ///
+    /// The config on disk is looked up relative to output_dir (usually the workspace dir).
+    pub fn check_gitlab_ci(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<(), miette::Report> {
+        let stale = self.stale_gitlab_files(dist, output_dir, post_processors)?;
+        crate::check_stale_files(stale)?;
+        Ok(())
+    }
+
+    /// Get every file we'd generate that differs from what's on disk
+    /// (empty if allow-dirty is set for Gitlab CI).
+    ///
+    /// The files on disk are looked up relative to output_dir (usually the workspace dir).
+    pub fn stale_gitlab_files(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<StaleFile>, miette::Report> {
+        let file = self.gitlab_ci_path(output_dir);
+        let rendered = self.generate_gitlab_ci(dist, post_processors)?;
+        Ok(super::stale_ci_file(&file, &rendered, self.allow_dirty))
+    }
+}
+
+/// The Gitlab equivalent of one of the Github Runners we'd pick for some targets
+struct GitlabMachine {
+    /// Short name of the platform, for job names
+    name: &'static str,
+    /// Which install-dist-methods entry applies here
+    os: RunnerOs,
+    image: Option<&'static str>,
+    tags: Vec<&'static str>,
+    windows: bool,
+    setup: Vec<String>,
+}
+
+impl GitlabMachine {
+    /// Map a Github Runner to the Gitlab hosted runner that's closest to it
+    fn for_github_runner(runner: GithubRunner) -> Self {
+        let rustup_sh = "command -v cargo > /dev/null || curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y --profile minimal";
+        if is_macos_github_runner(runner) {
+            // Gitlab only hosts Apple Silicon macos runners, which build x64 macos too
+            GitlabMachine {
+                name: if runner == GITHUB_MACOS_ARM64_RUNNER {
+                    "macos-arm64"
+                } else {
+                    "macos"
+                },
+                os: RunnerOs::Macos,
+                image: Some(GITLAB_MACOS_IMAGE),
+                tags: vec![GITLAB_MACOS_TAG],
+                windows: false,
+                setup: vec![
+                    rustup_sh.to_owned(),
+                    r#"export PATH="$HOME/.cargo/bin:$PATH""#.to_owned(),
+                    "command -v jq > /dev/null || brew install jq".to_owned(),
+                ],
+            }
+        } else if runner == GITHUB_WINDOWS_RUNNER {
+            GitlabMachine {
+                name: "windows",
+                os: RunnerOs::Windows,
+                image: None,
+                tags: vec![GITLAB_WINDOWS_TAG],
+                windows: true,
+                setup: vec![
+                    "if (!(Get-Command cargo -ErrorAction SilentlyContinue)) { Invoke-WebRequest https://win.rustup.rs/x86_64 -OutFile rustup-init.exe; ./rustup-init.exe -y --profile minimal }".to_owned(),
+                    r#"$env:Path = "$env:USERPROFILE\.cargo\bin;$env:Path""#.to_owned(),
+                ],
+            }
+        } else {
+            GitlabMachine {
+                name: "linux",
+                os: RunnerOs::Linux,
+                image: Some(GITLAB_LINUX_IMAGE),
+                tags: vec![],
+                windows: false,
+                setup: vec!["apt-get update && apt-get install -y jq".to_owned()],
+            }
+        }
+    }
+
+    /// Make a job that builds dist_args on this machine
+    fn job(&self, name: String, install_dist: String, dist_args: String) -> GitlabJob {
+        GitlabJob {
+            name,
+            image: self.image.map(|i| i.to_owned()),
+            tags: self.tags.iter().map(|t| (*t).to_owned()).collect(),
+            windows: self.windows,
+            setup: self.setup.clone(),
+            install_dist,
+            dist_args,
+        }
+    }
+}
+
+/// Get the web url of a Gitlab repository from its repository url
+///
+/// This converts ssh urls (git@gitlab.com:owner/repo.git) to https and trims .git,
+/// the same way axoproject does for Github.
+pub fn web_url(repo_url: &str) -> String {
+    let url = repo_url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    if let Some(path) = url.strip_prefix("git@") {
+        let path = path.replacen(':', "/", 1);
+        format!("https://{path}")
+    } else {
+        url.to_owned()
+    }
+}
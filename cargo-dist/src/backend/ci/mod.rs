@@ -1,24 +1,334 @@
 //! Support for generating CI scripts for running cargo-dist
 
+use axoasset::LocalAsset;
+use camino::Utf8Path;
 use semver::Version;
+use serde::Serialize;
+use tracing::info;
 
-use self::github::GithubCiInfo;
+use self::azure::AzureCiInfo;
+use self::circleci::CircleCiInfo;
+use self::github::{
+    distribute_targets_to_runners_merged, distribute_targets_to_runners_split,
+    split_runs_by_build_overrides, GithubCiInfo, GithubRunner, GITHUB_RUNNER_ARCH,
+};
+use self::gitlab::GitlabCiInfo;
+use crate::{
+    backend::templates::TemplateId,
+    config::{DistArchive, InstallDistMethod, RunnerOs},
+    errors::{DistError, DistResult, Result},
+    DistGraph, GeneratedFile, SortedMap, SortedSet, StaleFile, TargetTriple,
+};
 
+pub mod azure;
+pub mod circleci;
 pub mod github;
+pub mod gitlab;
 
 /// The current version of cargo-dist
 const SELF_DIST_VERSION: &str = env!("CARGO_PKG_VERSION");
 const BASE_DIST_FETCH_URL: &str = "https://github.com/axodotdev/cargo-dist/releases/download";
+/// Where cargo-binstall's own installer scripts live (append .sh or .ps1)
+const BINSTALL_INSTALLER_URL: &str =
+    "https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release";
 
 /// Info about all the enabled CI backends
 #[derive(Debug, Default)]
 pub struct CiInfo {
     /// Github CI
     pub github: Option<GithubCiInfo>,
+    /// Gitlab CI
+    pub gitlab: Option<GitlabCiInfo>,
+    /// Azure Pipelines
+    pub azure: Option<AzureCiInfo>,
+    /// CircleCI
+    pub circleci: Option<CircleCiInfo>,
+}
+
+impl CiInfo {
+    /// Write every enabled CI's files to disk, rooted at output_dir (usually the workspace dir)
+    ///
+    /// If `skip_dirty` is set, CIs with allow-dirty set are left alone.
+    pub fn write_to_disk(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+        skip_dirty: bool,
+    ) -> Result<Vec<GeneratedFile>> {
+        // If you add a CI backend, call its write_to_disk here
+        let CiInfo {
+            github,
+            gitlab,
+            azure,
+            circleci,
+        } = self;
+        let mut generated = vec![];
+        if let Some(github) = github.as_ref().filter(|ci| !skip_dirty || !ci.allow_dirty) {
+            generated.extend(github.write_to_disk(dist, output_dir, post_processors)?);
+        }
+        if let Some(gitlab) = gitlab.as_ref().filter(|ci| !skip_dirty || !ci.allow_dirty) {
+            generated.extend(gitlab.write_to_disk(dist, output_dir, post_processors)?);
+        }
+        if let Some(azure) = azure.as_ref().filter(|ci| !skip_dirty || !ci.allow_dirty) {
+            generated.extend(azure.write_to_disk(dist, output_dir, post_processors)?);
+        }
+        if let Some(circleci) = circleci
+            .as_ref()
+            .filter(|ci| !skip_dirty || !ci.allow_dirty)
+        {
+            generated.extend(circleci.write_to_disk(dist, output_dir, post_processors)?);
+        }
+        Ok(generated)
+    }
+
+    /// Get every file of every enabled CI that differs from what's on disk
+    /// (skipping CIs with allow-dirty set).
+    ///
+    /// The files on disk are looked up relative to output_dir (usually the workspace dir).
+    pub fn stale_files(
+        &self,
+        dist: &DistGraph,
+        output_dir: &Utf8Path,
+        post_processors: &[Box<dyn CiPostProcessor>],
+    ) -> Result<Vec<StaleFile>> {
+        // If you add a CI backend, call its stale files here
+        let CiInfo {
+            github,
+            gitlab,
+            azure,
+            circleci,
+        } = self;
+        let mut stale = vec![];
+        if let Some(github) = github {
+            stale.extend(github.stale_github_files(dist, output_dir, post_processors)?);
+        }
+        if let Some(gitlab) = gitlab {
+            stale.extend(gitlab.stale_gitlab_files(dist, output_dir, post_processors)?);
+        }
+        if let Some(azure) = azure {
+            stale.extend(azure.stale_azure_files(dist, output_dir, post_processors)?);
+        }
+        if let Some(circleci) = circleci {
+            stale.extend(circleci.stale_circleci_files(dist, output_dir, post_processors)?);
+        }
+        Ok(stale)
+    }
+}
+
+/// A hook that gets to edit a generated CI file before it's written to disk
+/// (or compared against the one on disk for --check)
+///
+/// This lets library users run a formatter or enforce policies on the output
+/// without editing our templates. Any `Fn(String) -> Result<String>` is one.
+pub trait CiPostProcessor {
+    /// Take the rendered contents of a CI file and return what to use instead
+    fn post_process(&self, rendered: String) -> Result<String>;
+}
+
+impl<F> CiPostProcessor for F
+where
+    F: Fn(String) -> Result<String>,
+{
+    fn post_process(&self, rendered: String) -> Result<String> {
+        self(rendered)
+    }
+}
+
+impl std::fmt::Debug for dyn CiPostProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CiPostProcessor")
+    }
+}
+
+/// A job that builds local artifacts in a CI whose jobs are fixed when it's generated
+/// (every backend but Github's, whose build matrix is planned for each release)
+///
+/// Targets are grouped the same way Github CI would group them onto its runners,
+/// and each backend runs the group on its own machine that's closest to that runner.
+struct FixedBuild {
+    /// The Github Runner these targets would build on
+    runner: GithubRunner,
+    /// The targets to build
+    targets: Vec<TargetTriple>,
+    /// Arguments to pass to `cargo dist build`
+    dist_args: String,
+}
+
+impl FixedBuild {
+    /// Name the job, using the machine's name if it builds more than one target
+    fn job_name(&self, machine: &str) -> String {
+        match &self.targets[..] {
+            [target] => format!("build-local-{target}"),
+            _ => format!("build-local-{machine}"),
+        }
+    }
+}
+
+/// Work out the local builds of a CI whose jobs are fixed when it's generated
+///
+/// The jobs can't be planned per-tag, so they cover every configured target.
+/// `ci` is the name of the CI, for errors.
+fn fixed_local_builds(dist: &DistGraph, ci: &str) -> DistResult<Vec<FixedBuild>> {
+    use std::fmt::Write;
+
+    let local_targets = dist.all_targets.iter().collect::<SortedSet<_>>();
+    // Only Github CI knows how to cross-compile Linux (with cross), and everyone
+    // else's Linux machines are the same arch as Github's
+    if let Some(target) = local_targets
+        .iter()
+        .find(|target| target.contains("linux") && !target.starts_with(GITHUB_RUNNER_ARCH))
+    {
+        return Err(DistError::CiCantBuildTarget {
+            ci: ci.to_owned(),
+            target: (*target).clone(),
+        });
+    }
+
+    // github-custom-runners and github-fallback-runners are Github labels,
+    // so they don't mean anything here
+    let (custom_runners, fallback_runners) = (SortedMap::new(), SortedMap::new());
+    let runs = if dist.merge_tasks {
+        distribute_targets_to_runners_merged(local_targets, &custom_runners, &fallback_runners)?
+    } else {
+        distribute_targets_to_runners_split(local_targets, &custom_runners, &fallback_runners)?
+    };
+    let mut builds = vec![];
+    for ((runner, ..), targets) in split_runs_by_build_overrides(runs, &dist.build_overrides) {
+        let mut dist_args = String::from("--artifacts=local");
+        for target in &targets {
+            write!(dist_args, " --target={target}").unwrap();
+        }
+        // Every target in a run has the same build-overrides (if any)
+        if let Some(build_override) = dist.build_overrides.get(targets[0]) {
+            for arg in build_override.dist_args() {
+                write!(dist_args, " {arg}").unwrap();
+            }
+        }
+        builds.push(FixedBuild {
+            runner,
+            targets: targets.into_iter().cloned().collect(),
+            dist_args,
+        });
+    }
+    Ok(builds)
+}
+
+/// Render a CI file's template, and let any post_processors edit the result (in order)
+fn render_ci_file(
+    dist: &DistGraph,
+    template: TemplateId,
+    info: &impl Serialize,
+    post_processors: &[Box<dyn CiPostProcessor>],
+) -> std::result::Result<String, miette::Report> {
+    let mut rendered = dist.templates.render_file_to_clean_string(template, info)?;
+    for post_processor in post_processors {
+        rendered = post_processor.post_process(rendered)?;
+    }
+    Ok(rendered)
+}
+
+/// Write a rendered CI file to disk, reporting it as `desc`
+fn write_ci_file(
+    ci_file: &Utf8Path,
+    rendered: &str,
+    desc: &str,
+) -> std::result::Result<Vec<GeneratedFile>, miette::Report> {
+    LocalAsset::write_new_all(rendered, ci_file)?;
+    info!(path = %ci_file, "generated {desc}");
+
+    Ok(vec![GeneratedFile {
+        desc: desc.to_owned(),
+        path: ci_file.to_string(),
+    }])
+}
+
+/// Compare a rendered CI file to the one on disk (never stale if allow_dirty is set)
+fn stale_ci_file(ci_file: &Utf8Path, rendered: &str, allow_dirty: bool) -> Vec<StaleFile> {
+    if allow_dirty {
+        return vec![];
+    }
+    StaleFile::check(ci_file, rendered).into_iter().collect()
+}
+
+/// What tools the sh commands that install cargo-dist can count on a runner having
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunnerTools {
+    /// Everything the hosted images have, like curl and shasum
+    Standard,
+    /// Just what minimal images like alpine have, which may only include busybox's wget
+    Minimal,
+}
+
+impl RunnerTools {
+    /// Guess what tools a runner has from its host target
+    ///
+    /// musl hosts are almost always minimal images (or self-hosted runners built on them)
+    fn for_host(host: &str) -> Self {
+        if host.contains("musl") {
+            RunnerTools::Minimal
+        } else {
+            RunnerTools::Standard
+        }
+    }
+
+    /// Get a sh command that downloads `url`, to stdout or to the file at `path`
+    fn fetch(self, url: &str, path: Option<&str>) -> String {
+        let curl = match path {
+            Some(path) => format!("curl --proto '=https' --tlsv1.2 -LsSf {url} -o {path}"),
+            None => format!("curl --proto '=https' --tlsv1.2 -LsSf {url}"),
+        };
+        match self {
+            RunnerTools::Standard => curl,
+            RunnerTools::Minimal => {
+                let wget = format!("wget -q {url} -O {}", path.unwrap_or("-"));
+                format!("(if command -v curl > /dev/null 2>&1; then {curl}; else {wget}; fi)")
+            }
+        }
+    }
+
+    /// Get a sh command that checks the sha256 of the file at `path`
+    fn check_sha256(self, sha256: &str, path: &str) -> String {
+        let tool = match self {
+            RunnerTools::Standard => "shasum -a 256",
+            RunnerTools::Minimal => "sha256sum",
+        };
+        format!("echo \"{sha256}  {path}\" | {tool} --check")
+    }
+}
+
+/// Get the command to invoke to install cargo-dist on a runner with the given OS,
+/// using whichever method install-dist-methods picked for it
+fn install_dist_for_os(
+    version: &Version,
+    os: RunnerOs,
+    methods: &SortedMap<RunnerOs, InstallDistMethod>,
+) -> String {
+    install_dist_for_runner(version, os, RunnerTools::Standard, methods)
+}
+
+/// Like [`install_dist_for_os`][], but only counting on the runner having `tools`
+fn install_dist_for_runner(
+    version: &Version,
+    os: RunnerOs,
+    tools: RunnerTools,
+    methods: &SortedMap<RunnerOs, InstallDistMethod>,
+) -> String {
+    let method = methods
+        .get(&os)
+        .copied()
+        .unwrap_or_else(|| os.default_install_dist_method());
+    match method {
+        InstallDistMethod::Shell => install_dist_sh_for_version(version, tools),
+        InstallDistMethod::Powershell => install_dist_ps1_for_version(version),
+        InstallDistMethod::Binstall => {
+            install_dist_binstall_for_version(version, os == RunnerOs::Windows, tools)
+        }
+    }
 }
 
 /// Get the command to invoke to install cargo-dist via sh script
-fn install_dist_sh_for_version(version: &Version) -> String {
+fn install_dist_sh_for_version(version: &Version, tools: RunnerTools) -> String {
     if let Some(git) = install_dist_git(version) {
         return git;
     }
@@ -35,7 +345,7 @@ fn install_dist_sh_for_version(version: &Version) -> String {
     // FIXME: it would be nice if these values were somehow using all the machinery
     // to compute these values for packages we build *BUT* it's messy and not that important
     let installer_url = format!("{BASE_DIST_FETCH_URL}/v{version}/{installer_name}");
-    format!("curl --proto '=https' --tlsv1.2 -LsSf {installer_url} | sh")
+    format!("{} | sh", tools.fetch(&installer_url, None))
 }
 
 /// Get the command to invoke to install cargo-dist via ps1 script
@@ -59,6 +369,54 @@ fn install_dist_ps1_for_version(version: &Version) -> String {
     format!("irm  {installer_url} | iex")
 }
 
+/// Get the command to invoke to install cargo-dist via cargo-binstall
+/// (installing cargo-binstall itself first, with powershell if `powershell` is set)
+fn install_dist_binstall_for_version(
+    version: &Version,
+    powershell: bool,
+    tools: RunnerTools,
+) -> String {
+    if let Some(git) = install_dist_git(version) {
+        return git;
+    }
+    if powershell {
+        format!("Set-ExecutionPolicy Unrestricted -Scope Process; iex (iwr \"{BINSTALL_INSTALLER_URL}.ps1\").Content; cargo binstall --no-confirm cargo-dist@{version}")
+    } else {
+        let fetch = tools.fetch(&format!("{BINSTALL_INSTALLER_URL}.sh"), None);
+        format!("{fetch} | bash && cargo binstall --no-confirm cargo-dist@{version}")
+    }
+}
+
+/// Get the command to invoke to install cargo-dist from a pinned archive via sh
+fn install_dist_sh_for_archive(archive: &DistArchive, tools: RunnerTools) -> String {
+    let DistArchive { url, sha256 } = archive;
+    let path = "\"$RUNNER_TEMP/cargo-dist-archive\"";
+    let dir = "\"$RUNNER_TEMP/cargo-dist-archive-contents\"";
+    let fetch = tools.fetch(url, Some(path));
+    let check = tools.check_sha256(sha256, "$RUNNER_TEMP/cargo-dist-archive");
+    format!(
+        "{fetch} \
+         && {check} \
+         && mkdir -p {dir} ~/.cargo/bin \
+         && tar -xf {path} -C {dir} \
+         && cp \"$(find {dir} -name cargo-dist -type f | head -n 1)\" ~/.cargo/bin/"
+    )
+}
+
+/// Get the command to invoke to install cargo-dist from a pinned archive via powershell
+fn install_dist_ps1_for_archive(archive: &DistArchive) -> String {
+    let DistArchive { url, sha256 } = archive;
+    let path = "\"$env:RUNNER_TEMP/cargo-dist-archive\"";
+    let dir = "\"$env:RUNNER_TEMP/cargo-dist-archive-contents\"";
+    format!(
+        "Invoke-WebRequest {url} -OutFile {path}; \
+         if ((Get-FileHash {path} -Algorithm SHA256).Hash -ne \"{sha256}\") {{ throw \"cargo-dist archive has the wrong sha256\" }}; \
+         New-Item -ItemType Directory -Force {dir} | Out-Null; \
+         tar -xf {path} -C {dir}; \
+         Copy-Item (Get-ChildItem {dir} -Recurse -Filter cargo-dist.exe | Select-Object -First 1).FullName \"$HOME/.cargo/bin/\""
+    )
+}
+
 /// Cute little hack for developing dist itself: if we see a version like "0.0.3-github-config"
 /// then install from the main github repo with branch=config!
 fn install_dist_git(version: &Version) -> Option<String> {
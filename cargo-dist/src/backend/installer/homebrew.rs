@@ -6,16 +6,19 @@ use serde::Serialize;
 
 use super::InstallerInfo;
 use crate::{
-    backend::templates::{Templates, TEMPLATE_INSTALLER_RB},
+    backend::templates::{Templates, TEMPLATE_INSTALLER_CASK, TEMPLATE_INSTALLER_RB},
+    config::HomebrewKind,
     errors::DistResult,
     generate_checksum,
     installer::ExecutableZipFragment,
     tasks::DistGraph,
 };
 
-/// Info about a Homebrew formula
+/// Info about a Homebrew formula (or cask)
 #[derive(Debug, Clone, Serialize)]
 pub struct HomebrewInstallerInfo {
+    /// Whether this is a formula or a cask
+    pub kind: HomebrewKind,
     /// The application's name
     pub name: String,
     /// Formula class name
@@ -45,6 +48,19 @@ pub(crate) fn write_homebrew_formula(
     graph: &DistGraph,
     source_info: &HomebrewInstallerInfo,
 ) -> DistResult<()> {
+    let script = generate_homebrew_formula(templates, graph, source_info)?;
+    LocalAsset::write_new(&script, &source_info.inner.dest_path)?;
+    Ok(())
+}
+
+/// Render the Homebrew formula (or cask) to a string without writing it anywhere
+///
+/// Checksums are only included for archives that have already been built.
+pub fn generate_homebrew_formula(
+    templates: &Templates,
+    graph: &DistGraph,
+    source_info: &HomebrewInstallerInfo,
+) -> DistResult<String> {
     let mut info = source_info.clone();
 
     // Generate sha256 as late as possible; the artifacts might not exist
@@ -64,7 +80,9 @@ pub(crate) fn write_homebrew_formula(
         }
     }
 
-    let script = templates.render_file_to_clean_string(TEMPLATE_INSTALLER_RB, &info)?;
-    LocalAsset::write_new(&script, &info.inner.dest_path)?;
-    Ok(())
+    let template = match info.kind {
+        HomebrewKind::Formula => TEMPLATE_INSTALLER_RB,
+        HomebrewKind::Cask => TEMPLATE_INSTALLER_CASK,
+    };
+    templates.render_file_to_clean_string(template, &info)
 }
@@ -6,7 +6,7 @@ use camino::Utf8PathBuf;
 use serde::Serialize;
 
 use crate::{
-    config::{JinjaInstallPathStrategy, ZipStyle},
+    config::{InstallerShell, JinjaInstallPathStrategy, ZipStyle},
     TargetTriple,
 };
 
@@ -51,6 +51,10 @@ pub struct InstallerInfo {
     pub hint: String,
     /// Where to install binaries
     pub install_path: JinjaInstallPathStrategy,
+    /// Which shell the script is written for (only used by the shell installer)
+    pub shell: InstallerShell,
+    /// URL to ping after a successful install, if the user opted into that
+    pub success_url: Option<String>,
 }
 
 /// A fake fragment of an ExecutableZip artifact for installers
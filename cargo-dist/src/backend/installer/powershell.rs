@@ -13,7 +13,15 @@ pub(crate) fn write_install_ps_script(
     templates: &Templates,
     info: &InstallerInfo,
 ) -> DistResult<()> {
-    let script = templates.render_file_to_clean_string(TEMPLATE_INSTALLER_PS1, info)?;
+    let script = generate_install_ps_script(templates, info)?;
     LocalAsset::write_new(&script, &info.dest_path)?;
     Ok(())
 }
+
+/// Render installer.ps1 to a string without writing it anywhere
+pub fn generate_install_ps_script(
+    templates: &Templates,
+    info: &InstallerInfo,
+) -> DistResult<String> {
+    templates.render_file_to_clean_string(TEMPLATE_INSTALLER_PS1, info)
+}
@@ -13,7 +13,15 @@ pub(crate) fn write_install_sh_script(
     templates: &Templates,
     info: &InstallerInfo,
 ) -> DistResult<()> {
-    let script = templates.render_file_to_clean_string(TEMPLATE_INSTALLER_SH, info)?;
+    let script = generate_install_sh_script(templates, info)?;
     LocalAsset::write_new(&script, &info.dest_path)?;
     Ok(())
 }
+
+/// Render installer.sh to a string without writing it anywhere
+pub fn generate_install_sh_script(
+    templates: &Templates,
+    info: &InstallerInfo,
+) -> DistResult<String> {
+    templates.render_file_to_clean_string(TEMPLATE_INSTALLER_SH, info)
+}
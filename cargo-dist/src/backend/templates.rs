@@ -17,10 +17,20 @@ pub const TEMPLATE_INSTALLER_PS1: TemplateId = "installer/installer.ps1";
 pub const TEMPLATE_INSTALLER_SH: TemplateId = "installer/installer.sh";
 /// Template key for Homebrew formula
 pub const TEMPLATE_INSTALLER_RB: TemplateId = "installer/homebrew.rb";
+/// Template key for Homebrew cask
+pub const TEMPLATE_INSTALLER_CASK: TemplateId = "installer/homebrew_cask.rb";
 /// Template key for the npm installer dir
 pub const TEMPLATE_INSTALLER_NPM: TemplateId = "installer/npm";
 /// Template key for the github ci.yml
 pub const TEMPLATE_CI_GITHUB: TemplateId = "ci/github_ci.yml";
+/// Template key for the github composite build action
+pub const TEMPLATE_CI_GITHUB_BUILD_ACTION: TemplateId = "ci/github_build_action.yml";
+/// Template key for the gitlab .gitlab-ci.yml
+pub const TEMPLATE_CI_GITLAB: TemplateId = "ci/gitlab_ci.yml";
+/// Template key for the azure azure-pipelines.yml
+pub const TEMPLATE_CI_AZURE: TemplateId = "ci/azure_pipelines.yml";
+/// Template key for the circleci .circleci/config.yml
+pub const TEMPLATE_CI_CIRCLECI: TemplateId = "ci/circleci_config.yml";
 
 /// ID used to look up an environment in [`Templates::envs`][]
 type EnvId = &'static str;
@@ -311,9 +321,18 @@ mod test {
 
         templates.get_template_file(TEMPLATE_INSTALLER_SH).unwrap();
         templates.get_template_file(TEMPLATE_INSTALLER_RB).unwrap();
+        templates
+            .get_template_file(TEMPLATE_INSTALLER_CASK)
+            .unwrap();
         templates.get_template_file(TEMPLATE_INSTALLER_PS1).unwrap();
         templates.get_template_dir(TEMPLATE_INSTALLER_NPM).unwrap();
 
         templates.get_template_file(TEMPLATE_CI_GITHUB).unwrap();
+        templates
+            .get_template_file(TEMPLATE_CI_GITHUB_BUILD_ACTION)
+            .unwrap();
+        templates.get_template_file(TEMPLATE_CI_GITLAB).unwrap();
+        templates.get_template_file(TEMPLATE_CI_AZURE).unwrap();
+        templates.get_template_file(TEMPLATE_CI_CIRCLECI).unwrap();
     }
 }
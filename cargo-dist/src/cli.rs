@@ -35,6 +35,13 @@ pub struct Cli {
     #[clap(help_heading = "GLOBAL OPTIONS", global = true)]
     pub verbose: LevelFilter,
 
+    /// Don't report progress (like which packages were found, or which files `generate` wrote)
+    ///
+    /// Warnings and errors are still printed, see --verbose for those.
+    #[clap(long)]
+    #[clap(help_heading = "GLOBAL OPTIONS", global = true)]
+    pub quiet: bool,
+
     /// The format of the output
     #[clap(long, short, value_enum)]
     #[clap(default_value_t = OutputFormat::Human)]
@@ -110,6 +117,14 @@ pub enum Commands {
     /// Build artifacts
     #[clap(disable_version_flag = true)]
     Build(BuildArgs),
+    /// Build artifacts for trying out locally, without any release machinery
+    ///
+    /// This builds the same archives (and installers, checksums...) that 'build' would
+    /// for the host system, or for the targets you pass with --target. Unlike 'build'
+    /// it doesn't care whether your CI is up to date, and once everything is built
+    /// it copies the artifacts into --output-dir so they're easy to find.
+    #[clap(disable_version_flag = true)]
+    BuildLocal(BuildLocalArgs),
     /// Setup or update cargo-dist
     ///
     /// This will interactively guide you through the process of selecting configuration options
@@ -162,6 +177,31 @@ pub enum Commands {
     ///
     #[clap(disable_version_flag = true)]
     Plan(PlanArgs),
+    /// Print the config cargo-dist resolved for your workspace
+    ///
+    /// This is the [workspace.metadata.dist] config and the merged config of each
+    /// package, with any unset values filled in with their defaults. Handy for
+    /// figuring out why a setting isn't taking effect.
+    ///
+    /// This is printed as TOML, or as JSON with --output-format=json.
+    #[clap(disable_version_flag = true)]
+    PrintConfig(PrintConfigArgs),
+    /// Print what cargo-dist's Github CI will do, as JSON
+    ///
+    /// This is everything release.yml gets generated from (the build matrix and
+    /// the runner each task uses, the global task, publish jobs...), for tools that
+    /// want to know what CI will do without parsing the YAML. Nothing is written to disk.
+    #[clap(disable_version_flag = true)]
+    CiInfo(CiInfoArgs),
+    /// Check that every artifact in dist-manifest.json made it onto the Github Release
+    ///
+    /// This downloads the assets of the Github Release for --tag (with the gh cli, which
+    /// needs to be installed and logged in) and reports any artifact the manifest lists
+    /// that's missing, or doesn't have the size or checksum the manifest says it should.
+    /// It checks against the dist-manifest.json on the release unless you pass --manifest,
+    /// in which case --tag defaults to that manifest's announcement tag.
+    #[clap(disable_version_flag = true)]
+    VerifyManifest(VerifyManifestArgs),
 }
 
 #[derive(Args, Clone, Debug)]
@@ -187,6 +227,36 @@ pub struct BuildArgs {
     #[clap(long, short, value_enum)]
     #[clap(default_value_t = ArtifactMode::Host)]
     pub artifacts: ArtifactMode,
+
+    /// Cargo features to build with, instead of the ones your packages are configured with
+    ///
+    /// This is how CI applies build-overrides to the targets they're set for.
+    /// Builds for feature-variants aren't affected.
+    #[clap(long, value_delimiter(','))]
+    pub features: Option<Vec<String>>,
+
+    /// Build without default features, regardless of your packages' default-features
+    #[clap(long)]
+    pub no_default_features: bool,
+
+    /// Build with all features, regardless of your packages' all-features
+    #[clap(long)]
+    pub all_features: bool,
+}
+
+impl BuildArgs {
+    /// Get the feature flags that should override the packages' config
+    pub fn build_override(&self) -> cargo_dist::config::BuildOverride {
+        cargo_dist::config::BuildOverride {
+            // `--features=` means "no features", not one feature with an empty name
+            features: self
+                .features
+                .as_ref()
+                .map(|features| features.iter().filter(|f| !f.is_empty()).cloned().collect()),
+            no_default_features: self.no_default_features.then_some(true),
+            all_features: self.all_features.then_some(true),
+        }
+    }
 }
 
 /// How we should select the artifacts to build
@@ -258,9 +328,23 @@ pub struct GenerateArgs {
     pub mode: Vec<GenerateMode>,
 
     /// Check if the generated output differs from on-disk config without writing it
+    ///
+    /// Every CI file that would be generated is checked, along with the installers
+    /// (whatever the mode), and all the ones that are out of date get reported together.
+    /// Installers are only checked if a previous build left them in the dist dir, since
+    /// they aren't kept in your repository. dist-manifest.json is never checked, since
+    /// it's printed by 'build' rather than written anywhere.
     #[clap(long)]
     #[clap(default_value_t = false)]
     pub check: bool,
+
+    /// Generate files under this directory instead of the workspace root
+    ///
+    /// This is mostly useful for testing, or tooling that wants to inspect
+    /// the output without touching the workspace. --check also compares
+    /// against the files under this directory.
+    #[clap(long)]
+    pub output_dir: Option<Utf8PathBuf>,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -269,6 +353,14 @@ pub struct GenerateCiArgs {
     #[clap(long)]
     #[clap(default_value_t = false)]
     pub check: bool,
+
+    /// Generate files under this directory instead of the workspace root
+    ///
+    /// This is mostly useful for testing, or tooling that wants to inspect
+    /// the output without touching the workspace. --check also compares
+    /// against the files under this directory.
+    #[clap(long)]
+    pub output_dir: Option<Utf8PathBuf>,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -279,6 +371,13 @@ pub struct HelpMarkdownArgs {}
 pub enum CiStyle {
     /// Generate github CI that uploads to github releases
     Github,
+    /// Generate gitlab CI that uploads to gitlab releases
+    Gitlab,
+    /// Generate azure pipelines that upload to azure artifacts
+    Azure,
+    /// Generate circleci config that uploads to github releases
+    #[value(name = "circleci")]
+    CircleCi,
 }
 
 impl CiStyle {
@@ -286,6 +385,9 @@ impl CiStyle {
     pub fn to_lib(self) -> cargo_dist::config::CiStyle {
         match self {
             CiStyle::Github => cargo_dist::config::CiStyle::Github,
+            CiStyle::Gitlab => cargo_dist::config::CiStyle::Gitlab,
+            CiStyle::Azure => cargo_dist::config::CiStyle::Azure,
+            CiStyle::CircleCi => cargo_dist::config::CiStyle::CircleCi,
         }
     }
 }
@@ -315,6 +417,15 @@ impl InstallerStyle {
     }
 }
 
+#[derive(Args, Clone, Debug)]
+pub struct BuildLocalArgs {
+    /// The directory to copy the built artifacts into
+    ///
+    /// Defaults to target/distrib-local/ in your workspace.
+    #[clap(long)]
+    pub output_dir: Option<Utf8PathBuf>,
+}
+
 #[derive(Args, Clone, Debug)]
 pub struct ManifestArgs {
     // Add the args from the "real" build command
@@ -325,6 +436,26 @@ pub struct ManifestArgs {
 #[derive(Args, Clone, Debug)]
 pub struct PlanArgs {}
 
+#[derive(Args, Clone, Debug)]
+pub struct PrintConfigArgs {}
+
+#[derive(Args, Clone, Debug)]
+pub struct CiInfoArgs {}
+
+#[derive(Args, Clone, Debug)]
+pub struct VerifyManifestArgs {
+    /// The dist-manifest.json to check the release against
+    ///
+    /// Defaults to the dist-manifest.json uploaded to the release.
+    #[clap(long)]
+    pub manifest: Option<Utf8PathBuf>,
+    /// The Github repo the release is in, in owner/name format
+    ///
+    /// Defaults to the repo of the current directory, as gh sees it.
+    #[clap(long)]
+    pub repo: Option<String>,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum OutputFormat {
     Human,
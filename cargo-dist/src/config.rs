@@ -5,12 +5,12 @@ use camino::{Utf8Path, Utf8PathBuf};
 use miette::Report;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use tracing::log::warn;
+use tracing::warn;
 
 use crate::errors::Result;
 use crate::{
     errors::{DistError, DistResult},
-    TargetTriple, METADATA_DIST,
+    SortedMap, TargetTriple, METADATA_DIST,
 };
 
 /// Contents of METADATA_DIST in Cargo.toml files
@@ -255,8 +255,587 @@ pub struct DistMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "create-release")]
     pub create_release: Option<bool>,
+
+    /// Whether generating CI should fail if it would use a Github runner image
+    /// that is known to be deprecated (or soon to be).
+    ///
+    /// If false (default), cargo-dist will only warn about these runners and
+    /// suggest a replacement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "deny-deprecated-runners")]
+    pub deny_deprecated_runners: Option<bool>,
+
+    /// Extra keys to add to every entry of the Github CI build matrix
+    ///
+    /// These are passed through verbatim, so that custom steps you add to
+    /// your release workflow can refer to them as `${{ matrix.my-key }}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-matrix-extra")]
+    pub github_matrix_extra: Option<SortedMap<String, serde_json::Value>>,
+
+    /// Whether CI should run the task that builds global artifacts (like installers)
+    ///
+    /// * `"auto"`: (default) run it only if some release has global artifacts
+    /// * `true`: always run it, even if there's nothing to build (useful for custom steps)
+    /// * `false`: never run it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "global-build")]
+    pub global_build: Option<GlobalBuildMode>,
+
+    /// Whether to produce a `<release>-build-matrix.json` global artifact recording
+    /// exactly what Github CI builds for each release (for auditing purposes)
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "build-matrix")]
+    pub build_matrix: Option<bool>,
+
+    /// Conditions under which each publish job should run in CI
+    ///
+    /// These are Github Actions expressions rendered verbatim as the `if:` of the
+    /// job, so you can e.g. only publish stable releases to Homebrew with
+    /// `homebrew = "${{ !fromJson(needs.plan.outputs.val).announcement_is_prerelease }}"`
+    ///
+    /// (defaults to none, meaning every publish job always runs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "publish-job-conditions")]
+    pub publish_job_conditions: Option<SortedMap<PublishStyle, String>>,
+
+    /// The name of the CI secret each publish job should use to authenticate
+    ///
+    /// e.g. `homebrew = "MY_TAP_TOKEN"` will make the Homebrew publish job
+    /// use `secrets.MY_TAP_TOKEN` instead of the default `secrets.HOMEBREW_TAP_TOKEN`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "publish-job-secrets")]
+    pub publish_job_secrets: Option<SortedMap<PublishStyle, String>>,
+
+    /// The archive format to use for specific target triples
+    ///
+    /// This takes precedence over windows-archive and unix-archive, e.g.
+    /// `x86_64-unknown-linux-musl = ".tar.gz"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "target-archive")]
+    pub target_archive: Option<SortedMap<TargetTriple, ZipStyle>>,
+
+    /// Groups of binaries (possibly from different packages) to also ship together
+    /// in a single archive, keyed by the name of the bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "bundles")]
+    pub bundles: Option<SortedMap<String, BundleConfig>>,
+
+    /// Homebrew taps to publish to, each getting either a formula or a cask
+    ///
+    /// This is a more general version of `tap`, for when you need more than one tap
+    /// or want a cask. If both are set, `tap` is treated as the first entry of this
+    /// list, publishing a formula.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "taps")]
+    pub taps: Option<Vec<HomebrewTap>>,
+
+    /// Whether auto-includes should pick up `README*` files
+    ///
+    /// Defaults to true, and does nothing if auto-includes are disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "include-readme")]
+    pub include_readme: Option<bool>,
+
+    /// Whether auto-includes should pick up `(UN)LICENSE*` files
+    ///
+    /// Defaults to true, and does nothing if auto-includes are disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "include-license")]
+    pub include_license: Option<bool>,
+
+    /// Whether the Github Release body should end with a table of every artifact,
+    /// with its platform, size, and checksum
+    ///
+    /// The table is built by the publish job from the files that were actually uploaded,
+    /// and replaces the download table that is otherwise computed at plan time.
+    /// Defaults to false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "release-notes-table")]
+    pub release_notes_table: Option<bool>,
+
+    /// Whether to only let one release workflow run at a time (per ref), and if so
+    /// whether new runs should queue up behind it or cancel it
+    ///
+    /// By default new runs queue up (except in reusable workflows, which get no limit).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "concurrency")]
+    pub concurrency: Option<ConcurrencyMode>,
+
+    /// Github Actions steps to run at the start of every job that runs cargo-dist
+    ///
+    /// Each entry is a step as you'd write it in a workflow (e.g. with `name`, `run`,
+    /// and `env` keys), and they run right after the repository is checked out,
+    /// before any toolchains are installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-setup-steps")]
+    pub github_setup_steps: Option<Vec<SortedMap<String, serde_json::Value>>>,
+
+    /// Archives of cargo-dist that CI should install it from, by the target triple they're for
+    ///
+    /// CI runners whose platform has an entry here will download that archive and check its
+    /// sha256 instead of running the cargo-dist installer script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cargo-dist-archives")]
+    pub cargo_dist_archives: Option<SortedMap<TargetTriple, DistArchive>>,
+
+    /// How many shards to split each build task (and the tests from run-tests it runs) into
+    ///
+    /// Each shard is a separate entry in the build matrix with `CARGO_DIST_SHARD` (0-based)
+    /// and `CARGO_DIST_SHARD_COUNT` set, running its share of the tests with cargo-nextest.
+    /// Only shard 0 builds and uploads the task's artifacts.
+    ///
+    /// (defaults to 1, meaning no sharding)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "test-shards")]
+    pub test_shards: Option<u32>,
+
+    /// Whether to generate Github CI as a reusable workflow (`on: workflow_call`)
+    ///
+    /// Instead of running on tag pushes, other workflows (possibly in other repos)
+    /// call it with a `tag` input naming the tag to release.
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-reusable-workflow")]
+    pub github_reusable_workflow: Option<bool>,
+
+    /// Whether to also generate a composite Github Action that installs cargo-dist
+    /// and builds artifacts, for use in your own workflows
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-build-action")]
+    pub github_build_action: Option<bool>,
+
+    /// The name that publish jobs (like homebrew) commit as
+    ///
+    /// (defaults to "axo bot")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "git-author-name")]
+    pub git_author_name: Option<String>,
+
+    /// The email that publish jobs (like homebrew) commit as
+    ///
+    /// (defaults to "admin+bot@axo.dev")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "git-author-email")]
+    pub git_author_email: Option<String>,
+
+    /// Whether publishing a prerelease should first delete every asset already
+    /// on its Github Release (for rolling prereleases like a moving `nightly` tag)
+    ///
+    /// This never happens for stable releases.
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "prune-prerelease-assets")]
+    pub prune_prerelease_assets: Option<bool>,
+
+    /// Globs for the names of workspace members that can be released
+    ///
+    /// If set, packages whose names don't match any of these won't be released.
+    /// `*` matches any run of characters, and `?` matches any one character.
+    ///
+    /// (defaults to every package)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "include-packages")]
+    pub include_packages: Option<Vec<String>>,
+
+    /// Globs for the names of workspace members that should never be released
+    ///
+    /// This takes priority over include-packages. Uses the same syntax.
+    ///
+    /// (defaults to none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "exclude-packages")]
+    pub exclude_packages: Option<Vec<String>>,
+
+    /// Which shell the shell installer is written for
+    ///
+    /// * `"sh"`: (default) portable POSIX sh (as run by `| sh`)
+    /// * `"bash"`: bash (as run by `| bash`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "installer-shell")]
+    pub installer_shell: Option<InstallerShell>,
+
+    /// A URL the shell and powershell installers should ping after a successful install
+    ///
+    /// This is strictly opt-in: if it isn't set the installers never phone home.
+    /// The ping is a GET with the app name, version, and target as query params,
+    /// and users can disable it with INSTALLER_NO_TELEMETRY=1 or DO_NOT_TRACK=1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "install-success-url")]
+    pub install_success_url: Option<String>,
+
+    /// Github runner labels to build specific targets on, instead of the default runner
+    ///
+    /// This is for targets that need a bigger (or otherwise special) runner,
+    /// like `{ x86_64-unknown-linux-gnu = "ubuntu-22.04-16core" }`, or a list of
+    /// labels for self-hosted runners, like `["self-hosted", "linux", "x64"]`.
+    /// Targets that aren't listed keep using the default runner.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-custom-runners")]
+    pub github_custom_runners:
+        Option<SortedMap<TargetTriple, cargo_dist_schema::GithubRunnerLabels>>,
+
+    /// Whether to pass `--locked` or `--frozen` to cargo when building
+    ///
+    /// * `"locked"`: fail if Cargo.lock is missing or out of date
+    /// * `"frozen"`: same as locked, but also don't touch the network
+    ///
+    /// (defaults to neither)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cargo-lock-mode")]
+    pub cargo_lock_mode: Option<CargoLockMode>,
+
+    /// Other names to use for the Github Actions that Github CI uses
+    ///
+    /// Keys can either be a whole action (`"actions/upload-artifact"`) or just an
+    /// owner (`"actions"`), and are replaced with the value (so `"myorg/upload-artifact"`
+    /// or `"myorg"`). The version of the action is kept as-is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-action-renames")]
+    pub github_action_renames: Option<SortedMap<String, String>>,
+
+    /// Whether to sign every artifact of the Github Release with sigstore's cosign
+    ///
+    /// This is "keyless" signing, which uses the OIDC identity of the Github CI run,
+    /// so there are no keys to manage. Each artifact gets a `.sig` and `.pem` uploaded
+    /// next to it, and the manifest records how to verify it.
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cosign")]
+    pub cosign: Option<bool>,
+
+    /// A command that publish jobs run to fetch secrets (e.g. from Vault) before doing
+    /// anything sensitive
+    ///
+    /// Every `NAME=value` line it prints gets added to the environment of the rest of
+    /// the job (with the value masked in the logs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "fetch-secrets-command")]
+    pub fetch_secrets_command: Option<String>,
+
+    /// The name of the Github CI workflow (as shown in the Actions UI)
+    ///
+    /// (defaults to "Release")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "workflow-name")]
+    pub workflow_name: Option<String>,
+
+    /// Whether Github CI should skip publishing when it runs in a fork of the repository
+    ///
+    /// The repository is the one in the `repository` key of your Cargo.toml.
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "skip-forks")]
+    pub skip_forks: Option<bool>,
+
+    /// The CARGO_TARGET_DIR that Github CI builds in (e.g. a bigger disk mounted on the runner)
+    ///
+    /// This is set verbatim in the env of the build jobs, so it can use Github Actions
+    /// expressions like `${{ runner.temp }}`.
+    ///
+    /// (defaults to cargo's usual target dir)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cargo-target-dir")]
+    pub cargo_target_dir: Option<String>,
+
+    /// Whether Github CI should free up disk space on Linux runners before building,
+    /// by deleting preinstalled SDKs (Android, .NET, ...) that builds don't need
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "free-disk-space")]
+    pub free_disk_space: Option<bool>,
+
+    /// Whether Github CI should create the Github Release as a draft up front (in the plan job),
+    /// have each build job upload its artifacts to it, and only undraft it at the end
+    ///
+    /// This only does anything if create-release is enabled.
+    ///
+    /// (defaults to false, meaning the release is created with everything at the end)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "early-draft-release")]
+    pub early_draft_release: Option<bool>,
+
+    /// The format of the announcement's title (e.g. the name of the Github Release)
+    ///
+    /// `{tag}` is replaced with the git tag, and `{version}` with the version being
+    /// announced (or the tag, if there isn't one consistent version).
+    ///
+    /// (defaults to the title of the changelog entry, or the tag if there isn't one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "announcement-title")]
+    pub announcement_title: Option<String>,
+
+    /// Installers that are allowed to fail to build without blocking the release
+    ///
+    /// Github CI builds each of these in its own task with continue-on-error, so a
+    /// failure just means that installer is missing from the release. Every other
+    /// installer is required, and still gets built by the global task.
+    ///
+    /// (defaults to none, meaning every installer is required)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "optional-installers")]
+    pub optional_installers: Option<Vec<InstallerStyle>>,
+
+    /// How many days Github should keep the artifacts each job uploads for later jobs
+    ///
+    /// (defaults to the repository's setting)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "artifact-retention-days")]
+    pub artifact_retention_days: Option<u32>,
+
+    /// The compression level (0-9) Github CI uploads artifacts for later jobs with
+    ///
+    /// (defaults to upload-artifact's default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "artifact-compression-level")]
+    pub artifact_compression_level: Option<u8>,
+
+    /// Per-job overrides of artifact-retention-days and artifact-compression-level,
+    /// keyed by the name of the job (like "upload-local-artifacts")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "artifact-upload-overrides")]
+    pub artifact_upload_overrides: Option<SortedMap<String, ArtifactUploadSettings>>,
+
+    /// System packages Github CI should install before building local artifacts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "dependencies")]
+    pub dependencies: Option<SystemDependencies>,
+
+    /// Per-target overrides of dependencies, keyed by target triple
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "dependency-overrides")]
+    pub dependency_overrides: Option<SortedMap<TargetTriple, SystemDependencies>>,
+
+    /// Extra files (or globs) whose contents should be part of Github CI's rust-cache key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cache-key-files")]
+    pub cache_key_files: Option<Vec<String>>,
+
+    /// The prefix of Github CI's rust-cache keys (rust-cache's prefix-key)
+    ///
+    /// (defaults to rust-cache's default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cache-prefix-key")]
+    pub cache_prefix_key: Option<String>,
+
+    /// Whether the release workflow can also be run by hand (`on: workflow_dispatch`),
+    /// with an input that picks whether to publish or just build artifacts
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-workflow-dispatch")]
+    pub github_workflow_dispatch: Option<bool>,
+
+    /// Whether Github CI's build jobs should cache their builds (with rust-cache)
+    ///
+    /// (defaults to true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cache")]
+    pub cache: Option<bool>,
+
+    /// Per-target directories to find built binaries in, instead of where cargo says it put them
+    ///
+    /// (paths are relative to the workspace root)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "binary-dirs")]
+    pub binary_dirs: Option<SortedMap<TargetTriple, Utf8PathBuf>>,
+
+    /// Extra builds of a package's binaries with different cargo features, keyed by a name
+    /// that gets put in their archives' names
+    ///
+    /// (defaults to none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "feature-variants")]
+    pub feature_variants: Option<SortedMap<String, FeatureVariant>>,
+
+    /// Whether Github CI should run the test suite (once, on Linux) before building anything
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "run-tests")]
+    pub run_tests: Option<bool>,
+
+    /// Commit SHAs to pin the Github Actions that Github CI uses to, instead of their tags
+    ///
+    /// Keys are whole actions (`"actions/checkout"`), before any github-action-renames.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-action-pins")]
+    pub github_action_pins: Option<SortedMap<String, String>>,
+
+    /// How CI should install cargo-dist on each OS its runners use
+    ///
+    /// OSes without an entry use the installer script for their shell (sh for linux
+    /// and macos, powershell for windows). cargo-dist-archives take precedence over this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "install-dist-methods")]
+    pub install_dist_methods: Option<SortedMap<RunnerOs, InstallDistMethod>>,
+
+    /// Cargo feature flags to build specific targets with, instead of the packages' own
+    ///
+    /// CI passes these to `cargo dist build` on whichever runner builds that target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "build-overrides")]
+    pub build_overrides: Option<SortedMap<TargetTriple, BuildOverride>>,
+
+    /// How many minutes Github CI jobs may run before they're cancelled
+    ///
+    /// Either one number for every job, or a table with separate `local`, `global`,
+    /// and `publish` values (defaults to 60 for each).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "timeout-minutes")]
+    pub timeout_minutes: Option<JobTimeouts>,
+
+    /// Where Github CI jobs hand artifacts off to each other
+    ///
+    /// Defaults to Github's own artifact storage (`actions/upload-artifact`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "artifact-transport")]
+    pub artifact_transport: Option<ArtifactTransport>,
+
+    /// Github runner labels to fall back to for specific targets, in order
+    ///
+    /// These go after the target's normal (or custom) runner in its job's `runs-on`,
+    /// like `{ x86_64-unknown-linux-gnu = ["self-hosted-backup"] }`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-fallback-runners")]
+    pub github_fallback_runners: Option<SortedMap<TargetTriple, Vec<String>>>,
+
+    /// Whether to Authenticode-sign Windows binaries and installers in Github CI
+    ///
+    /// The certificate (a base64-encoded .pfx) and its password come from the Github
+    /// secrets named by windows-signing-certificate-secret and windows-signing-password-secret.
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "windows-signing")]
+    pub windows_signing: Option<bool>,
+
+    /// The Github secret holding the base64-encoded .pfx certificate for windows-signing
+    ///
+    /// (defaults to WINDOWS_CERTIFICATE)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "windows-signing-certificate-secret")]
+    pub windows_signing_certificate_secret: Option<String>,
+
+    /// The Github secret holding the password of the windows-signing certificate
+    ///
+    /// (defaults to WINDOWS_CERTIFICATE_PASSWORD)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "windows-signing-password-secret")]
+    pub windows_signing_password_secret: Option<String>,
+
+    /// The name to give each build job in the Github Actions UI
+    ///
+    /// `{targets}` gets replaced with the targets the job builds, and `{runner}` with
+    /// the runner it runs on, like `"build ({targets})"`. (defaults to Github's own name)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-build-job-name")]
+    pub github_build_job_name: Option<String>,
+
+    /// Whether Github CI should record where each artifact was built in the published
+    /// dist-manifest.json (the workflow run, commit, and workflow identity)
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "manifest-provenance")]
+    pub manifest_provenance: Option<bool>,
+
+    /// Whether Github CI should generate SLSA build provenance attestations for every
+    /// artifact of the Github Release (with actions/attest-build-provenance)
+    ///
+    /// (defaults to false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-attestations")]
+    pub github_attestations: Option<bool>,
+
+    /// Extra permissions to give specific jobs of Github CI, keyed by the name of the
+    /// job (like "publish-release"), then by scope (like "packages"), with a level of
+    /// "read", "write", or "none"
+    ///
+    /// Each job already gets the permissions cargo-dist knows it needs, so this is for
+    /// things you've added to the workflow (like custom publishing steps).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-permissions")]
+    pub github_permissions: Option<SortedMap<String, SortedMap<String, String>>>,
+
+    /// Environment variables to set for every job of Github CI
+    ///
+    /// Values can use Github expressions (like `${{ secrets.FOO }}`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-env")]
+    pub github_env: Option<SortedMap<String, String>>,
+
+    /// Environment variables to set only in the Github CI jobs that build certain targets
+    ///
+    /// Values can reference secrets (like `${{ secrets.FOO }}`), but not other expressions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-target-env")]
+    pub github_target_env: Option<SortedMap<TargetTriple, SortedMap<String, String>>>,
+
+    /// Per-target cargo config (like the linker) to build with, on top of your
+    /// .cargo/config.toml, as if it was in a `[target.<triple>]` table there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cargo-target-config")]
+    pub cargo_target_config: Option<SortedMap<TargetTriple, CargoTargetConfig>>,
+
+    /// Cargo registries the crates-io publish job publishes to, each in its own job
+    ///
+    /// (defaults to just crates.io)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cargo-registries")]
+    pub cargo_registries: Option<Vec<CargoRegistry>>,
+
+    /// Whether to generate a separate Github CI workflow for each app, that only its
+    /// own tags trigger, instead of one release.yml for the whole workspace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "github-split-workflows")]
+    pub github_split_workflows: Option<bool>,
+
+    /// The prefix of the git tags that release this package, instead of its name
+    /// (like "cli" for tags like "cli-v1.0.0")
+    ///
+    /// Every package needs its own prefix, so this is never inherited from the workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "tag-prefix")]
+    pub tag_prefix: Option<String>,
+
+    /// The URL that published artifacts can be downloaded from, as recorded in
+    /// dist-manifest.json, with `{tag}` and `{filename}` placeholders
+    ///
+    /// (defaults to the artifact's URL on the Github Release)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "download-url-template")]
+    pub download_url_template: Option<String>,
+
+    /// What the binaries we build should do when they panic
+    ///
+    /// * `"unwind"`: unwind the stack, like rust does by default
+    /// * `"abort"`: abort the process immediately, for smaller binaries
+    ///
+    /// (defaults to whatever the dist profile says)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "panic-strategy")]
+    pub panic_strategy: Option<PanicStrategy>,
 }
 
+/// The name publish jobs commit as if git-author-name isn't set
+pub(crate) const DEFAULT_GIT_AUTHOR_NAME: &str = "axo bot";
+/// The email publish jobs commit as if git-author-email isn't set
+pub(crate) const DEFAULT_GIT_AUTHOR_EMAIL: &str = "admin+bot@axo.dev";
+/// The name of the Github CI workflow if workflow-name isn't set
+pub(crate) const DEFAULT_WORKFLOW_NAME: &str = "Release";
+/// How many minutes Github CI jobs get if timeout-minutes doesn't say
+pub(crate) const DEFAULT_TIMEOUT_MINUTES: u32 = 60;
+
 impl DistMetadata {
     /// Apply the base path to any relative paths contained in this DistMetadata
     pub fn make_relative_to(&mut self, base_path: &Utf8Path) {
@@ -286,12 +865,87 @@ impl DistMetadata {
             create_release: _,
             pr_run_mode: _,
             allow_dirty: _,
+            deny_deprecated_runners: _,
+            github_matrix_extra: _,
+            global_build: _,
+            build_matrix: _,
+            publish_job_conditions: _,
+            publish_job_secrets: _,
+            target_archive: _,
+            bundles: _,
+            taps: _,
+            include_readme: _,
+            include_license: _,
+            release_notes_table: _,
+            concurrency: _,
+            github_setup_steps: _,
+            cargo_dist_archives: _,
+            test_shards: _,
+            github_reusable_workflow: _,
+            github_build_action: _,
+            git_author_name: _,
+            git_author_email: _,
+            prune_prerelease_assets: _,
+            include_packages: _,
+            exclude_packages: _,
+            installer_shell: _,
+            install_success_url: _,
+            github_custom_runners: _,
+            cargo_lock_mode: _,
+            github_action_renames: _,
+            cosign: _,
+            fetch_secrets_command: _,
+            workflow_name: _,
+            skip_forks: _,
+            cargo_target_dir: _,
+            free_disk_space: _,
+            early_draft_release: _,
+            announcement_title: _,
+            optional_installers: _,
+            artifact_retention_days: _,
+            artifact_compression_level: _,
+            artifact_upload_overrides: _,
+            dependencies: _,
+            dependency_overrides: _,
+            cache_key_files: _,
+            cache_prefix_key: _,
+            github_workflow_dispatch: _,
+            cache: _,
+            binary_dirs,
+            feature_variants: _,
+            run_tests: _,
+            github_action_pins: _,
+            install_dist_methods: _,
+            build_overrides: _,
+            timeout_minutes: _,
+            artifact_transport: _,
+            github_fallback_runners: _,
+            windows_signing: _,
+            windows_signing_certificate_secret: _,
+            windows_signing_password_secret: _,
+            github_build_job_name: _,
+            manifest_provenance: _,
+            github_attestations: _,
+            github_permissions: _,
+            github_env: _,
+            github_target_env: _,
+            cargo_target_config: _,
+            cargo_registries: _,
+            github_split_workflows: _,
+            tag_prefix: _,
+            download_url_template: _,
+            panic_strategy: _,
         } = self;
         if let Some(include) = include {
             for include in include {
                 *include = base_path.join(&*include);
             }
         }
+        if let Some(binary_dirs) = binary_dirs {
+            for dir in binary_dirs.values_mut() {
+                *dir = base_path.join(&*dir);
+            }
+        }
     }
 
     /// Merge a workspace config into a package config (self)
@@ -326,29 +980,171 @@ impl DistMetadata {
             create_release,
             pr_run_mode: _,
             allow_dirty,
+            deny_deprecated_runners,
+            github_matrix_extra,
+            global_build,
+            build_matrix,
+            publish_job_conditions,
+            publish_job_secrets,
+            target_archive,
+            bundles,
+            taps,
+            include_readme,
+            include_license,
+            release_notes_table,
+            concurrency,
+            github_setup_steps,
+            cargo_dist_archives,
+            test_shards,
+            github_reusable_workflow,
+            github_build_action,
+            git_author_name,
+            git_author_email,
+            prune_prerelease_assets,
+            include_packages,
+            exclude_packages,
+            installer_shell,
+            install_success_url,
+            github_custom_runners,
+            cargo_lock_mode,
+            github_action_renames,
+            cosign,
+            fetch_secrets_command,
+            workflow_name,
+            skip_forks,
+            cargo_target_dir,
+            free_disk_space,
+            early_draft_release,
+            announcement_title,
+            optional_installers,
+            artifact_retention_days,
+            artifact_compression_level,
+            artifact_upload_overrides,
+            dependencies,
+            dependency_overrides,
+            cache_key_files,
+            cache_prefix_key,
+            github_workflow_dispatch,
+            cache,
+            binary_dirs,
+            feature_variants,
+            run_tests,
+            github_action_pins,
+            install_dist_methods,
+            build_overrides,
+            timeout_minutes,
+            artifact_transport,
+            github_fallback_runners,
+            windows_signing,
+            windows_signing_certificate_secret,
+            windows_signing_password_secret,
+            github_build_job_name,
+            manifest_provenance,
+            github_attestations,
+            github_permissions,
+            github_env,
+            github_target_env,
+            cargo_target_config,
+            cargo_registries,
+            github_split_workflows,
+            // Every package needs its own prefix, so it's never inherited
+            tag_prefix: _,
+            download_url_template,
+            panic_strategy,
         } = self;
 
         // Check for global settings on local packages
-        if cargo_dist_version.is_some() {
-            warn!("package.metadata.dist.cargo-dist-version is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if rust_toolchain_version.is_some() {
-            warn!("package.metadata.dist.rust-toolchain-version is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if ci.is_some() {
-            warn!("package.metadata.dist.ci is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if precise_builds.is_some() {
-            warn!("package.metadata.dist.precise-builds is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if merge_tasks.is_some() {
-            warn!("package.metadata.dist.merge-tasks is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if fail_fast.is_some() {
-            warn!("package.metadata.dist.fail-fast is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
-        }
-        if create_release.is_some() {
-            warn!("package.metadata.dist.create-release is set, but this is only accepted in workspace.metadata (value is being ignored): {}", package_manifest_path);
+        let global_only = [
+            ("cargo-dist-version", cargo_dist_version.is_some()),
+            ("rust-toolchain-version", rust_toolchain_version.is_some()),
+            ("ci", ci.is_some()),
+            ("precise-builds", precise_builds.is_some()),
+            ("merge-tasks", merge_tasks.is_some()),
+            ("fail-fast", fail_fast.is_some()),
+            ("create-release", create_release.is_some()),
+            ("release-notes-table", release_notes_table.is_some()),
+            ("concurrency", concurrency.is_some()),
+            ("github-setup-steps", github_setup_steps.is_some()),
+            ("cargo-dist-archives", cargo_dist_archives.is_some()),
+            ("test-shards", test_shards.is_some()),
+            (
+                "github-reusable-workflow",
+                github_reusable_workflow.is_some(),
+            ),
+            ("github-build-action", github_build_action.is_some()),
+            ("git-author-name", git_author_name.is_some()),
+            ("git-author-email", git_author_email.is_some()),
+            ("prune-prerelease-assets", prune_prerelease_assets.is_some()),
+            ("include-packages", include_packages.is_some()),
+            ("exclude-packages", exclude_packages.is_some()),
+            ("github-custom-runners", github_custom_runners.is_some()),
+            ("cargo-lock-mode", cargo_lock_mode.is_some()),
+            ("github-action-renames", github_action_renames.is_some()),
+            ("cosign", cosign.is_some()),
+            ("fetch-secrets-command", fetch_secrets_command.is_some()),
+            ("workflow-name", workflow_name.is_some()),
+            ("skip-forks", skip_forks.is_some()),
+            ("cargo-target-dir", cargo_target_dir.is_some()),
+            ("free-disk-space", free_disk_space.is_some()),
+            ("early-draft-release", early_draft_release.is_some()),
+            ("announcement-title", announcement_title.is_some()),
+            ("optional-installers", optional_installers.is_some()),
+            ("artifact-retention-days", artifact_retention_days.is_some()),
+            (
+                "artifact-compression-level",
+                artifact_compression_level.is_some(),
+            ),
+            (
+                "artifact-upload-overrides",
+                artifact_upload_overrides.is_some(),
+            ),
+            ("dependencies", dependencies.is_some()),
+            ("dependency-overrides", dependency_overrides.is_some()),
+            ("cache-key-files", cache_key_files.is_some()),
+            ("cache-prefix-key", cache_prefix_key.is_some()),
+            (
+                "github-workflow-dispatch",
+                github_workflow_dispatch.is_some(),
+            ),
+            ("cache", cache.is_some()),
+            ("binary-dirs", binary_dirs.is_some()),
+            ("run-tests", run_tests.is_some()),
+            ("github-action-pins", github_action_pins.is_some()),
+            ("install-dist-methods", install_dist_methods.is_some()),
+            ("build-overrides", build_overrides.is_some()),
+            ("timeout-minutes", timeout_minutes.is_some()),
+            ("artifact-transport", artifact_transport.is_some()),
+            ("github-fallback-runners", github_fallback_runners.is_some()),
+            ("windows-signing", windows_signing.is_some()),
+            (
+                "windows-signing-certificate-secret",
+                windows_signing_certificate_secret.is_some(),
+            ),
+            (
+                "windows-signing-password-secret",
+                windows_signing_password_secret.is_some(),
+            ),
+            ("github-build-job-name", github_build_job_name.is_some()),
+            ("manifest-provenance", manifest_provenance.is_some()),
+            ("github-attestations", github_attestations.is_some()),
+            ("github-permissions", github_permissions.is_some()),
+            ("github-env", github_env.is_some()),
+            ("github-target-env", github_target_env.is_some()),
+            ("cargo-target-config", cargo_target_config.is_some()),
+            ("cargo-registries", cargo_registries.is_some()),
+            ("github-split-workflows", github_split_workflows.is_some()),
+            ("download-url-template", download_url_template.is_some()),
+            ("panic-strategy", panic_strategy.is_some()),
+            ("deny-deprecated-runners", deny_deprecated_runners.is_some()),
+            ("github-matrix-extra", github_matrix_extra.is_some()),
+            ("global-build", global_build.is_some()),
+            ("build-matrix", build_matrix.is_some()),
+            ("publish-job-conditions", publish_job_conditions.is_some()),
+            ("publish-job-secrets", publish_job_secrets.is_some()),
+            ("bundles", bundles.is_some()),
+        ];
+        for (name, _) in global_only.iter().filter(|(_, is_set)| *is_set) {
+            warn!("package.metadata.dist.{name} is set, but this is only accepted in workspace.metadata (value is being ignored): {package_manifest_path}");
         }
 
         // Merge non-global settings
@@ -364,12 +1160,21 @@ impl DistMetadata {
         if auto_includes.is_none() {
             *auto_includes = workspace_config.auto_includes;
         }
+        if include_readme.is_none() {
+            *include_readme = workspace_config.include_readme;
+        }
+        if include_license.is_none() {
+            *include_license = workspace_config.include_license;
+        }
         if windows_archive.is_none() {
             *windows_archive = workspace_config.windows_archive;
         }
         if unix_archive.is_none() {
             *unix_archive = workspace_config.unix_archive;
         }
+        if target_archive.is_none() {
+            *target_archive = workspace_config.target_archive.clone();
+        }
         if npm_scope.is_none() {
             *npm_scope = workspace_config.npm_scope.clone();
         }
@@ -379,6 +1184,12 @@ impl DistMetadata {
         if install_path.is_none() {
             *install_path = workspace_config.install_path.clone();
         }
+        if installer_shell.is_none() {
+            *installer_shell = workspace_config.installer_shell;
+        }
+        if install_success_url.is_none() {
+            *install_success_url = workspace_config.install_success_url.clone();
+        }
         if features.is_none() {
             *features = workspace_config.features.clone();
         }
@@ -388,9 +1199,15 @@ impl DistMetadata {
         if all_features.is_none() {
             *all_features = workspace_config.all_features;
         }
+        if feature_variants.is_none() {
+            *feature_variants = workspace_config.feature_variants.clone();
+        }
         if tap.is_none() {
             *tap = workspace_config.tap.clone();
         }
+        if taps.is_none() {
+            *taps = workspace_config.taps.clone();
+        }
         if publish_jobs.is_none() {
             *publish_jobs = workspace_config.publish_jobs.clone();
         }
@@ -408,6 +1225,205 @@ impl DistMetadata {
             *include = workspace_config.include.clone();
         }
     }
+
+    /// Fill in any unset values with the defaults cargo-dist would use for them
+    ///
+    /// Global-only settings are only filled in if this is the workspace config,
+    /// because they're ignored on packages. Settings with no meaningful default
+    /// (like cargo-dist-version or tap) are left unset.
+    pub fn fill_defaults(&mut self, is_workspace: bool) {
+        // This is intentionally written awkwardly to make you update it
+        let DistMetadata {
+            cargo_dist_version: _,
+            rust_toolchain_version: _,
+            dist: _,
+            ci,
+            installers,
+            tap: _,
+            targets,
+            include,
+            auto_includes,
+            windows_archive,
+            unix_archive,
+            npm_scope: _,
+            checksum,
+            precise_builds: _,
+            merge_tasks,
+            fail_fast,
+            install_path,
+            features,
+            default_features,
+            all_features,
+            publish_jobs,
+            create_release,
+            pr_run_mode,
+            allow_dirty,
+            deny_deprecated_runners,
+            github_matrix_extra,
+            global_build,
+            build_matrix,
+            publish_job_conditions,
+            publish_job_secrets,
+            target_archive,
+            bundles,
+            taps: _,
+            include_readme,
+            include_license,
+            release_notes_table,
+            // There's no limit by default
+            concurrency: _,
+            github_setup_steps,
+            cargo_dist_archives,
+            test_shards,
+            github_reusable_workflow,
+            github_build_action,
+            git_author_name,
+            git_author_email,
+            prune_prerelease_assets,
+            // Every package is included by default
+            include_packages: _,
+            exclude_packages,
+            installer_shell,
+            // No telemetry by default
+            install_success_url: _,
+            github_custom_runners,
+            // Cargo.lock is left alone by default
+            cargo_lock_mode: _,
+            github_action_renames,
+            cosign,
+            // Secrets only come from Github by default
+            fetch_secrets_command: _,
+            workflow_name,
+            skip_forks,
+            // Cargo picks the target dir by default
+            cargo_target_dir: _,
+            free_disk_space,
+            early_draft_release,
+            // The title comes from the changelog (or tag) by default
+            announcement_title: _,
+            optional_installers,
+            // Github's own defaults apply if these aren't set
+            artifact_retention_days: _,
+            artifact_compression_level: _,
+            artifact_upload_overrides,
+            dependencies,
+            dependency_overrides,
+            cache_key_files,
+            // rust-cache's own default applies if this isn't set
+            cache_prefix_key: _,
+            github_workflow_dispatch,
+            cache,
+            binary_dirs,
+            feature_variants,
+            run_tests,
+            github_action_pins,
+            install_dist_methods,
+            build_overrides,
+            timeout_minutes,
+            artifact_transport,
+            github_fallback_runners,
+            windows_signing: _,
+            windows_signing_certificate_secret: _,
+            windows_signing_password_secret: _,
+            github_build_job_name: _,
+            manifest_provenance: _,
+            github_attestations,
+            github_permissions,
+            github_env,
+            github_target_env,
+            cargo_target_config,
+            cargo_registries: _,
+            github_split_workflows,
+            tag_prefix: _,
+            download_url_template: _,
+            panic_strategy: _,
+        } = self;
+
+        installers.get_or_insert_with(Vec::new);
+        targets.get_or_insert_with(Vec::new);
+        include.get_or_insert_with(Vec::new);
+        auto_includes.get_or_insert(true);
+        include_readme.get_or_insert(true);
+        include_license.get_or_insert(true);
+        windows_archive.get_or_insert(ZipStyle::Zip);
+        unix_archive.get_or_insert(ZipStyle::Tar(CompressionImpl::Xzip));
+        target_archive.get_or_insert_with(Default::default);
+        checksum.get_or_insert(ChecksumStyle::Sha256);
+        install_path.get_or_insert(InstallPathStrategy::CargoHome);
+        installer_shell.get_or_insert(InstallerShell::Sh);
+        features.get_or_insert_with(Vec::new);
+        default_features.get_or_insert(true);
+        all_features.get_or_insert(false);
+        publish_jobs.get_or_insert_with(Vec::new);
+        allow_dirty.get_or_insert_with(Vec::new);
+
+        if !is_workspace {
+            return;
+        }
+        ci.get_or_insert_with(Vec::new);
+        merge_tasks.get_or_insert(false);
+        fail_fast.get_or_insert(false);
+        create_release.get_or_insert(true);
+        release_notes_table.get_or_insert(false);
+        pr_run_mode.get_or_insert_with(Default::default);
+        deny_deprecated_runners.get_or_insert(false);
+        github_matrix_extra.get_or_insert_with(Default::default);
+        github_setup_steps.get_or_insert_with(Vec::new);
+        cargo_dist_archives.get_or_insert_with(Default::default);
+        test_shards.get_or_insert(1);
+        github_reusable_workflow.get_or_insert(false);
+        github_build_action.get_or_insert(false);
+        git_author_name.get_or_insert_with(|| DEFAULT_GIT_AUTHOR_NAME.to_owned());
+        git_author_email.get_or_insert_with(|| DEFAULT_GIT_AUTHOR_EMAIL.to_owned());
+        prune_prerelease_assets.get_or_insert(false);
+        exclude_packages.get_or_insert_with(Vec::new);
+        github_custom_runners.get_or_insert_with(Default::default);
+        github_action_renames.get_or_insert_with(Default::default);
+        global_build.get_or_insert_with(Default::default);
+        build_matrix.get_or_insert(false);
+        cosign.get_or_insert(false);
+        github_attestations.get_or_insert(false);
+        github_permissions.get_or_insert_with(Default::default);
+        github_env.get_or_insert_with(Default::default);
+        github_target_env.get_or_insert_with(Default::default);
+        cargo_target_config.get_or_insert_with(Default::default);
+        github_split_workflows.get_or_insert(false);
+        workflow_name.get_or_insert_with(|| DEFAULT_WORKFLOW_NAME.to_owned());
+        skip_forks.get_or_insert(false);
+        free_disk_space.get_or_insert(false);
+        early_draft_release.get_or_insert(false);
+        optional_installers.get_or_insert_with(Vec::new);
+        artifact_upload_overrides.get_or_insert_with(Default::default);
+        dependencies.get_or_insert_with(Default::default);
+        dependency_overrides.get_or_insert_with(Default::default);
+        cache_key_files.get_or_insert_with(Vec::new);
+        github_workflow_dispatch.get_or_insert(false);
+        cache.get_or_insert(true);
+        binary_dirs.get_or_insert_with(Default::default);
+        feature_variants.get_or_insert_with(Default::default);
+        run_tests.get_or_insert(false);
+        github_action_pins.get_or_insert_with(Default::default);
+        install_dist_methods.get_or_insert_with(Default::default);
+        build_overrides.get_or_insert_with(Default::default);
+        timeout_minutes.get_or_insert_with(Default::default);
+        artifact_transport.get_or_insert_with(Default::default);
+        github_fallback_runners.get_or_insert_with(Default::default);
+        bundles.get_or_insert_with(Default::default);
+        publish_job_conditions.get_or_insert_with(Default::default);
+        publish_job_secrets.get_or_insert_with(Default::default);
+    }
+}
+
+/// The config cargo-dist actually ended up using (impl of `cargo dist print-config`)
+///
+/// This is the workspace config and the merged config of each package,
+/// with defaults filled in.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedConfig {
+    /// The resolved [workspace.metadata.dist]
+    pub workspace: DistMetadata,
+    /// The resolved [package.metadata.dist] of each package, by package name
+    pub packages: SortedMap<String, DistMetadata>,
 }
 
 /// Global config for commands
@@ -429,6 +1445,14 @@ pub struct Config {
     pub installers: Vec<InstallerStyle>,
     /// The (git) tag to use for this Announcement.
     pub announcement_tag: Option<String>,
+    /// Cargo feature flags that replace the packages' own for this build
+    pub build_override: BuildOverride,
+    /// Whether to skip reporting progress (like which packages we're looking at)
+    pub quiet: bool,
+    /// Whether we need to compute the CI we'd generate
+    ///
+    /// Commands like build-local don't touch CI, so they shouldn't fail on CI-only problems
+    pub needs_ci: bool,
 }
 
 /// How we should select the artifacts to build
@@ -450,12 +1474,24 @@ pub enum CiStyle {
     /// Generate Github CI
     #[serde(rename = "github")]
     Github,
+    /// Generate Gitlab CI
+    #[serde(rename = "gitlab")]
+    Gitlab,
+    /// Generate Azure Pipelines
+    #[serde(rename = "azure")]
+    Azure,
+    /// Generate CircleCI
+    #[serde(rename = "circleci")]
+    CircleCi,
 }
 
 impl std::fmt::Display for CiStyle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
             CiStyle::Github => "github",
+            CiStyle::Gitlab => "gitlab",
+            CiStyle::Azure => "azure",
+            CiStyle::CircleCi => "circleci",
         };
         string.fmt(f)
     }
@@ -491,22 +1527,575 @@ impl std::fmt::Display for InstallerStyle {
 }
 
 /// The publish jobs we should run
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PublishStyle {
     /// Publish a Homebrew formula to a tap repository
     #[serde(rename = "homebrew")]
     Homebrew,
+    /// Publish `cargo doc` output to Github Pages
+    #[serde(rename = "docs")]
+    Docs,
+    /// Publish the workspace's crates to crates.io
+    #[serde(rename = "crates-io")]
+    CratesIo,
 }
 
 impl std::fmt::Display for PublishStyle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
             PublishStyle::Homebrew => "homebrew",
+            PublishStyle::Docs => "docs",
+            PublishStyle::CratesIo => "crates-io",
+        };
+        string.fmt(f)
+    }
+}
+
+/// A group of binaries to ship together in a single archive
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundleConfig {
+    /// The binaries to include (by the name they have in their Cargo.toml)
+    pub binaries: Vec<String>,
+    /// Names to give binaries inside the bundle, if they shouldn't keep their own
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SortedMap::is_empty")]
+    pub rename: SortedMap<String, String>,
+}
+
+impl BundleConfig {
+    /// Get the name the given binary should have inside the bundle
+    pub fn name_in_bundle<'a>(&'a self, binary_name: &'a str) -> &'a str {
+        self.rename
+            .get(binary_name)
+            .map(|s| &**s)
+            .unwrap_or(binary_name)
+    }
+}
+
+/// Settings for the artifacts a Github CI job uploads for later jobs
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ArtifactUploadSettings {
+    /// How many days Github should keep the artifacts
+    #[serde(default)]
+    #[serde(rename = "retention-days")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+    /// The compression level (0-9) to upload the artifacts with
+    #[serde(default)]
+    #[serde(rename = "compression-level")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_level: Option<u8>,
+}
+
+/// System packages to install with each package manager before building
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SystemDependencies {
+    /// Packages to install with apt (on Linux runners)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apt: Option<Vec<String>>,
+    /// Packages to install with Homebrew (on macOS runners)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brew: Option<Vec<String>>,
+    /// Packages to install with Chocolatey (on Windows runners)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chocolatey: Option<Vec<String>>,
+}
+
+/// An extra build of a package's binaries with different cargo features
+///
+/// Unset fields don't inherit the package's feature settings, they get cargo's defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FeatureVariant {
+    /// Features to enable
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Whether to enable default features
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "default-features")]
+    pub default_features: Option<bool>,
+    /// Whether to enable all features
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "all-features")]
+    pub all_features: Option<bool>,
+}
+
+/// A Homebrew tap to publish to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HomebrewTap {
+    /// The GitHub repo of the tap, in owner/name format
+    pub repo: String,
+    /// What kind of Homebrew package to publish to it
+    #[serde(default)]
+    pub kind: HomebrewKind,
+    /// The name of the CI secret with the token to push to the tap with
+    /// (the homebrew publish job's secret if unset)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "token-secret")]
+    pub token_secret: Option<String>,
+}
+
+impl HomebrewTap {
+    /// Get all the taps the given config asks for, with `tap` first
+    pub fn from_config(tap: &Option<String>, taps: &Option<Vec<HomebrewTap>>) -> Vec<Self> {
+        tap.iter()
+            .map(|repo| HomebrewTap {
+                repo: repo.clone(),
+                kind: HomebrewKind::Formula,
+                token_secret: None,
+            })
+            .chain(taps.iter().flatten().cloned())
+            .collect()
+    }
+}
+
+/// The kinds of Homebrew package we can publish
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HomebrewKind {
+    /// A formula (the usual kind of package, and the default)
+    #[default]
+    #[serde(rename = "formula")]
+    Formula,
+    /// A cask
+    #[serde(rename = "cask")]
+    Cask,
+}
+
+impl std::fmt::Display for HomebrewKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            HomebrewKind::Formula => "formula",
+            HomebrewKind::Cask => "cask",
         };
         string.fmt(f)
     }
 }
 
+/// Cargo feature flags that replace a package's configured features for some builds
+///
+/// Anything left unset keeps using the package's config. Extra builds from
+/// feature-variants aren't affected.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildOverride {
+    /// Features to enable instead of the package's `features`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "features")]
+    pub features: Option<Vec<String>>,
+    /// Whether to disable default features (instead of the package's `default-features`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "no-default-features")]
+    pub no_default_features: Option<bool>,
+    /// Whether to enable all features (instead of the package's `all-features`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "all-features")]
+    pub all_features: Option<bool>,
+}
+
+impl BuildOverride {
+    /// Get the `cargo dist build` flags that apply this override
+    pub fn dist_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(features) = &self.features {
+            args.push(format!("--features={}", features.join(",")));
+        }
+        if self.no_default_features == Some(true) {
+            args.push("--no-default-features".to_owned());
+        }
+        if self.all_features == Some(true) {
+            args.push("--all-features".to_owned());
+        }
+        args
+    }
+}
+
+/// Cargo config for building one target, like a `[target.<triple>]` table of .cargo/config.toml
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoTargetConfig {
+    /// The linker to link the target with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "linker")]
+    pub linker: Option<String>,
+    /// The command to run the target's executables with (like qemu or wine)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "runner")]
+    pub runner: Option<String>,
+}
+
+impl CargoTargetConfig {
+    /// Get the `--config` values that apply this to a cargo build of `target`
+    pub fn cargo_config_args(&self, target: &TargetTriple) -> Vec<String> {
+        let quote = |s: &str| toml_edit::Value::from(s).to_string();
+        let settings = [("linker", &self.linker), ("runner", &self.runner)];
+        settings
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let value = value.as_deref()?;
+                Some(format!("target.{}.{key}={}", quote(target), quote(value)))
+            })
+            .collect()
+    }
+}
+
+/// The name cargo knows crates.io by
+pub(crate) const CRATES_IO_REGISTRY: &str = "crates-io";
+
+/// A cargo registry to publish crates to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CargoRegistry {
+    /// The name of the registry, as passed to `cargo publish --registry` ("crates-io" for crates.io)
+    pub name: String,
+    /// The index URL of the registry, if it isn't already in .cargo/config.toml
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    /// The name of the CI secret with the token to publish to the registry with
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "token-secret")]
+    pub token_secret: Option<String>,
+}
+
+impl CargoRegistry {
+    /// Get all the registries the given config asks for (just crates.io if it doesn't say)
+    pub fn from_config(registries: &Option<Vec<CargoRegistry>>) -> Vec<Self> {
+        registries.clone().unwrap_or_else(|| {
+            vec![CargoRegistry {
+                name: CRATES_IO_REGISTRY.to_owned(),
+                index: None,
+                token_secret: None,
+            }]
+        })
+    }
+
+    /// Whether this is crates.io
+    pub fn is_crates_io(&self) -> bool {
+        self.name == CRATES_IO_REGISTRY
+    }
+
+    /// The prefix of the environment variables cargo reads this registry's settings from
+    pub fn env_prefix(&self) -> String {
+        format!(
+            "CARGO_REGISTRIES_{}",
+            self.name.to_ascii_uppercase().replace('-', "_")
+        )
+    }
+}
+
+/// Where Github CI jobs store artifacts for the jobs after them to pick up
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum ArtifactTransport {
+    /// Github's artifact storage (`kind = "github"`)
+    #[default]
+    #[serde(rename = "github")]
+    Github,
+    /// An S3 bucket, with the aws cli (`kind = "s3"`)
+    ///
+    /// Every runner needs the aws cli, and credentials that can read and write the bucket.
+    #[serde(rename = "s3")]
+    S3 {
+        /// The bucket to store artifacts in
+        bucket: String,
+        /// A path in the bucket to store artifacts under
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// A directory every runner can reach, like a shared volume (`kind = "path"`)
+    #[serde(rename = "path")]
+    Path {
+        /// The directory to store artifacts under
+        path: String,
+    },
+}
+
+/// How many minutes each kind of Github CI job may run before it's cancelled
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobTimeouts {
+    /// Jobs that build (and test) local artifacts
+    pub local: u32,
+    /// The plan job, and jobs that build global artifacts
+    pub global: u32,
+    /// Jobs that publish the release (like publish-release and homebrew)
+    pub publish: u32,
+}
+
+impl Default for JobTimeouts {
+    fn default() -> Self {
+        Self {
+            local: DEFAULT_TIMEOUT_MINUTES,
+            global: DEFAULT_TIMEOUT_MINUTES,
+            publish: DEFAULT_TIMEOUT_MINUTES,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JobTimeouts {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Kinds {
+            local: Option<u32>,
+            global: Option<u32>,
+            publish: Option<u32>,
+        }
+
+        // Not an untagged enum, so a bad table gets a useful error instead of
+        // "data did not match any variant"
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        let timeouts = if raw.is_object() {
+            let Kinds {
+                local,
+                global,
+                publish,
+            } = Kinds::deserialize(raw).map_err(D::Error::custom)?;
+            JobTimeouts {
+                local: local.unwrap_or(DEFAULT_TIMEOUT_MINUTES),
+                global: global.unwrap_or(DEFAULT_TIMEOUT_MINUTES),
+                publish: publish.unwrap_or(DEFAULT_TIMEOUT_MINUTES),
+            }
+        } else {
+            let minutes = u32::deserialize(raw).map_err(|_| {
+                D::Error::custom(
+                    "timeout-minutes should be a number of minutes, or a table of them",
+                )
+            })?;
+            JobTimeouts {
+                local: minutes,
+                global: minutes,
+                publish: minutes,
+            }
+        };
+        if timeouts.local == 0 || timeouts.global == 0 || timeouts.publish == 0 {
+            return Err(D::Error::custom("timeout-minutes must be at least 1"));
+        }
+        Ok(timeouts)
+    }
+}
+
+/// A specific archive of cargo-dist to install
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DistArchive {
+    /// Where to download the archive from
+    pub url: String,
+    /// The expected sha256 of the archive, in hex
+    pub sha256: String,
+}
+
+/// The OS of a CI runner, for picking how to install cargo-dist on it
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RunnerOs {
+    /// Linux runners (`"linux"`)
+    #[serde(rename = "linux")]
+    Linux,
+    /// macOS runners, whatever their arch (`"macos"`)
+    #[serde(rename = "macos")]
+    Macos,
+    /// Windows runners (`"windows"`)
+    #[serde(rename = "windows")]
+    Windows,
+}
+
+impl RunnerOs {
+    /// The install method to use on this OS if install-dist-methods doesn't say
+    pub fn default_install_dist_method(self) -> InstallDistMethod {
+        match self {
+            RunnerOs::Linux | RunnerOs::Macos => InstallDistMethod::Shell,
+            RunnerOs::Windows => InstallDistMethod::Powershell,
+        }
+    }
+}
+
+impl std::fmt::Display for RunnerOs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            RunnerOs::Linux => "linux",
+            RunnerOs::Macos => "macos",
+            RunnerOs::Windows => "windows",
+        };
+        string.fmt(f)
+    }
+}
+
+/// How CI should install cargo-dist
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InstallDistMethod {
+    /// Pipe cargo-dist's shell installer into sh (`"shell"`), linux and macos only
+    #[serde(rename = "shell")]
+    Shell,
+    /// Pipe cargo-dist's powershell installer into iex (`"powershell"`), windows only
+    #[serde(rename = "powershell")]
+    Powershell,
+    /// Install cargo-binstall, then use it to install cargo-dist (`"binstall"`)
+    #[serde(rename = "binstall")]
+    Binstall,
+}
+
+impl std::fmt::Display for InstallDistMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            InstallDistMethod::Shell => "shell",
+            InstallDistMethod::Powershell => "powershell",
+            InstallDistMethod::Binstall => "binstall",
+        };
+        string.fmt(f)
+    }
+}
+
+/// Which shell the shell installer is written for
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InstallerShell {
+    /// Portable POSIX sh (`"sh"`)
+    #[default]
+    #[serde(rename = "sh")]
+    Sh,
+    /// Bash (`"bash"`)
+    #[serde(rename = "bash")]
+    Bash,
+}
+
+impl std::fmt::Display for InstallerShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            InstallerShell::Sh => "sh",
+            InstallerShell::Bash => "bash",
+        };
+        string.fmt(f)
+    }
+}
+
+/// Whether cargo builds should insist on an up to date Cargo.lock
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CargoLockMode {
+    /// Pass `--locked` (`"locked"`)
+    #[serde(rename = "locked")]
+    Locked,
+    /// Pass `--frozen` (`"frozen"`)
+    #[serde(rename = "frozen")]
+    Frozen,
+}
+
+impl std::fmt::Display for CargoLockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            CargoLockMode::Locked => "locked",
+            CargoLockMode::Frozen => "frozen",
+        };
+        string.fmt(f)
+    }
+}
+
+/// What binaries should do when they panic (the `panic` setting of a cargo profile)
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PanicStrategy {
+    /// Unwind the stack (`"unwind"`)
+    #[serde(rename = "unwind")]
+    Unwind,
+    /// Abort the process (`"abort"`)
+    #[serde(rename = "abort")]
+    Abort,
+}
+
+impl std::fmt::Display for PanicStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            PanicStrategy::Unwind => "unwind",
+            PanicStrategy::Abort => "abort",
+        };
+        string.fmt(f)
+    }
+}
+
+/// How to limit release workflows to one at a time
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ConcurrencyMode {
+    /// Don't limit them at all (`"none"`)
+    #[serde(rename = "none")]
+    Off,
+    /// Wait for the running workflow to finish (`"queue"`)
+    #[serde(rename = "queue")]
+    Queue,
+    /// Cancel the running workflow (`"cancel"`)
+    #[serde(rename = "cancel")]
+    Cancel,
+}
+
+impl std::fmt::Display for ConcurrencyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            ConcurrencyMode::Off => "none",
+            ConcurrencyMode::Queue => "queue",
+            ConcurrencyMode::Cancel => "cancel",
+        };
+        string.fmt(f)
+    }
+}
+
+/// Whether CI should run the task that builds global artifacts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlobalBuildMode {
+    /// Run it only if some release has global artifacts (`"auto"`)
+    #[default]
+    Auto,
+    /// Always run it (`true`)
+    Always,
+    /// Never run it (`false`)
+    Never,
+}
+
+impl Serialize for GlobalBuildMode {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            GlobalBuildMode::Auto => serializer.serialize_str("auto"),
+            GlobalBuildMode::Always => serializer.serialize_bool(true),
+            GlobalBuildMode::Never => serializer.serialize_bool(false),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GlobalBuildMode {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bool(bool),
+            Str(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Bool(true) => Ok(GlobalBuildMode::Always),
+            Raw::Bool(false) => Ok(GlobalBuildMode::Never),
+            Raw::Str(mode) => match &*mode {
+                "auto" => Ok(GlobalBuildMode::Auto),
+                "true" => Ok(GlobalBuildMode::Always),
+                "false" => Ok(GlobalBuildMode::Never),
+                _ => Err(D::Error::custom(format!(
+                    "unknown global-build mode {mode}, expected one of: auto, true, false"
+                ))),
+            },
+        }
+    }
+}
+
 /// The style of zip/tarball to make
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ZipStyle {
@@ -795,3 +2384,26 @@ pub fn save_cargo_toml(manifest_path: &Utf8Path, toml: toml_edit::Document) -> R
     axoasset::LocalAsset::write_new(&toml_text, manifest_path)?;
     Ok(())
 }
+
+/// The file rustup reads a project's pinned toolchain from
+pub const RUST_TOOLCHAIN_FILE: &str = "rust-toolchain.toml";
+
+/// Get the rust-toolchain.toml that pins the same toolchain as the deprecated
+/// rust-toolchain-version (rustup toolchain syntax)
+pub fn rust_toolchain_toml(rust_toolchain_version: &str) -> String {
+    let mut toml = toml_edit::Document::new();
+    let mut toolchain = toml_edit::Table::new();
+    toolchain.insert("channel", toml_edit::value(rust_toolchain_version));
+    toml.insert("toolchain", toml_edit::Item::Table(toolchain));
+    toml.to_string()
+}
+
+/// Find the file that pins the project's toolchain for rustup, if it has one
+///
+/// rustup also accepts a legacy extension-less rust-toolchain file.
+pub fn find_rust_toolchain_file(workspace_dir: &Utf8Path) -> Option<Utf8PathBuf> {
+    [RUST_TOOLCHAIN_FILE, "rust-toolchain"]
+        .iter()
+        .map(|file| workspace_dir.join(file))
+        .find(|path| path.exists())
+}
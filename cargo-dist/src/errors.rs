@@ -114,7 +114,7 @@ pub enum DistError {
 
     /// Use explicitly requested workspace builds, but had packages with custom feature settings
     #[error("precise-builds = false was set, but some packages have custom build features, making it impossible")]
-    #[help("these packages customized either features, no-default-features, or all-features: {packages:?}")]
+    #[help("these packages customized either features, no-default-features, all-features, or feature-variants: {packages:?}")]
     PreciseImpossible {
         /// names of problem packages
         packages: Vec<String>,
@@ -127,6 +127,32 @@ pub enum DistError {
         tag: String,
     },
 
+    /// A package's tag-prefix can't be used in tags
+    #[error("{package} has an invalid tag-prefix: {prefix}")]
+    #[diagnostic(help(
+        "tag prefixes can only contain alphanumerics, dashes, underscores, and dots"
+    ))]
+    InvalidTagPrefix {
+        /// The package
+        package: String,
+        /// The invalid prefix
+        prefix: String,
+    },
+
+    /// A package's tag-prefix could also mean another package
+    #[error("{package} has tag-prefix = {prefix:?}, but {other} is also released with tags starting with that")]
+    #[diagnostic(help(
+        "give each package a tag-prefix that isn't another package's name or tag-prefix"
+    ))]
+    AmbiguousTagPrefix {
+        /// The package
+        package: String,
+        /// Its prefix
+        prefix: String,
+        /// The other package with that prefix (or name)
+        other: String,
+    },
+
     /// parse_tag concluded there was nothing to release
     #[error("This workspace doesn't have anything for cargo-dist to Release!")]
     NothingToRelease,
@@ -160,11 +186,238 @@ pub enum DistError {
         details: semver::Error,
     },
 
+    /// The generated CI would use a Github runner that is deprecated, and the user asked us to care
+    #[error("Github CI would use the deprecated runner image {runner}")]
+    #[diagnostic(help(
+        "consider using {replacement} instead, or unset deny-deprecated-runners to only warn"
+    ))]
+    DeprecatedGithubRunner {
+        /// The deprecated runner
+        runner: String,
+        /// The runner we suggest using instead
+        replacement: String,
+    },
+
+    /// publish-job-secrets had a name that isn't a valid secret name
+    #[error("publish-job-secrets has an invalid secret name for the {job} publish job: {secret}")]
+    #[diagnostic(help(
+        "secret names can only contain alphanumerics and underscores, and can't start with a number"
+    ))]
+    InvalidSecretName {
+        /// The publish job
+        job: String,
+        /// The invalid secret name
+        secret: String,
+    },
+
+    /// cargo-registries had a name cargo can't use for a registry
+    #[error("cargo-registries has an invalid registry name: {name}")]
+    #[diagnostic(help("registry names can only contain alphanumerics, dashes, and underscores"))]
+    InvalidCargoRegistryName {
+        /// The invalid registry name
+        name: String,
+    },
+
+    /// cargo-registries had the same registry more than once
+    #[error("cargo-registries has more than one registry named {name}")]
+    DuplicateCargoRegistry {
+        /// The repeated registry name
+        name: String,
+    },
+
+    /// cargo-registries had a token-secret that isn't a valid secret name
+    #[error("cargo-registries has an invalid token-secret for the {registry} registry: {secret}")]
+    #[diagnostic(help(
+        "secret names can only contain alphanumerics and underscores, and can't start with a number"
+    ))]
+    InvalidCargoRegistrySecret {
+        /// The registry
+        registry: String,
+        /// The invalid secret name
+        secret: String,
+    },
+
+    /// A bundle was configured without any binaries
+    #[error("bundles.{bundle} doesn't have any binaries")]
+    EmptyBundle {
+        /// The name of the bundle
+        bundle: String,
+    },
+
+    /// A bundle has the same name as a package, so their archives would collide
+    #[error("bundles.{bundle} has the same name as a package in your workspace")]
+    #[diagnostic(help(
+        "the archives of bundles and packages would collide, so rename the bundle"
+    ))]
+    BundleNameCollision {
+        /// The name of the bundle
+        bundle: String,
+    },
+
+    /// Two binaries in a bundle would have the same name inside it
+    #[error("bundles.{bundle} contains more than one binary named {name}")]
+    #[diagnostic(help("use rename to give one of them a different name inside the bundle"))]
+    BundleBinaryCollision {
+        /// The name of the bundle
+        bundle: String,
+        /// The name the binaries would both have
+        name: String,
+    },
+
+    /// Github CI wouldn't build any targets or global artifacts for releases with binaries
+    #[error("Github CI has nothing to build: no release has any targets or global artifacts")]
+    #[diagnostic(help(
+        "set the targets to build in your cargo-dist config, like targets = [\"x86_64-unknown-linux-gnu\"]"
+    ))]
+    GithubCiNothingToBuild,
+
+    /// Asked about Github CI for a workspace that doesn't use it
+    #[error("Github CI isn't enabled for this workspace")]
+    #[diagnostic(help("add \"github\" to ci in your cargo-dist config, or pass --ci=github"))]
+    GithubCiNotEnabled,
+
+    /// download-url-template would give every artifact the same URL
+    #[error("download-url-template doesn't have a {{filename}} placeholder: {template}")]
+    #[diagnostic(help(
+        "every artifact needs its own URL, like \"https://cdn.example.com/{{tag}}/{{filename}}\""
+    ))]
+    DownloadUrlTemplateNoFilename {
+        /// The template
+        template: String,
+    },
+
+    /// verify-manifest was asked to check a release without saying which one
+    #[error("don't know which release to verify")]
+    #[diagnostic(help("pass --tag, or a --manifest with an announcement tag"))]
+    NoReleaseToVerify,
+
+    /// verify-manifest found artifacts missing or changed on the release
+    #[error("{problems} artifacts on the Github Release for {tag} don't match the manifest")]
+    ReleaseVerificationFailed {
+        /// The tag of the release
+        tag: String,
+        /// How many artifacts are missing or mismatched
+        problems: usize,
+    },
+
+    /// We don't know what CI runner can build this target
+    #[error("not sure which CI runner should build {target}")]
+    #[diagnostic(help(
+        "remove it from your targets, or (for Github CI) pick a runner for it with github-custom-runners"
+    ))]
+    UnknownRunnerForTarget {
+        /// The target
+        target: String,
+    },
+
+    /// A CI backend with fixed jobs has no way to build a target
+    #[error("{ci} can't build {target}")]
+    #[diagnostic(help(
+        "its Linux machines are x86_64, and it doesn't cross-compile Linux for other arches like Github CI does (with cross), so remove the target or build it with Github CI"
+    ))]
+    CiCantBuildTarget {
+        /// The CI backend
+        ci: String,
+        /// The target
+        target: String,
+    },
+
+    /// We couldn't make a valid entry for the Github CI matrix
+    #[error("couldn't build the Github CI matrix")]
+    #[diagnostic(help("check your github-matrix-extra setting"))]
+    GithubMatrixEntry {
+        /// What was wrong with the entry
+        #[from]
+        details: cargo_dist_schema::GithubMatrixEntryError,
+    },
+
+    /// An artifact upload compression level that upload-artifact won't accept
+    #[error("{setting} must be between 0 and 9, but it's {level}")]
+    InvalidCompressionLevel {
+        /// The setting with the bad level
+        setting: String,
+        /// The bad level
+        level: u8,
+    },
+
+    /// A pinned cargo-dist archive has a checksum that can't be a sha256
+    #[error("cargo-dist-archives.{target}.sha256 isn't a sha256: {sha256}")]
+    #[diagnostic(help("this should be 64 hex digits, like what `sha256sum` prints"))]
+    InvalidDistArchiveChecksum {
+        /// The target the archive is for
+        target: String,
+        /// The bad checksum
+        sha256: String,
+    },
+
+    /// install-dist-methods picked a method that can't work on that OS's runners
+    #[error("install-dist-methods.{os} is {method}, which can't be used on {os} runners")]
+    #[diagnostic(help(
+        "shell only works on linux and macos, and powershell only works on windows (binstall works everywhere)"
+    ))]
+    UnsupportedInstallDistMethod {
+        /// The OS the method was picked for
+        os: String,
+        /// The method that was picked
+        method: String,
+    },
+
+    /// We generated Github CI that isn't valid YAML, which is a bug in cargo-dist
+    /// (or in a CI post-processor)
+    #[error("Generated Github CI isn't valid YAML")]
+    #[diagnostic(help("this is a bug in cargo-dist (or a CI post-processor you added), let us know and we'll fix it: https://github.com/axodotdev/cargo-dist/issues/new"))]
+    GithubCiInvalidYaml {
+        /// The generated CI
+        #[source_code]
+        source: String,
+        /// Where the error was found
+        #[label]
+        span: Option<miette::SourceSpan>,
+        /// Details of the error
+        #[source]
+        details: yaml_rust::ScanError,
+    },
+
+    /// We generated Github CI that parses but doesn't look like a workflow, which is a
+    /// bug in cargo-dist (or in a CI post-processor)
+    #[error("Generated Github CI isn't a valid workflow: {reason}")]
+    #[diagnostic(help("this is a bug in cargo-dist (or a CI post-processor you added), let us know and we'll fix it: https://github.com/axodotdev/cargo-dist/issues/new"))]
+    GithubCiInvalidWorkflow {
+        /// What was wrong with it
+        reason: String,
+    },
+
     /// Not an error; indicates that a file's contents differ via --check
     #[error(r#"generated contents for file {file} differ"#)]
+    #[diagnostic(help(
+        "run `cargo dist generate` to update it (or `cargo dist build` if it's an installer)"
+    ))]
     CheckFileMismatch {
         /// The file whose contents differ
         file: String,
+        /// A unified diff from the file on disk to what we'd generate
+        #[source_code]
+        diff: String,
+        /// The whole diff, so that all of it gets shown
+        #[label("what would change")]
+        span: miette::SourceSpan,
+    },
+
+    /// Not an error; indicates that several files' contents differ via --check
+    #[error("generated contents for {} files differ", files.len())]
+    #[diagnostic(help(
+        "these files are out of date: {}\n\nrun `cargo dist generate` to update them (and `cargo dist build` for any installers)",
+        files.join(", ")
+    ))]
+    CheckFilesMismatch {
+        /// The files whose contents differ
+        files: Vec<String>,
+        /// Unified diffs from the files on disk to what we'd generate
+        #[source_code]
+        diff: String,
+        /// The whole diff, so that all of it gets shown
+        #[label("what would change")]
+        span: miette::SourceSpan,
     },
 }
 
@@ -9,8 +9,8 @@ use serde::Deserialize;
 
 use crate::{
     config::{
-        self, CiStyle, CompressionImpl, Config, DistMetadata, InstallerStyle, PublishStyle,
-        ZipStyle,
+        self, CiStyle, CompressionImpl, Config, DistMetadata, GlobalBuildMode, InstallerStyle,
+        PublishStyle, ZipStyle,
     },
     do_generate_ci,
     errors::{DistError, DistResult, Result},
@@ -111,8 +111,14 @@ pub fn do_init(cfg: &Config, args: &InitArgs) -> Result<()> {
             eprintln!("running 'cargo dist generate-ci' to apply any changes to your CI scripts");
             eprintln!();
 
-            let ci_args = GenerateCiArgs { check: false };
-            do_generate_ci(cfg, &ci_args)?;
+            let ci_args = GenerateCiArgs {
+                check: false,
+                output_dir: None,
+                ci_post_processors: vec![],
+            };
+            for file in do_generate_ci(cfg, &ci_args)? {
+                eprintln!("generated {} to {}", file.desc, file.path);
+            }
         }
     }
     Ok(())
@@ -208,6 +214,76 @@ fn get_new_dist_metadata(
             create_release: None,
             pr_run_mode: None,
             allow_dirty: None,
+            deny_deprecated_runners: None,
+            github_matrix_extra: None,
+            global_build: None,
+            build_matrix: None,
+            publish_job_conditions: None,
+            publish_job_secrets: None,
+            target_archive: None,
+            bundles: None,
+            taps: None,
+            include_readme: None,
+            include_license: None,
+            release_notes_table: None,
+            concurrency: None,
+            github_setup_steps: None,
+            cargo_dist_archives: None,
+            test_shards: None,
+            github_reusable_workflow: None,
+            github_build_action: None,
+            git_author_name: None,
+            git_author_email: None,
+            prune_prerelease_assets: None,
+            include_packages: None,
+            exclude_packages: None,
+            installer_shell: None,
+            install_success_url: None,
+            github_custom_runners: None,
+            cargo_lock_mode: None,
+            github_action_renames: None,
+            cosign: None,
+            fetch_secrets_command: None,
+            workflow_name: None,
+            skip_forks: None,
+            cargo_target_dir: None,
+            free_disk_space: None,
+            early_draft_release: None,
+            announcement_title: None,
+            optional_installers: None,
+            artifact_retention_days: None,
+            artifact_compression_level: None,
+            artifact_upload_overrides: None,
+            dependencies: None,
+            dependency_overrides: None,
+            cache_key_files: None,
+            cache_prefix_key: None,
+            github_workflow_dispatch: None,
+            cache: None,
+            binary_dirs: None,
+            feature_variants: None,
+            run_tests: None,
+            github_action_pins: None,
+            install_dist_methods: None,
+            build_overrides: None,
+            timeout_minutes: None,
+            artifact_transport: None,
+            github_fallback_runners: None,
+            windows_signing: None,
+            windows_signing_certificate_secret: None,
+            windows_signing_password_secret: None,
+            github_build_job_name: None,
+            manifest_provenance: None,
+            github_attestations: None,
+            github_permissions: None,
+            github_env: None,
+            github_target_env: None,
+            cargo_target_config: None,
+            cargo_registries: None,
+            github_split_workflows: None,
+            tag_prefix: None,
+            download_url_template: None,
+            panic_strategy: None,
         }
     };
 
@@ -254,7 +330,7 @@ fn get_new_dist_metadata(
                 return Err(DistError::NoUpdateVersion {
                     project_version: desired_version.clone(),
                     running_version: current_version,
-                })?;
+                });
             }
         }
     } else {
@@ -283,15 +359,63 @@ fn get_new_dist_metadata(
         }
     }
 
+    // Offer to migrate the deprecated rust-toolchain-version to a rust-toolchain.toml
+    if let Some(rust_toolchain_version) = meta.rust_toolchain_version.clone() {
+        let existing = config::find_rust_toolchain_file(&workspace_info.workspace_dir);
+        let prompt = if let Some(existing) = &existing {
+            format!(
+                r#"rust-toolchain-version is deprecated, remove it?
+    your {} already pins your toolchain (make sure it's {})"#,
+                existing.file_name().unwrap(),
+                rust_toolchain_version
+            )
+        } else {
+            format!(
+                r#"rust-toolchain-version is deprecated, move it to a {}?
+    this pins {} for everyone building your project, not just CI"#,
+                config::RUST_TOOLCHAIN_FILE,
+                rust_toolchain_version
+            )
+        };
+        let default = true;
+
+        let response = if args.yes {
+            default
+        } else {
+            let res = Confirm::with_theme(&theme)
+                .with_prompt(prompt)
+                .default(default)
+                .interact()?;
+            eprintln!();
+            res
+        };
+        if response {
+            if existing.is_none() {
+                let toolchain_file = workspace_info
+                    .workspace_dir
+                    .join(config::RUST_TOOLCHAIN_FILE);
+                let contents = config::rust_toolchain_toml(&rust_toolchain_version);
+                axoasset::LocalAsset::write_new(&contents, &toolchain_file)?;
+                eprintln!("{check} added {toolchain_file}");
+                eprintln!();
+            }
+            meta.rust_toolchain_version = None;
+        } else {
+            eprintln!("{notice} keeping rust-toolchain-version, which may stop working in a future version");
+            eprintln!();
+        }
+    }
+
     // Enable CI backends
     {
-        // FIXME: when there is more than one option this should be a proper
-        // multiselect like the installer selector is! For now we do
-        // most of the multi-select logic and then just give a prompt.
-        let known = &[CiStyle::Github];
+        let known = &[
+            CiStyle::Github,
+            CiStyle::Gitlab,
+            CiStyle::Azure,
+            CiStyle::CircleCi,
+        ];
         let mut defaults = vec![];
         let mut keys = vec![];
-        let mut github_key = 0;
         for item in known {
             // If this CI style is in their config, keep it
             // If they passed it on the CLI, flip it on
@@ -302,14 +426,17 @@ fn get_new_dist_metadata(
                 .unwrap_or(false)
                 || cfg.ci.contains(item);
 
-            // If they have a well-defined repo url and it's github, default enable it
-            #[allow(irrefutable_let_patterns)]
-            if let CiStyle::Github = item {
-                github_key = 0;
-                if let Some(repo_url) = &workspace_info.repository_url {
-                    if repo_url.contains("github.com") {
-                        default = true;
-                    }
+            // If they have a well-defined repo url on a forge we support, default enable it
+            if let Some(repo_url) = &workspace_info.repository_url {
+                // (CircleCI isn't a forge, so it's never the obvious choice)
+                let host = match item {
+                    CiStyle::Github => Some("github.com"),
+                    CiStyle::Gitlab => Some("gitlab"),
+                    CiStyle::Azure => Some("dev.azure.com"),
+                    CiStyle::CircleCi => None,
+                };
+                if host.is_some_and(|host| repo_url.contains(host)) {
+                    default = true;
                 }
             }
             defaults.push(default);
@@ -317,33 +444,34 @@ fn get_new_dist_metadata(
             // to `known` above!
             keys.push(match item {
                 CiStyle::Github => "github",
+                CiStyle::Gitlab => "gitlab",
+                CiStyle::Azure => "azure",
+                CiStyle::CircleCi => "circleci",
             });
         }
 
         // Prompt the user
-        let prompt = r#"enable Github CI integration?
-    this creates a CI action which automates creating a Github Release,
+        let prompt = r#"enable CI integration?
+    this creates a CI pipeline which automates creating a Release,
     builds all your binaries/archives, and then uploads them to the Release
-    it also unlocks the ability to generate installers which fetch those artifacts"#;
-        let default = defaults[github_key];
-
-        let github_selected = if args.yes {
-            default
+    it also unlocks the ability to generate installers which fetch those artifacts
+    (select with arrow keys and space, submit with enter)"#;
+        let selected = if args.yes {
+            defaults
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, enabled)| enabled.then_some(idx))
+                .collect()
         } else {
-            let res = Confirm::with_theme(&theme)
+            let res = MultiSelect::with_theme(&theme)
+                .items(&keys)
+                .defaults(&defaults)
                 .with_prompt(prompt)
-                .default(default)
                 .interact()?;
             eprintln!();
             res
         };
 
-        let selected = if github_selected {
-            vec![github_key]
-        } else {
-            vec![]
-        };
-
         // Apply the results
         let ci: Vec<_> = selected.into_iter().map(|i| known[i]).collect();
         meta.ci = if ci.is_empty() { None } else { Some(ci) };
@@ -378,15 +506,16 @@ fn get_new_dist_metadata(
             }
         });
         if let Some(inner) = conflict {
-            return Err(DistError::CantEnableGithubUrlInconsistent { inner })?;
+            return Err(DistError::CantEnableGithubUrlInconsistent { inner });
         } else {
             // Otherwise assume no URL
-            return Err(DistError::CantEnableGithubNoUrl)?;
+            return Err(DistError::CantEnableGithubNoUrl);
         }
     }
 
-    if has_github_ci {
-        let prompt = r#"enable Github CI checks on pull requests?
+    let has_ci = meta.ci.as_ref().map(|ci| !ci.is_empty()).unwrap_or(false);
+    if has_ci {
+        let prompt = r#"enable CI checks on pull requests?
     This will run most of your release process in order to allow you to
     test it regularly. "Upload" will build and upload the release artifacts,
     while "plan" will only plan out the release without actually
@@ -411,7 +540,6 @@ fn get_new_dist_metadata(
 
     // Enable installer backends (if they have a CI backend that can provide URLs)
     // In the future, "vendored" installers like MSIs could be enabled in this situation!
-    let has_ci = meta.ci.as_ref().map(|ci| !ci.is_empty()).unwrap_or(false);
     if has_ci {
         let known = &[
             InstallerStyle::Shell,
@@ -604,7 +732,7 @@ fn get_new_dist_metadata(
                 meta.unix_archive = TAR_GZ;
                 meta.windows_archive = TAR_GZ;
             } else {
-                return Err(DistError::MustEnableTarGz)?;
+                return Err(DistError::MustEnableTarGz);
             }
         }
     }
@@ -663,6 +791,77 @@ fn update_toml_metadata(
         create_release,
         pr_run_mode,
         allow_dirty,
+        deny_deprecated_runners,
+        global_build,
+        build_matrix,
+        include_readme,
+        include_license,
+        release_notes_table,
+        concurrency,
+        github_reusable_workflow,
+        github_build_action,
+        git_author_name,
+        git_author_email,
+        prune_prerelease_assets,
+        include_packages,
+        exclude_packages,
+        installer_shell,
+        install_success_url,
+        cargo_lock_mode,
+        cosign,
+        fetch_secrets_command,
+        workflow_name,
+        skip_forks,
+        cargo_target_dir,
+        free_disk_space,
+        early_draft_release,
+        announcement_title,
+        optional_installers,
+        artifact_retention_days,
+        artifact_compression_level,
+        cache_key_files,
+        cache_prefix_key,
+        github_workflow_dispatch,
+        cache,
+        run_tests,
+        windows_signing,
+        windows_signing_certificate_secret,
+        windows_signing_password_secret,
+        github_build_job_name,
+        manifest_provenance,
+        github_attestations,
+        github_split_workflows,
+        tag_prefix,
+        download_url_template,
+        panic_strategy,
+        // Tables (and arrays of tables) that init never edits, so just leave them as-is
+        github_matrix_extra: _,
+        publish_job_conditions: _,
+        publish_job_secrets: _,
+        target_archive: _,
+        bundles: _,
+        taps: _,
+        github_setup_steps: _,
+        cargo_dist_archives: _,
+        test_shards: _,
+        github_custom_runners: _,
+        github_action_renames: _,
+        artifact_upload_overrides: _,
+        dependencies: _,
+        dependency_overrides: _,
+        binary_dirs: _,
+        feature_variants: _,
+        github_action_pins: _,
+        install_dist_methods: _,
+        build_overrides: _,
+        timeout_minutes: _,
+        artifact_transport: _,
+        github_fallback_runners: _,
+        github_permissions: _,
+        github_env: _,
+        github_target_env: _,
+        cargo_target_config: _,
+        cargo_registries: _,
     } = &meta;
 
     apply_optional_value(
@@ -728,6 +927,20 @@ fn update_toml_metadata(
         *auto_includes,
     );
 
+    apply_optional_value(
+        table,
+        "include-readme",
+        "# Whether auto-includes should include READMEs (default true)\n",
+        *include_readme,
+    );
+
+    apply_optional_value(
+        table,
+        "include-license",
+        "# Whether auto-includes should include LICENSEs (default true)\n",
+        *include_license,
+    );
+
     apply_optional_value(
         table,
         "windows-archive",
@@ -763,6 +976,20 @@ fn update_toml_metadata(
         *precise_builds,
     );
 
+    apply_optional_value(
+        table,
+        "cargo-lock-mode",
+        "# Whether cargo builds require an up to date Cargo.lock (\"locked\" or \"frozen\")\n",
+        cargo_lock_mode.as_ref().map(|m| m.to_string()),
+    );
+
+    apply_optional_value(
+        table,
+        "panic-strategy",
+        "# What the binaries should do when they panic (\"unwind\" or \"abort\")\n",
+        panic_strategy.as_ref().map(|s| s.to_string()),
+    );
+
     apply_optional_value(
         table,
         "merge-tasks",
@@ -784,6 +1011,83 @@ fn update_toml_metadata(
         *create_release,
     );
 
+    apply_optional_value(
+        table,
+        "release-notes-table",
+        "# Whether the Github Release body should end with a table of every artifact\n",
+        *release_notes_table,
+    );
+
+    apply_optional_value(
+        table,
+        "concurrency",
+        "# Whether to only run one release workflow per ref at a time (\"queue\", \"cancel\", or \"none\")\n",
+        concurrency.as_ref().map(|c| c.to_string()),
+    );
+
+    apply_optional_value(
+        table,
+        "github-reusable-workflow",
+        "# Whether to generate the release workflow as one other workflows call (on: workflow_call)\n",
+        *github_reusable_workflow,
+    );
+
+    apply_optional_value(
+        table,
+        "github-build-action",
+        "# Whether to also generate a composite Github Action that builds with cargo-dist\n",
+        *github_build_action,
+    );
+
+    apply_optional_value(
+        table,
+        "git-author-name",
+        "# The name that publish jobs commit as\n",
+        git_author_name.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "git-author-email",
+        "# The email that publish jobs commit as\n",
+        git_author_email.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "prune-prerelease-assets",
+        "# Whether publishing a prerelease should delete the assets already on its Github Release\n",
+        *prune_prerelease_assets,
+    );
+
+    apply_string_list(
+        table,
+        "include-packages",
+        "# Globs for the names of the packages that can be released\n",
+        include_packages.as_ref(),
+    );
+
+    apply_string_list(
+        table,
+        "exclude-packages",
+        "# Globs for the names of the packages that should never be released\n",
+        exclude_packages.as_ref(),
+    );
+
+    apply_optional_value(
+        table,
+        "installer-shell",
+        "# Which shell the shell installer is written for (\"sh\" or \"bash\")\n",
+        installer_shell.as_ref().map(|s| s.to_string()),
+    );
+
+    apply_optional_value(
+        table,
+        "install-success-url",
+        "# A URL the installers ping after a successful install (opt-in install counts)\n",
+        install_success_url.as_deref(),
+    );
+
     apply_optional_value(
         table,
         "install-path",
@@ -833,6 +1137,206 @@ fn update_toml_metadata(
         allow_dirty.as_ref(),
     );
 
+    apply_optional_value(
+        table,
+        "deny-deprecated-runners",
+        "# Whether generating CI with deprecated Github runners should be an error\n",
+        *deny_deprecated_runners,
+    );
+
+    apply_optional_value(
+        table,
+        "global-build",
+        "# Whether CI should build global artifacts (\"auto\", true, or false)\n",
+        global_build.map(|mode| match mode {
+            GlobalBuildMode::Auto => toml_edit::Value::from("auto"),
+            GlobalBuildMode::Always => toml_edit::Value::from(true),
+            GlobalBuildMode::Never => toml_edit::Value::from(false),
+        }),
+    );
+
+    apply_optional_value(
+        table,
+        "build-matrix",
+        "# Whether to record the CI build matrix as an artifact of each release\n",
+        *build_matrix,
+    );
+
+    apply_optional_value(
+        table,
+        "cosign",
+        "# Whether to sign artifacts with sigstore's cosign (keyless, using Github's OIDC)\n",
+        *cosign,
+    );
+
+    apply_optional_value(
+        table,
+        "fetch-secrets-command",
+        "# A command publish jobs run to fetch secrets (printing NAME=value lines)\n",
+        fetch_secrets_command.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "workflow-name",
+        "# The name of the Github CI workflow\n",
+        workflow_name.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "skip-forks",
+        "# Whether CI should skip publishing when it runs in a fork of the repository\n",
+        *skip_forks,
+    );
+
+    apply_optional_value(
+        table,
+        "cargo-target-dir",
+        "# The CARGO_TARGET_DIR that CI builds in\n",
+        cargo_target_dir.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "free-disk-space",
+        "# Whether CI should free up disk space on Linux runners before building\n",
+        *free_disk_space,
+    );
+
+    apply_optional_value(
+        table,
+        "early-draft-release",
+        "# Whether CI should create a draft release up front that build jobs upload to\n",
+        *early_draft_release,
+    );
+
+    apply_optional_value(
+        table,
+        "announcement-title",
+        "# The format of the announcement's title ({tag} and {version} get filled in)\n",
+        announcement_title.as_deref(),
+    );
+
+    apply_string_list(
+        table,
+        "optional-installers",
+        "# Installers that are allowed to fail to build without blocking the release\n",
+        optional_installers.as_ref(),
+    );
+
+    apply_optional_value(
+        table,
+        "artifact-retention-days",
+        "# How many days Github should keep the artifacts CI jobs upload for later jobs\n",
+        artifact_retention_days.map(i64::from),
+    );
+
+    apply_optional_value(
+        table,
+        "artifact-compression-level",
+        "# The compression level (0-9) CI uploads artifacts for later jobs with\n",
+        artifact_compression_level.map(i64::from),
+    );
+
+    apply_string_list(
+        table,
+        "cache-key-files",
+        "# Extra files whose contents should be part of CI's rust-cache key\n",
+        cache_key_files.as_ref(),
+    );
+
+    apply_optional_value(
+        table,
+        "cache-prefix-key",
+        "# The prefix of CI's rust-cache keys\n",
+        cache_prefix_key.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "github-workflow-dispatch",
+        "# Whether the release workflow can also be run by hand (on: workflow_dispatch)\n",
+        *github_workflow_dispatch,
+    );
+
+    apply_optional_value(
+        table,
+        "run-tests",
+        "# Whether CI should run your tests before building anything\n",
+        *run_tests,
+    );
+
+    apply_optional_value(
+        table,
+        "cache",
+        "# Whether CI's build jobs should cache their builds\n",
+        *cache,
+    );
+
+    apply_optional_value(
+        table,
+        "windows-signing",
+        "# Whether to Authenticode-sign Windows binaries and installers in CI\n",
+        *windows_signing,
+    );
+
+    apply_optional_value(
+        table,
+        "windows-signing-certificate-secret",
+        "# The Github secret with the base64-encoded .pfx certificate to sign with\n",
+        windows_signing_certificate_secret.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "windows-signing-password-secret",
+        "# The Github secret with the password of the signing certificate\n",
+        windows_signing_password_secret.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "github-build-job-name",
+        "# The name of each build job in the Github Actions UI ({targets} and {runner} get filled in)\n",
+        github_build_job_name.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "manifest-provenance",
+        "# Whether to record where each artifact was built in the published dist-manifest.json\n",
+        *manifest_provenance,
+    );
+
+    apply_optional_value(
+        table,
+        "github-attestations",
+        "# Whether to generate SLSA build provenance attestations for artifacts in Github CI\n",
+        *github_attestations,
+    );
+
+    apply_optional_value(
+        table,
+        "github-split-workflows",
+        "# Whether to generate a separate Github CI workflow for each app\n",
+        *github_split_workflows,
+    );
+
+    apply_optional_value(
+        table,
+        "tag-prefix",
+        "# The prefix of the git tags that release this package (instead of its name)\n",
+        tag_prefix.as_deref(),
+    );
+
+    apply_optional_value(
+        table,
+        "download-url-template",
+        "# The URL published artifacts can be downloaded from ({tag} and {filename} get filled in)\n",
+        download_url_template.as_deref(),
+    );
+
     // Finalize the table
     table
         .decor_mut()
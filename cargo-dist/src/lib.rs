@@ -17,14 +17,15 @@ use std::{
 
 use axoasset::LocalAsset;
 use backend::{
-    ci::CiInfo,
+    ci::{github::GithubCiInfo, CiInfo, CiPostProcessor},
     installer::{self, homebrew::HomebrewInstallerInfo, npm::NpmInstallerInfo, InstallerImpl},
     templates::{TemplateEntry, TEMPLATE_INSTALLER_NPM},
 };
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_dist_schema::{Asset, AssetKind, DistManifest, ExecutableAsset};
-use config::{ChecksumStyle, CompressionImpl, Config, GenerateMode, ZipStyle};
+use config::{CargoLockMode, ChecksumStyle, CompressionImpl, Config, GenerateMode, ZipStyle};
 use semver::Version;
+use serde::Serialize;
 use tracing::{info, warn};
 
 use errors::*;
@@ -50,12 +51,48 @@ pub fn do_dist(cfg: &Config) -> Result<DistManifest> {
         ));
     }
 
-    // If you add a CI backend, call its check here
-    let CiInfo { github } = &dist.ci;
-    if let Some(github) = github {
-        github.check_github_ci(&dist)?;
+    let stale = dist.ci.stale_files(&dist, &dist.workspace_dir, &[])?;
+    check_stale_files(stale)?;
+
+    build_artifacts(cfg, &dist)
+}
+
+/// Build artifacts for local testing, without any of the release machinery
+///
+/// This builds the same artifacts as [`do_dist`][] would, but doesn't check that your
+/// CI is up to date, and copies everything it built into `output_dir` (so the paths
+/// in the returned manifest point at those copies). If no `output_dir` is given,
+/// this is `target/distrib-local/`. CI is only computed if `cfg.needs_ci` is set, so
+/// problems that only matter to CI can be kept from stopping local builds.
+pub fn do_build_local(cfg: &Config, output_dir: Option<&Utf8Path>) -> Result<DistManifest> {
+    let dist = tasks::gather_work(cfg)?;
+    if !dist.is_init {
+        return Err(miette!(
+            "please run 'cargo dist init' before running any other commands!"
+        ));
     }
 
+    let mut manifest = build_artifacts(cfg, &dist)?;
+
+    let output_dir = output_dir
+        .map(|dir| dir.to_owned())
+        .unwrap_or_else(|| dist.target_dir.join("distrib-local"));
+    LocalAsset::create_dir_all(&output_dir)?;
+    for artifact in &dist.artifacts {
+        let dest_path = output_dir.join(artifact.file_path.file_name().unwrap());
+        copy_file(&artifact.file_path, &dest_path)?;
+        if let Some(manifest_artifact) = manifest.artifacts.get_mut(&artifact.id) {
+            if manifest_artifact.path.is_some() {
+                manifest_artifact.path = Some(dest_path.to_string());
+            }
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Run all the build steps of a DistGraph, and report what got built
+fn build_artifacts(cfg: &Config, dist: &DistGraph) -> Result<DistManifest> {
     // FIXME: parallelize this by working this like a dependency graph, so we can start
     // bundling up an executable the moment it's built! Note however that you shouldn't
     // parallelize Cargo invocations because it has global state that can get clobbered.
@@ -69,19 +106,27 @@ pub fn do_dist(cfg: &Config) -> Result<DistManifest> {
 
     for artifact in &dist.artifacts {
         eprintln!("bundling {}", artifact.id);
-        init_artifact_dir(&dist, artifact)?;
+        init_artifact_dir(dist, artifact)?;
     }
 
     // Run all the build steps
     for step in &dist.build_steps {
-        run_build_step(&dist, step)?;
+        run_build_step(dist, step)?;
     }
 
     for artifact in &dist.artifacts {
         eprintln!("bundled: {}", artifact.file_path);
     }
 
-    Ok(build_manifest(cfg, &dist))
+    // Now that everything is built we can say how big it all is
+    let mut manifest = build_manifest(cfg, dist);
+    for artifact in &dist.artifacts {
+        if let Some(manifest_artifact) = manifest.artifacts.get_mut(&artifact.id) {
+            manifest_artifact.size = artifact.file_path.metadata().ok().map(|m| m.len());
+        }
+    }
+
+    Ok(manifest)
 }
 
 /// Just generate the manifest produced by `cargo dist build` without building
@@ -145,7 +190,12 @@ fn build_manifest(cfg: &Config, dist: &DistGraph) -> DistManifest {
 
     // ci metadata
     if !dist.ci_style.is_empty() {
-        let CiInfo { github } = &dist.ci;
+        let CiInfo {
+            github,
+            gitlab: _,
+            azure: _,
+            circleci: _,
+        } = &dist.ci;
         let github = github.as_ref().map(|info| cargo_dist_schema::GithubCiInfo {
             artifacts_matrix: Some(info.artifacts_matrix.clone()),
             pr_run_mode: Some(info.pr_run_mode.clone()),
@@ -267,10 +317,21 @@ fn manifest_artifact(
             description = None;
             kind = cargo_dist_schema::ArtifactKind::Checksum;
         }
+        ArtifactKind::BuildMatrix(_) => {
+            install_hint = None;
+            description = None;
+            kind = cargo_dist_schema::ArtifactKind::BuildMatrix;
+        }
     };
 
     let checksum = artifact.checksum.map(|idx| dist.artifact(idx).id.clone());
 
+    // CI signs everything it uploads, so every artifact gets a signature
+    let verify_hint = dist.cosign_identity_regexp.as_ref().map(|identity| {
+        let name = &artifact.id;
+        format!("cosign verify-blob --certificate {name}.pem --signature {name}.sig --certificate-identity-regexp '{identity}' --certificate-oidc-issuer {COSIGN_OIDC_ISSUER} {name}")
+    });
+
     cargo_dist_schema::Artifact {
         name: Some(artifact.id.clone()),
         path: if cfg.no_local_paths {
@@ -280,10 +341,14 @@ fn manifest_artifact(
         },
         target_triples: artifact.target_triples.clone(),
         install_hint,
+        verify_hint,
         description,
         assets,
         kind,
         checksum,
+        size: None,
+        provenance: None,
+        download_url: dist.artifact_url(&artifact.id),
     }
 }
 
@@ -312,9 +377,25 @@ fn run_build_step(dist_graph: &DistGraph, target: &BuildStep) -> Result<()> {
             src_path,
             dest_path,
         }) => Ok(generate_and_write_checksum(checksum, src_path, dest_path)?),
+        BuildStep::GenerateBuildMatrix(BuildMatrixImpl { dest_path }) => {
+            generate_build_matrix(dist_graph, dest_path)
+        }
     }
 }
 
+/// Write out a record of the CI build matrix to dest_path
+fn generate_build_matrix(dist: &DistGraph, dest_path: &Utf8Path) -> Result<()> {
+    let Some(github) = &dist.ci.github else {
+        return Err(miette!(
+            "can't record the build matrix without Github CI enabled"
+        ));
+    };
+    let record = github.build_matrix_record(dist);
+    let json = serde_json::to_string_pretty(&record).into_diagnostic()?;
+    LocalAsset::write_new(&json, dest_path)?;
+    Ok(())
+}
+
 /// Generate a checksum for the src_path to dest_path
 fn generate_and_write_checksum(
     checksum: &ChecksumStyle,
@@ -367,7 +448,7 @@ fn build_cargo_target(dist_graph: &DistGraph, target: &CargoBuildStep) -> Result
         target.target_triple, target.profile
     );
 
-    let mut command = Command::new(&dist_graph.tools.cargo.cmd);
+    let mut command = Command::new(&target.cargo);
     command
         .arg("build")
         .arg("--profile")
@@ -377,6 +458,18 @@ fn build_cargo_target(dist_graph: &DistGraph, target: &CargoBuildStep) -> Result
         .arg(&target.target_triple)
         .env("RUSTFLAGS", &target.rustflags)
         .stdout(std::process::Stdio::piped());
+    for config in &target.cargo_config {
+        command.arg("--config").arg(config);
+    }
+    match dist_graph.cargo_lock_mode {
+        Some(CargoLockMode::Locked) => {
+            command.arg("--locked");
+        }
+        Some(CargoLockMode::Frozen) => {
+            command.arg("--frozen");
+        }
+        None => {}
+    }
     if !target.features.default_features {
         command.arg("--no-default-features");
     }
@@ -483,6 +576,29 @@ fn build_cargo_target(dist_graph: &DistGraph, target: &CargoBuildStep) -> Result
         }
     }
 
+    // If binary-dirs says the binaries end up somewhere else, look for them there instead
+    if let Some(binary_dir) = &target.binary_dir {
+        let exe_ext = if target.target_triple.contains("windows") {
+            ".exe"
+        } else {
+            ""
+        };
+        for exes in expected_exes.values_mut() {
+            for (exe_name, (src_path, _)) in exes.iter_mut() {
+                let exe_path = binary_dir.join(format!("{exe_name}{exe_ext}"));
+                if !exe_path.exists() {
+                    return Err(miette!(
+                        "failed to find bin {} in binary-dirs entry for {} ({})",
+                        exe_name,
+                        target.target_triple,
+                        exe_path
+                    ));
+                }
+                *src_path = exe_path;
+            }
+        }
+    }
+
     // Check that we got everything we expected, and normalize to ArtifactIdx => Artifact Path
     for (package_id, exes) in expected_exes {
         for (exe_name, (src_path, dest_path)) in &exes {
@@ -585,6 +701,10 @@ pub struct GenerateArgs {
     pub check: bool,
     /// Which type(s) of config to generate
     pub modes: Vec<GenerateMode>,
+    /// Root to generate files under (defaults to the workspace dir)
+    pub output_dir: Option<Utf8PathBuf>,
+    /// Hooks to run over generated CI scripts before they're written (or checked)
+    pub ci_post_processors: Vec<Box<dyn CiPostProcessor>>,
 }
 
 fn do_generate_preflight_checks(dist: &DistGraph) -> Result<()> {
@@ -606,10 +726,77 @@ fn do_generate_preflight_checks(dist: &DistGraph) -> Result<()> {
     Ok(())
 }
 
+/// A file written by [`do_generate`][] or [`do_generate_ci`][]
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedFile {
+    /// What kind of file this is (e.g. "Github CI")
+    pub desc: String,
+    /// Where the file was written
+    pub path: String,
+}
+
+/// A generated file that differs from the one on disk (found by --check)
+#[derive(Debug, Clone)]
+pub struct StaleFile {
+    /// Where the file is
+    pub path: String,
+    /// A unified diff from the file on disk to what we'd generate
+    pub diff: String,
+}
+
+impl StaleFile {
+    /// Compare what we'd generate for path to what's on disk,
+    /// returning None if they're the same
+    pub(crate) fn check(path: &Utf8Path, rendered: &str) -> Option<StaleFile> {
+        // FIXME: should we catch all errors, or only LocalAssetNotFound?
+        let existing = LocalAsset::load_string(path).unwrap_or("".to_owned());
+        if rendered == existing {
+            return None;
+        }
+        let diff = similar::TextDiff::from_lines(existing.as_str(), rendered)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("{path} (on disk)"), &format!("{path} (generated)"))
+            .to_string();
+        Some(StaleFile {
+            path: path.to_string(),
+            diff,
+        })
+    }
+}
+
+/// Turn the list of generated files that differ from the ones on disk into the
+/// result of a --check, reporting all of them at once
+pub(crate) fn check_stale_files(mut stale: Vec<StaleFile>) -> DistResult<()> {
+    match stale.len() {
+        0 => Ok(()),
+        1 => {
+            let StaleFile { path, diff } = stale.remove(0);
+            Err(DistError::CheckFileMismatch {
+                file: path,
+                span: (0, diff.len()).into(),
+                diff,
+            })
+        }
+        _ => {
+            let files = stale.iter().map(|f| f.path.clone()).collect();
+            let diff = stale.into_iter().map(|f| f.diff).collect::<String>();
+            Err(DistError::CheckFilesMismatch {
+                files,
+                span: (0, diff.len()).into(),
+                diff,
+            })
+        }
+    }
+}
+
 /// Generate any scripts which are relevant (impl of `cargo dist generate`)
-pub fn do_generate(cfg: &Config, args: &GenerateArgs) -> Result<()> {
+///
+/// Returns the files that were written (none if `check` was set).
+pub fn do_generate(cfg: &Config, args: &GenerateArgs) -> Result<Vec<GeneratedFile>> {
     let dist = gather_work(cfg)?;
     do_generate_preflight_checks(&dist)?;
+    let output_dir = args.output_dir.as_deref().unwrap_or(&dist.workspace_dir);
 
     // If specific modes are specified, operate *only* on those modes
     // Otherwise, choose any modes that are appropriate
@@ -625,47 +812,301 @@ pub fn do_generate(cfg: &Config, args: &GenerateArgs) -> Result<()> {
         args.modes.clone()
     };
 
+    let mut generated = vec![];
+    // With --check, every file that's out of date, so we can report them all at once
+    let mut stale = vec![];
     for mode in modes {
         match mode {
             GenerateMode::Ci => {
-                // If you add a CI backend, call it here
-                let CiInfo { github } = &dist.ci;
-                if let Some(github) = github {
+                if args.check {
+                    stale.extend(dist.ci.stale_files(
+                        &dist,
+                        output_dir,
+                        &args.ci_post_processors,
+                    )?);
+                } else {
                     // Always write if not inferred, otherwise only write
                     // if allow_dirty is off.
-                    if !inferred || !github.allow_dirty {
-                        github.write_to_disk(&dist)?;
-                    }
+                    generated.extend(dist.ci.write_to_disk(
+                        &dist,
+                        output_dir,
+                        &args.ci_post_processors,
+                        inferred,
+                    )?);
+                }
+            }
+        }
+    }
+    // Installers aren't a mode of their own, so they're always checked
+    if args.check {
+        stale.extend(stale_installers(&dist)?);
+    }
+    check_stale_files(stale)?;
+
+    Ok(generated)
+}
+
+/// Find the installers from a previous build that differ from what we'd build now
+///
+/// Installers live in the dist dir rather than the repo, so ones that haven't been
+/// built yet have nothing to be out of date with and are skipped. npm packages are
+/// checked file by file, in the dir they're built in.
+pub(crate) fn stale_installers(dist: &DistGraph) -> Result<Vec<StaleFile>> {
+    let mut stale = vec![];
+    for step in &dist.build_steps {
+        let BuildStep::GenerateInstaller(installer) = step else {
+            continue;
+        };
+        let dest_path = match installer {
+            InstallerImpl::Shell(info) | InstallerImpl::Powershell(info) => &info.dest_path,
+            InstallerImpl::Homebrew(info) => &info.inner.dest_path,
+            InstallerImpl::Npm(info) => {
+                if !info.package_dir.exists() {
+                    continue;
+                }
+                let files = dist
+                    .templates
+                    .render_dir_to_clean_strings(TEMPLATE_INSTALLER_NPM, info)?;
+                for (relpath, rendered) in files {
+                    stale.extend(StaleFile::check(&info.package_dir.join(relpath), &rendered));
                 }
+                continue;
             }
+        };
+        if !dest_path.exists() {
+            continue;
         }
+        if let Some(rendered) = render_installer(dist, installer)? {
+            stale.extend(StaleFile::check(dest_path, &rendered));
+        }
+    }
+    Ok(stale)
+}
+
+/// Compute the Github CI cargo-dist would generate, without generating it
+/// (impl of `cargo dist ci-info`)
+///
+/// This is the same [`GithubCiInfo`][] the release.yml template gets rendered with,
+/// for tools that want to know what CI will do (e.g. which runners build which targets).
+pub fn do_ci_info(cfg: &Config) -> Result<GithubCiInfo> {
+    let mut dist = gather_work(cfg)?;
+    let github = dist.ci.github.take().ok_or(DistError::GithubCiNotEnabled)?;
+    Ok(github)
+}
+
+/// Arguments for `cargo dist verify-manifest` ([`do_verify_manifest`][])
+#[derive(Debug)]
+pub struct VerifyManifestArgs {
+    /// The dist-manifest.json to check the release against (defaults to the release's own)
+    pub manifest: Option<Utf8PathBuf>,
+    /// The tag of the Github Release to check (defaults to the manifest's announcement tag)
+    pub tag: Option<String>,
+    /// The Github repo the release is in, in owner/name format (defaults to the current one)
+    pub repo: Option<String>,
+}
+
+/// What `cargo dist verify-manifest` found on the Github Release
+#[derive(Debug, Serialize)]
+pub struct VerifyManifestReport {
+    /// The tag of the release that was checked
+    pub tag: String,
+    /// Artifacts that are on the release and match the manifest
+    pub verified: Vec<String>,
+    /// Artifacts the manifest lists that aren't on the release
+    pub missing: Vec<String>,
+    /// Artifacts on the release that don't match the manifest, with what's wrong with them
+    pub mismatched: SortedMap<String, String>,
+}
+
+impl VerifyManifestReport {
+    /// How many artifacts are missing or mismatched
+    pub fn problems(&self) -> usize {
+        self.missing.len() + self.mismatched.len()
+    }
+}
+
+/// Check that every artifact in a dist-manifest.json made it onto its Github Release intact
+/// (impl of `cargo dist verify-manifest`)
+///
+/// This downloads the release's assets with the `gh` cli (so it needs to be installed and
+/// logged in) and checks that each artifact the manifest lists is there, with the size the
+/// manifest recorded and the checksum in its checksum artifact.
+pub fn do_verify_manifest(args: &VerifyManifestArgs) -> Result<VerifyManifestReport> {
+    let manifest = args
+        .manifest
+        .as_ref()
+        .map(|path| -> Result<DistManifest> {
+            let src = axoasset::SourceFile::load_local(path)?;
+            Ok(src.deserialize_json()?)
+        })
+        .transpose()?;
+    let tag = args
+        .tag
+        .clone()
+        .or_else(|| manifest.as_ref()?.announcement_tag.clone())
+        .ok_or(DistError::NoReleaseToVerify)?;
+
+    let temp_dir = Utf8PathBuf::try_from(std::env::temp_dir()).into_diagnostic()?;
+    let download_dir = temp_dir.join(format!("cargo-dist-verify-{}", std::process::id()));
+    let report = download_release(&tag, args.repo.as_deref(), &download_dir)
+        .and_then(|()| verify_release_assets(&tag, manifest, &download_dir));
+    if download_dir.exists() {
+        LocalAsset::remove_dir_all(&download_dir)?;
     }
+    report
+}
 
+/// Download every asset of a Github Release into dest_dir
+fn download_release(tag: &str, repo: Option<&str>, dest_dir: &Utf8Path) -> Result<()> {
+    eprintln!("downloading the Github Release for {tag}");
+    let mut command = Command::new("gh");
+    command
+        .arg("release")
+        .arg("download")
+        .arg(tag)
+        .arg("--dir")
+        .arg(dest_dir);
+    if let Some(repo) = repo {
+        command.arg("--repo").arg(repo);
+    }
+    let status = command
+        .status()
+        .into_diagnostic()
+        .wrap_err("failed to run gh (is the Github cli installed?)")?;
+    if !status.success() {
+        return Err(miette!("failed to download the Github Release for {tag}"));
+    }
     Ok(())
 }
 
+/// Check the downloaded assets of a release against its manifest (or the one it was uploaded with)
+fn verify_release_assets(
+    tag: &str,
+    manifest: Option<DistManifest>,
+    assets_dir: &Utf8Path,
+) -> Result<VerifyManifestReport> {
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => {
+            let path = assets_dir.join("dist-manifest.json");
+            if !path.exists() {
+                return Err(miette!(
+                    "the Github Release for {tag} has no dist-manifest.json, pass --manifest to check it against one"
+                ));
+            }
+            let src = axoasset::SourceFile::load_local(&path)?;
+            src.deserialize_json()?
+        }
+    };
+
+    let mut report = VerifyManifestReport {
+        tag: tag.to_owned(),
+        verified: vec![],
+        missing: vec![],
+        mismatched: SortedMap::new(),
+    };
+    for artifact in manifest.artifacts.values() {
+        // Artifacts without a name are purely informative (like `cargo install` hints)
+        let Some(name) = &artifact.name else {
+            continue;
+        };
+        let path = assets_dir.join(name);
+        if !path.exists() {
+            report.missing.push(name.clone());
+            continue;
+        }
+        if let Some(size) = artifact.size {
+            let actual_size = path.metadata().into_diagnostic()?.len();
+            if actual_size != size {
+                report.mismatched.insert(
+                    name.clone(),
+                    format!("the manifest says it's {size} bytes, but it's {actual_size} bytes"),
+                );
+                continue;
+            }
+        }
+        let checksum_name = artifact
+            .checksum
+            .as_ref()
+            .and_then(|id| manifest.artifacts.get(id)?.name.as_ref());
+        if let Some(checksum_name) = checksum_name {
+            let checksum_path = assets_dir.join(checksum_name);
+            // A missing checksum gets reported as its own missing artifact
+            if checksum_path.exists() {
+                let style = if checksum_name.ends_with(ChecksumStyle::Sha512.ext()) {
+                    ChecksumStyle::Sha512
+                } else {
+                    ChecksumStyle::Sha256
+                };
+                let expected = LocalAsset::load_string(&checksum_path)?;
+                let expected = expected.split_whitespace().next().unwrap_or_default();
+                let actual = generate_checksum(&style, &path)?;
+                if expected != actual {
+                    report.mismatched.insert(
+                        name.clone(),
+                        format!("its checksum doesn't match {checksum_name}"),
+                    );
+                    continue;
+                }
+            }
+        }
+        report.verified.push(name.clone());
+    }
+    Ok(report)
+}
+
 /// Arguments for `cargo dist generate-ci` ([`do_generate_ci][])
 #[derive(Debug)]
 pub struct GenerateCiArgs {
     /// Check whether the output differs without writing to disk
     pub check: bool,
+    /// Root to generate files under (defaults to the workspace dir)
+    pub output_dir: Option<Utf8PathBuf>,
+    /// Hooks to run over generated CI scripts before they're written (or checked)
+    pub ci_post_processors: Vec<Box<dyn CiPostProcessor>>,
 }
 
 /// Generate CI scripts (impl of `cargo dist generate-ci`)
-pub fn do_generate_ci(cfg: &Config, args: &GenerateCiArgs) -> Result<()> {
+///
+/// Returns the files that were written (none if `check` was set).
+pub fn do_generate_ci(cfg: &Config, args: &GenerateCiArgs) -> Result<Vec<GeneratedFile>> {
     let dist = gather_work(cfg)?;
     do_generate_preflight_checks(&dist)?;
+    let output_dir = args.output_dir.as_deref().unwrap_or(&dist.workspace_dir);
+
+    if args.check {
+        let stale = dist
+            .ci
+            .stale_files(&dist, output_dir, &args.ci_post_processors)?;
+        check_stale_files(stale)?;
+        return Ok(vec![]);
+    }
+    let generated = dist
+        .ci
+        .write_to_disk(&dist, output_dir, &args.ci_post_processors, false)?;
+    Ok(generated)
+}
 
-    // If you add a CI backend, call its write_to_disk here
-    let CiInfo { github } = &dist.ci;
-    if let Some(github) = github {
-        if args.check {
-            github.check_github_ci(&dist)?;
-        } else {
-            github.write_to_disk(&dist)?;
+/// Render an installer to a string without writing it to disk, for embedding it
+/// somewhere else (like a docs site)
+///
+/// The installers to build can be found in the [`BuildStep::GenerateInstaller`][]s
+/// of the graph from [`gather_work`][]. Returns None for installers that aren't a single
+/// file (npm packages).
+pub fn render_installer(dist: &DistGraph, style: &InstallerImpl) -> Result<Option<String>> {
+    let rendered = match style {
+        InstallerImpl::Shell(info) => {
+            installer::shell::generate_install_sh_script(&dist.templates, info)?
         }
-    }
-    Ok(())
+        InstallerImpl::Powershell(info) => {
+            installer::powershell::generate_install_ps_script(&dist.templates, info)?
+        }
+        InstallerImpl::Npm(_) => return Ok(None),
+        InstallerImpl::Homebrew(info) => {
+            installer::homebrew::generate_homebrew_formula(&dist.templates, dist, info)?
+        }
+    };
+    Ok(Some(rendered))
 }
 
 /// Build a cargo target
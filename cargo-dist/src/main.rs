@@ -10,12 +10,13 @@ use cargo_dist::*;
 use cargo_dist_schema::{AssetKind, DistManifest};
 use clap::Parser;
 use cli::{
-    Cli, Commands, FakeCli, GenerateMode, HelpMarkdownArgs, ManifestArgs, OutputFormat, PlanArgs,
+    CiInfoArgs, Cli, Commands, FakeCli, GenerateMode, HelpMarkdownArgs, ManifestArgs, OutputFormat,
+    PlanArgs, PrintConfigArgs, VerifyManifestArgs,
 };
 use console::Term;
 use miette::IntoDiagnostic;
 
-use crate::cli::{BuildArgs, GenerateArgs, GenerateCiArgs, InitArgs};
+use crate::cli::{BuildArgs, BuildLocalArgs, GenerateArgs, GenerateCiArgs, InitArgs};
 
 mod cli;
 
@@ -38,6 +39,10 @@ fn real_main(cli: &axocli::CliApp<Cli>) -> Result<(), miette::Report> {
         Commands::HelpMarkdown(args) => cmd_help_md(config, args),
         Commands::ManifestSchema(args) => cmd_manifest_schema(config, args),
         Commands::Build(args) => cmd_dist(config, args),
+        Commands::BuildLocal(args) => cmd_build_local(config, args),
+        Commands::PrintConfig(args) => cmd_print_config(config, args),
+        Commands::CiInfo(args) => cmd_ci_info(config, args),
+        Commands::VerifyManifest(args) => cmd_verify_manifest(config, args),
     }
 }
 
@@ -111,6 +116,45 @@ fn print_human(out: &mut Term, manifest: &DistManifest) -> Result<(), std::io::E
     Ok(())
 }
 
+fn print_human_ci_tasks(out: &mut Term, manifest: &DistManifest) -> Result<(), std::io::Error> {
+    let Some(matrix) = manifest
+        .ci
+        .as_ref()
+        .and_then(|ci| ci.github.as_ref())
+        .and_then(|github| github.artifacts_matrix.as_ref())
+    else {
+        return Ok(());
+    };
+
+    // Say which runner builds each target, and how
+    writeln!(out, "github ci build tasks")?;
+    for task in &matrix.include {
+        let targets = task
+            .dist_args
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|arg| arg.strip_prefix("--target="))
+            .collect::<Vec<_>>();
+        let runner = match task.runs_on() {
+            [] => "(unknown runner)".to_owned(),
+            [runner] => runner.clone(),
+            labels => format!("[{}]", labels.join(", ")),
+        };
+        let build_command = task.build_command.as_deref().unwrap_or("(unknown command)");
+        write!(
+            out,
+            "  {} on {runner} with {build_command}",
+            out.style().blue().apply_to(targets.join(", "))
+        )?;
+        if let (Some(shard), Some(shard_count)) = (task.shard, task.shard_count) {
+            write!(out, " (shard {}/{shard_count})", shard + 1)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
 fn print_human_artifact_path(
     out: &mut Term,
     artifact: &cargo_dist_schema::Artifact,
@@ -133,7 +177,7 @@ fn print_human_artifact_path(
     Ok(())
 }
 
-fn print_json(out: &mut Term, report: &DistManifest) -> Result<(), std::io::Error> {
+fn print_json(out: &mut Term, report: &impl serde::Serialize) -> Result<(), std::io::Error> {
     let string = serde_json::to_string_pretty(report).unwrap();
     writeln!(out, "{string}")?;
     Ok(())
@@ -148,6 +192,9 @@ fn cmd_dist(cli: &Cli, args: &BuildArgs) -> Result<(), miette::Report> {
         ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
         installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
         announcement_tag: cli.tag.clone(),
+        build_override: args.build_override(),
+        quiet: cli.quiet,
+        needs_ci: true,
     };
     let report = do_dist(&config)?;
     let mut out = Term::stdout();
@@ -158,6 +205,31 @@ fn cmd_dist(cli: &Cli, args: &BuildArgs) -> Result<(), miette::Report> {
     Ok(())
 }
 
+fn cmd_build_local(cli: &Cli, args: &BuildLocalArgs) -> Result<(), miette::Report> {
+    let config = cargo_dist::config::Config {
+        needs_coherent_announcement_tag: true,
+        // Host mode already does the right thing for "what would I get on this machine",
+        // and respects --target if you want something else
+        artifact_mode: cargo_dist::config::ArtifactMode::Host,
+        no_local_paths: cli.no_local_paths,
+        targets: cli.target.clone(),
+        ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
+        installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
+        announcement_tag: cli.tag.clone(),
+        build_override: Default::default(),
+        quiet: cli.quiet,
+        // Local builds have nothing to do with CI, so don't let its problems stop them
+        needs_ci: false,
+    };
+    let report = do_build_local(&config, args.output_dir.as_deref())?;
+    let mut out = Term::stdout();
+    match cli.output_format {
+        OutputFormat::Human => print_human(&mut out, &report).into_diagnostic()?,
+        OutputFormat::Json => print_json(&mut out, &report).into_diagnostic()?,
+    }
+    Ok(())
+}
+
 fn cmd_manifest(cli: &Cli, args: &ManifestArgs) -> Result<(), miette::Report> {
     let config = cargo_dist::config::Config {
         needs_coherent_announcement_tag: true,
@@ -167,12 +239,16 @@ fn cmd_manifest(cli: &Cli, args: &ManifestArgs) -> Result<(), miette::Report> {
         ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
         installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
         announcement_tag: cli.tag.clone(),
+        build_override: args.build_args.build_override(),
+        quiet: cli.quiet,
+        needs_ci: true,
     };
     let report = do_manifest(&config)?;
     let mut out = Term::stdout();
     match cli.output_format {
         OutputFormat::Human => {
             print_human(&mut out, &report).into_diagnostic()?;
+            print_human_ci_tasks(&mut out, &report).into_diagnostic()?;
 
             // Add some context if we're printing predicted paths
             if !cli.no_local_paths {
@@ -193,12 +269,103 @@ fn cmd_plan(cli: &Cli, _args: &PlanArgs) -> Result<(), miette::Report> {
     let args = &ManifestArgs {
         build_args: BuildArgs {
             artifacts: cli::ArtifactMode::All,
+            features: None,
+            no_default_features: false,
+            all_features: false,
         },
     };
 
     cmd_manifest(&new_cli, args)
 }
 
+fn cmd_print_config(cli: &Cli, _args: &PrintConfigArgs) -> Result<(), miette::Report> {
+    let resolved = resolve_config()?;
+    let mut out = Term::stdout();
+    match cli.output_format {
+        OutputFormat::Human => {
+            let toml = toml::Value::try_from(&resolved).into_diagnostic()?;
+            let rendered = toml::to_string_pretty(&toml).into_diagnostic()?;
+            write!(out, "{rendered}").into_diagnostic()?;
+        }
+        OutputFormat::Json => print_json(&mut out, &resolved).into_diagnostic()?,
+    }
+    Ok(())
+}
+
+fn cmd_ci_info(cli: &Cli, _args: &CiInfoArgs) -> Result<(), miette::Report> {
+    let config = cargo_dist::config::Config {
+        needs_coherent_announcement_tag: false,
+        artifact_mode: cargo_dist::config::ArtifactMode::All,
+        no_local_paths: cli.no_local_paths,
+        targets: cli.target.clone(),
+        ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
+        installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
+        announcement_tag: cli.tag.clone(),
+        build_override: Default::default(),
+        quiet: cli.quiet,
+        needs_ci: true,
+    };
+    let info = do_ci_info(&config)?;
+    // Always json, this is only for machines
+    let mut out = Term::stdout();
+    print_json(&mut out, &info).into_diagnostic()?;
+    Ok(())
+}
+
+fn cmd_verify_manifest(cli: &Cli, args: &VerifyManifestArgs) -> Result<(), miette::Report> {
+    let args = cargo_dist::VerifyManifestArgs {
+        manifest: args.manifest.clone(),
+        tag: cli.tag.clone(),
+        repo: args.repo.clone(),
+    };
+    let report = do_verify_manifest(&args)?;
+    let mut out = Term::stdout();
+    match cli.output_format {
+        OutputFormat::Human => print_human_verify_manifest(&mut out, &report).into_diagnostic()?,
+        OutputFormat::Json => print_json(&mut out, &report).into_diagnostic()?,
+    }
+    let problems = report.problems();
+    if problems > 0 {
+        return Err(errors::DistError::ReleaseVerificationFailed {
+            tag: report.tag,
+            problems,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn print_human_verify_manifest(
+    out: &mut Term,
+    report: &VerifyManifestReport,
+) -> Result<(), std::io::Error> {
+    writeln!(out, "verified the Github Release for {}", report.tag)?;
+    for name in &report.verified {
+        writeln!(
+            out,
+            "  {}",
+            out.style().green().apply_to(format!("[ok] {name}"))
+        )?;
+    }
+    for name in &report.missing {
+        writeln!(
+            out,
+            "  {}",
+            out.style().red().apply_to(format!("[missing] {name}"))
+        )?;
+    }
+    for (name, reason) in &report.mismatched {
+        writeln!(
+            out,
+            "  {}",
+            out.style()
+                .red()
+                .apply_to(format!("[mismatched] {name} ({reason})"))
+        )?;
+    }
+    Ok(())
+}
+
 fn cmd_init(cli: &Cli, args: &InitArgs) -> Result<(), miette::Report> {
     // This command is more automagic, so provide default targets if none are chosen
     let targets = if cli.target.is_empty() {
@@ -214,6 +381,9 @@ fn cmd_init(cli: &Cli, args: &InitArgs) -> Result<(), miette::Report> {
         ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
         installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
         announcement_tag: cli.tag.clone(),
+        build_override: Default::default(),
+        quiet: cli.quiet,
+        needs_ci: true,
     };
     let args = cargo_dist::InitArgs {
         yes: args.yes,
@@ -232,12 +402,32 @@ fn cmd_generate(cli: &Cli, args: &GenerateArgs) -> Result<(), miette::Report> {
         ci: cli.ci.iter().map(|ci| ci.to_lib()).collect(),
         installers: cli.installer.iter().map(|ins| ins.to_lib()).collect(),
         announcement_tag: cli.tag.clone(),
+        build_override: Default::default(),
+        quiet: cli.quiet,
+        needs_ci: true,
     };
     let args = cargo_dist::GenerateArgs {
         check: args.check,
         modes: args.mode.iter().map(|m| m.to_lib()).collect(),
+        output_dir: args.output_dir.clone(),
+        ci_post_processors: vec![],
     };
-    do_generate(&config, &args)
+    let generated = do_generate(&config, &args)?;
+
+    match cli.output_format {
+        OutputFormat::Human => {
+            if !cli.quiet {
+                for file in generated {
+                    eprintln!("generated {} to {}", file.desc, file.path);
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let mut out = Term::stdout();
+            print_json(&mut out, &generated).into_diagnostic()?;
+        }
+    }
+    Ok(())
 }
 
 fn cmd_generate_ci(cli: &Cli, args: &GenerateCiArgs) -> Result<(), miette::Report> {
@@ -246,6 +436,7 @@ fn cmd_generate_ci(cli: &Cli, args: &GenerateCiArgs) -> Result<(), miette::Repor
         &GenerateArgs {
             check: args.check,
             mode: vec![GenerateMode::Ci],
+            output_dir: args.output_dir.clone(),
         },
     )
 }
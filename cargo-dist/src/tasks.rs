@@ -53,14 +53,17 @@ use std::process::Command;
 
 use axoproject::platforms::triple_to_display_name;
 use axoproject::{PackageIdx, WorkspaceInfo};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use cruet::to_class_case;
 use guppy::PackageId;
 use miette::{miette, Context, IntoDiagnostic};
 use semver::Version;
 use tracing::{info, warn};
 
+use crate::backend::ci::azure::AzureCiInfo;
+use crate::backend::ci::circleci::CircleCiInfo;
 use crate::backend::ci::github::GithubCiInfo;
+use crate::backend::ci::gitlab::{self, GitlabCiInfo};
 use crate::backend::ci::CiInfo;
 use crate::{
     backend::{
@@ -71,8 +74,12 @@ use crate::{
         templates::Templates,
     },
     config::{
-        self, ArtifactMode, ChecksumStyle, CiStyle, CompressionImpl, Config, DistMetadata,
-        InstallPathStrategy, InstallerStyle, PublishStyle, ZipStyle,
+        self, ArtifactMode, ArtifactTransport, ArtifactUploadSettings, BuildOverride, BundleConfig,
+        CargoLockMode, CargoRegistry, CargoTargetConfig, ChecksumStyle, CiStyle, CompressionImpl,
+        ConcurrencyMode, Config, DistArchive, DistMetadata, GlobalBuildMode, HomebrewKind,
+        HomebrewTap, InstallDistMethod, InstallPathStrategy, InstallerShell, InstallerStyle,
+        JobTimeouts, PanicStrategy, PublishStyle, ResolvedConfig, RunnerOs, SystemDependencies,
+        ZipStyle, DEFAULT_GIT_AUTHOR_EMAIL, DEFAULT_GIT_AUTHOR_NAME, DEFAULT_WORKFLOW_NAME,
     },
     errors::{DistError, DistResult, Result},
 };
@@ -84,6 +91,8 @@ pub const METADATA_DIST: &str = "dist";
 pub const TARGET_DIST: &str = "distrib";
 /// The profile we will build with
 pub const PROFILE_DIST: &str = "dist";
+/// Env var naming a cargo wrapper (like cross) to build non-host targets with
+pub const BUILD_TOOL_ENV_VAR: &str = "CARGO_DIST_BUILD_TOOL";
 
 /// The key for referring to linux as an "os"
 pub const OS_LINUX: &str = "linux";
@@ -100,6 +109,8 @@ pub const CPU_X86: &str = "x86";
 pub const CPU_ARM64: &str = "arm64";
 /// The key for referring to 32-bit arm as an "cpu"
 pub const CPU_ARM: &str = "arm";
+/// The OIDC issuer of the identities that Github CI signs things with
+pub const COSIGN_OIDC_ISSUER: &str = "https://token.actions.githubusercontent.com";
 
 /// A rust target-triple (e.g. "x86_64-pc-windows-msvc")
 pub type TargetTriple = String;
@@ -149,12 +160,126 @@ pub struct DistGraph {
     pub dist_dir: Utf8PathBuf,
     /// Whether to bother using --package instead of --workspace when building apps
     pub precise_builds: bool,
+    /// Whether cargo builds should pass --locked or --frozen
+    pub cargo_lock_mode: Option<CargoLockMode>,
     /// Whether to try to merge otherwise-parallelizable tasks the same machine
     pub merge_tasks: bool,
     /// Whether failing tasks should make us give up on all other tasks
     pub fail_fast: bool,
     /// Whether to creat a github release or edit an existing draft
     pub create_release: bool,
+    /// Whether the publish job should add a table of every artifact to the Github Release
+    pub release_notes_table: bool,
+    /// How to limit release workflows to one at a time, if at all
+    pub concurrency: Option<ConcurrencyMode>,
+    /// Steps to run at the start of every Github CI job that runs cargo-dist
+    pub github_setup_steps: Vec<SortedMap<String, serde_json::Value>>,
+    /// Archives of cargo-dist that CI should install it from, by target
+    pub cargo_dist_archives: SortedMap<TargetTriple, DistArchive>,
+    /// How many shards to split each Github build task's tests into
+    pub test_shards: u32,
+    /// Github runner labels to use for specific targets instead of the defaults
+    pub github_custom_runners: SortedMap<TargetTriple, cargo_dist_schema::GithubRunnerLabels>,
+    /// Other names to use for the Github Actions that Github CI uses
+    pub github_action_renames: SortedMap<String, String>,
+    /// Whether Github CI should be a reusable workflow (`on: workflow_call`)
+    pub github_reusable_workflow: bool,
+    /// Whether to also generate a composite Github Action for building with cargo-dist
+    pub github_build_action: bool,
+    /// Whether Github CI can also be run by hand (`on: workflow_dispatch`)
+    pub github_workflow_dispatch: bool,
+    /// The name that publish jobs commit as
+    pub git_author_name: String,
+    /// The email that publish jobs commit as
+    pub git_author_email: String,
+    /// Whether publishing a prerelease should delete the assets already on its Github Release
+    pub prune_prerelease_assets: bool,
+    /// Whether using deprecated Github runners should be an error instead of a warning
+    pub deny_deprecated_runners: bool,
+    /// Extra keys to pass through verbatim to each entry of the Github CI build matrix
+    pub github_matrix_extra: SortedMap<String, serde_json::Value>,
+    /// Whether CI should run the task that builds global artifacts
+    pub global_build: GlobalBuildMode,
+    /// Whether to produce a record of the CI build matrix as a global artifact
+    pub build_matrix: bool,
+    /// Whether CI should sign every artifact with cosign (keyless)
+    pub cosign: bool,
+    /// The regex that the certificate identity of cosign signatures should match
+    ///
+    /// This is only known if cosign is enabled and we know the repository's URL.
+    pub cosign_identity_regexp: Option<String>,
+    /// A command publish jobs run to fetch secrets before doing anything sensitive
+    pub fetch_secrets_command: Option<String>,
+    /// The name of the Github CI workflow
+    pub workflow_name: String,
+    /// Whether Github CI should skip publishing in forks of the repository
+    pub skip_forks: bool,
+    /// The CARGO_TARGET_DIR that Github CI builds in
+    pub cargo_target_dir: Option<String>,
+    /// Whether Github CI should free up disk space on Linux runners before building
+    pub free_disk_space: bool,
+    /// Whether Github CI should create a draft release up front that build jobs upload to
+    pub early_draft_release: bool,
+    /// The format of the announcement's title, if not the default
+    pub announcement_title_format: Option<String>,
+    /// Installers that are allowed to fail to build without blocking the release
+    pub optional_installers: Vec<InstallerStyle>,
+    /// Every installer any package is configured to build, regardless of --installer
+    ///
+    /// CI uses this to tell the required installers apart from the optional ones.
+    pub all_installers: Vec<InstallerStyle>,
+    /// Settings for the artifacts Github CI jobs upload for later jobs
+    pub artifact_uploads: ArtifactUploadSettings,
+    /// Per-job overrides of artifact_uploads, keyed by job name
+    pub artifact_upload_overrides: SortedMap<String, ArtifactUploadSettings>,
+    /// System packages Github CI should install before building local artifacts
+    pub dependencies: SystemDependencies,
+    /// Per-target overrides of dependencies
+    pub dependency_overrides: SortedMap<TargetTriple, SystemDependencies>,
+    /// Extra files (or globs) whose contents should be part of Github CI's rust-cache key
+    pub cache_key_files: Vec<String>,
+    /// The prefix of Github CI's rust-cache keys
+    pub cache_prefix_key: Option<String>,
+    /// Whether Github CI's build jobs should cache their builds
+    pub cache: bool,
+    /// Per-target directories to find built binaries in (instead of where cargo put them)
+    pub binary_dirs: SortedMap<TargetTriple, Utf8PathBuf>,
+    /// Whether Github CI should run the tests before building anything
+    pub run_tests: bool,
+    /// Commit SHAs to pin the Github Actions that Github CI uses to
+    pub github_action_pins: SortedMap<String, String>,
+    /// How CI should install cargo-dist on each OS (OSes missing here use their default)
+    pub install_dist_methods: SortedMap<RunnerOs, InstallDistMethod>,
+    /// Cargo feature flags CI should build specific targets with
+    pub build_overrides: SortedMap<TargetTriple, BuildOverride>,
+    /// How many minutes each kind of Github CI job may run
+    pub timeout_minutes: JobTimeouts,
+    /// Where Github CI jobs hand artifacts off to each other
+    pub artifact_transport: ArtifactTransport,
+    /// Github runner labels to fall back to for specific targets, in order
+    pub github_fallback_runners: SortedMap<TargetTriple, Vec<String>>,
+    /// Whether Github CI should Authenticode-sign Windows binaries and installers
+    pub windows_signing: bool,
+    /// The Github secret holding the certificate for windows_signing, if not the default
+    pub windows_signing_certificate_secret: Option<String>,
+    /// The Github secret holding the certificate's password, if not the default
+    pub windows_signing_password_secret: Option<String>,
+    /// The name to give each Github CI build job (with `{targets}`/`{runner}` filled in)
+    pub github_build_job_name: Option<String>,
+    /// Whether Github CI should record where each artifact was built in the published manifest
+    pub manifest_provenance: bool,
+    /// Whether Github CI should generate build provenance attestations for every artifact
+    pub github_attestations: bool,
+    /// Extra permissions to give jobs of Github CI (by job name, then scope)
+    pub github_permissions: SortedMap<String, SortedMap<String, String>>,
+    /// Environment variables to set for every job of Github CI
+    pub github_env: SortedMap<String, String>,
+    /// Environment variables to set in the Github CI jobs that build each target
+    pub github_target_env: SortedMap<TargetTriple, SortedMap<String, String>>,
+    /// Extra cargo config (like the linker) to build each target with
+    pub cargo_target_config: SortedMap<TargetTriple, CargoTargetConfig>,
+    /// Whether to generate a separate Github CI workflow for each app
+    pub github_split_workflows: bool,
     /// The desired cargo-dist version for handling this project
     pub desired_cargo_dist_version: Option<Version>,
     /// The desired rust toolchain for handling this project
@@ -179,6 +304,17 @@ pub struct DistGraph {
     pub announcement_github_body: Option<String>,
     /// Base URL that artifacts are downloadable from ("{artifact_download_url}/{artifact.id}")
     pub artifact_download_url: Option<String>,
+    /// The URL dist-manifest.json says artifacts can be downloaded from, with `{tag}`
+    /// and `{filename}` placeholders (if not the artifact_download_url)
+    pub download_url_template: Option<String>,
+    /// What the binaries we build should do when they panic (if not what the profile says)
+    pub panic_strategy: Option<PanicStrategy>,
+    /// The Github repository of the project, in owner/name format
+    pub github_repo: Option<String>,
+    /// Every target any package is configured to build, regardless of --target
+    ///
+    /// CI that can't be planned at runtime (like Gitlab's) needs jobs for all of them.
+    pub all_targets: Vec<TargetTriple>,
 
     /// Targets we need to build
     pub build_steps: Vec<BuildStep>,
@@ -194,8 +330,18 @@ pub struct DistGraph {
     pub ci: CiInfo,
     /// List of publish jobs to run
     pub publish_jobs: Vec<PublishStyle>,
-    /// A GitHub repo to publish the Homebrew formula to
-    pub tap: Option<String>,
+    /// Conditions (Github Actions expressions) under which each publish job should run
+    pub publish_job_conditions: SortedMap<PublishStyle, String>,
+    /// Names of the CI secrets each publish job should use (if not the default)
+    pub publish_job_secrets: SortedMap<PublishStyle, String>,
+    /// Packages the crates-io publish job publishes, dependencies before dependents
+    pub crates_io_packages: Vec<String>,
+    /// Cargo registries the crates-io publish job publishes to (each gets its own job)
+    pub cargo_registries: Vec<CargoRegistry>,
+    /// Homebrew taps to publish Homebrew formulas and casks to
+    pub taps: Vec<HomebrewTap>,
+    /// Groups of binaries to also ship together in a single archive, by bundle name
+    pub bundles: SortedMap<String, BundleConfig>,
 }
 
 /// Various tools we have found installed on the system
@@ -276,6 +422,8 @@ pub enum BuildStep {
     GenerateInstaller(InstallerImpl),
     /// Checksum a file
     Checksum(ChecksumImpl),
+    /// Write out a record of the CI build matrix
+    GenerateBuildMatrix(BuildMatrixImpl),
     // FIXME: For macos universal builds we'll want
     // Lipo(LipoStep)
 }
@@ -293,6 +441,12 @@ pub struct CargoBuildStep {
     pub profile: String,
     /// The value to set for RUSTFLAGS
     pub rustflags: String,
+    /// Extra `--config` values to pass (from cargo-target-config)
+    pub cargo_config: Vec<String>,
+    /// The cargo to invoke (or a cargo wrapper like cross, for cross-compiling)
+    pub cargo: String,
+    /// The directory to find the built binaries in, if not where cargo says it put them
+    pub binary_dir: Option<Utf8PathBuf>,
     /// Binaries we expect from this build
     pub expected_binaries: Vec<BinaryIdx>,
 }
@@ -348,6 +502,13 @@ pub struct ChecksumImpl {
     pub dest_path: Utf8PathBuf,
 }
 
+/// Write out a record of the CI build matrix
+#[derive(Debug, Clone)]
+pub struct BuildMatrixImpl {
+    /// where to write it
+    pub dest_path: Utf8PathBuf,
+}
+
 /// A kind of symbols (debuginfo)
 #[derive(Copy, Clone, Debug)]
 pub enum SymbolKind {
@@ -432,6 +593,8 @@ pub enum ArtifactKind {
     Installer(InstallerImpl),
     /// A checksum
     Checksum(ChecksumImpl),
+    /// A record of the CI build matrix
+    BuildMatrix(BuildMatrixImpl),
 }
 
 /// An ExecutableZip Artifact
@@ -489,6 +652,8 @@ pub struct Release {
     pub windows_archive: ZipStyle,
     /// Archive format to use on non-windows
     pub unix_archive: ZipStyle,
+    /// Archive formats to use for specific targets (overriding the above)
+    pub target_archive: SortedMap<TargetTriple, ZipStyle>,
     /// Style of checksum to produce
     pub checksum: ChecksumStyle,
     /// The @scope to include in NPM packages
@@ -497,8 +662,27 @@ pub struct Release {
     pub static_assets: Vec<(StaticAssetKind, Utf8PathBuf)>,
     /// Strategy for selecting paths to install to
     pub install_path: InstallPathStrategy,
-    /// GitHub repository to push the Homebrew formula to, if built
-    pub tap: Option<String>,
+    /// Which shell the shell installer is written for
+    pub installer_shell: InstallerShell,
+    /// URL the installers ping after a successful install, if any
+    pub install_success_url: Option<String>,
+    /// Homebrew taps to push Homebrew formulas and casks to, if built
+    pub taps: Vec<HomebrewTap>,
+    /// The prefix of the git tags that release this app (its name, unless tag-prefix is set)
+    pub tag_prefix: String,
+}
+
+impl Release {
+    /// Get the archive format to use for the given target
+    pub fn archive_for_target(&self, target: &str) -> ZipStyle {
+        if let Some(zip_style) = self.target_archive.get(target) {
+            *zip_style
+        } else if target.contains("windows") {
+            self.windows_archive
+        } else {
+            self.unix_archive
+        }
+    }
 }
 
 /// A particular variant of a Release (e.g. "the macos build")
@@ -511,6 +695,8 @@ pub struct ReleaseVariant {
     pub id: String,
     /// Binaries included in this Release Variant
     pub binaries: Vec<BinaryIdx>,
+    /// Extra builds of the binaries with other features, keyed by the feature-variant's name
+    pub feature_variants: SortedMap<String, Vec<BinaryIdx>>,
     /// Static assets that should be included in bundles like executable-zips
     pub static_assets: Vec<(StaticAssetKind, Utf8PathBuf)>,
     /// Artifacts that are "local" to this variant (binaries, symbols, msi-installer...)
@@ -569,6 +755,8 @@ pub(crate) struct DistGraphBuilder<'pkg_graph> {
     pub(crate) inner: DistGraph,
     pub(crate) workspace: &'pkg_graph WorkspaceInfo,
     artifact_mode: ArtifactMode,
+    build_override: BuildOverride,
+    quiet: bool,
     binaries_by_id: FastMap<String, BinaryIdx>,
     workspace_metadata: DistMetadata,
     package_metadata: Vec<DistMetadata>,
@@ -643,16 +831,112 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             create_release,
             pr_run_mode: _,
             allow_dirty,
+            deny_deprecated_runners,
+            github_matrix_extra,
+            global_build,
+            build_matrix,
+            publish_job_conditions,
+            publish_job_secrets,
+            // Only the final value merged into a package_config matters
+            target_archive: _,
+            bundles,
+            // Only the final value merged into a package_config matters
+            taps: _,
+            // Only the final value merged into a package_config matters
+            include_readme: _,
+            // Only the final value merged into a package_config matters
+            include_license: _,
+            release_notes_table,
+            concurrency,
+            github_setup_steps,
+            cargo_dist_archives,
+            test_shards,
+            github_reusable_workflow,
+            github_build_action,
+            git_author_name,
+            git_author_email,
+            prune_prerelease_assets,
+            // Only used when selecting packages in parse_tag
+            include_packages: _,
+            // Only used when selecting packages in parse_tag
+            exclude_packages: _,
+            // Only the final value merged into a package_config matters
+            installer_shell: _,
+            // Only the final value merged into a package_config matters
+            install_success_url: _,
+            github_custom_runners,
+            cargo_lock_mode,
+            github_action_renames,
+            cosign,
+            fetch_secrets_command,
+            workflow_name,
+            skip_forks,
+            cargo_target_dir,
+            free_disk_space,
+            early_draft_release,
+            announcement_title,
+            optional_installers,
+            artifact_retention_days,
+            artifact_compression_level,
+            artifact_upload_overrides,
+            dependencies,
+            dependency_overrides,
+            cache_key_files,
+            cache_prefix_key,
+            github_workflow_dispatch,
+            cache,
+            binary_dirs,
+            // Only read from package configs (which inherit it)
+            feature_variants: _,
+            run_tests,
+            github_action_pins,
+            install_dist_methods,
+            build_overrides,
+            timeout_minutes,
+            artifact_transport,
+            github_fallback_runners,
+            windows_signing,
+            windows_signing_certificate_secret,
+            windows_signing_password_secret,
+            github_build_job_name,
+            manifest_provenance,
+            github_attestations,
+            github_permissions,
+            github_env,
+            github_target_env,
+            cargo_target_config,
+            cargo_registries,
+            github_split_workflows,
+            // Only read from each package's config
+            tag_prefix: _,
+            download_url_template,
+            panic_strategy,
         } = &workspace_metadata;
 
         let desired_cargo_dist_version = cargo_dist_version.clone();
         let desired_rust_toolchain = rust_toolchain_version.clone();
-        if desired_rust_toolchain.is_some() {
-            warn!("rust-toolchain-version is deprecated, use rust-toolchain.toml if you want pinned toolchains");
+        if let Some(version) = &desired_rust_toolchain {
+            warn!("rust-toolchain-version is deprecated, use rust-toolchain.toml if you want pinned toolchains (`cargo dist init` can migrate it for you). The equivalent {} is:\n{}", config::RUST_TOOLCHAIN_FILE, config::rust_toolchain_toml(version));
         }
         let merge_tasks = merge_tasks.unwrap_or(false);
         let fail_fast = fail_fast.unwrap_or(false);
         let create_release = create_release.unwrap_or(true);
+        let release_notes_table = release_notes_table.unwrap_or(false);
+        let github_reusable_workflow = github_reusable_workflow.unwrap_or(false);
+        let github_build_action = github_build_action.unwrap_or(false);
+        let github_workflow_dispatch = github_workflow_dispatch.unwrap_or(false);
+        let prune_prerelease_assets = prune_prerelease_assets.unwrap_or(false);
+        let git_author_name = git_author_name
+            .clone()
+            .unwrap_or_else(|| DEFAULT_GIT_AUTHOR_NAME.to_owned());
+        let git_author_email = git_author_email
+            .clone()
+            .unwrap_or_else(|| DEFAULT_GIT_AUTHOR_EMAIL.to_owned());
+        let deny_deprecated_runners = deny_deprecated_runners.unwrap_or(false);
+        let github_matrix_extra = github_matrix_extra.clone().unwrap_or_default();
+        let global_build = global_build.unwrap_or_default();
+        let build_matrix = build_matrix.unwrap_or(false);
+        let cosign = cosign.unwrap_or(false);
         let mut packages_with_mismatched_features = vec![];
         // Compute/merge package configs
         let mut package_metadata = vec![];
@@ -665,15 +949,53 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             package_config.merge_workspace_config(&workspace_metadata, &package.manifest_path);
 
             // Only do workspace builds if all the packages agree with the workspace feature settings
+            // (and don't need extra builds with other features)
             if &package_config.features != features
                 || &package_config.all_features != all_features
                 || &package_config.default_features != no_default_features
+                || package_config
+                    .feature_variants
+                    .as_ref()
+                    .is_some_and(|variants| !variants.is_empty())
             {
                 packages_with_mismatched_features.push(package.name.clone());
             }
 
+            if let Some(prefix) = &package_config.tag_prefix {
+                // This is matched against tags, and spliced into CI's tag filters
+                let is_valid = !prefix.is_empty()
+                    && prefix
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+                if !is_valid {
+                    return Err(DistError::InvalidTagPrefix {
+                        package: package.name.clone(),
+                        prefix: prefix.clone(),
+                    });
+                }
+            }
+
             package_metadata.push(package_config);
         }
+        for (package, package_config) in workspace.package_info.iter().zip(&package_metadata) {
+            let Some(prefix) = &package_config.tag_prefix else {
+                continue;
+            };
+            let other = workspace.package_info.iter().zip(&package_metadata).find(
+                |(other, other_config)| {
+                    other.name != package.name
+                        && (&other.name == prefix
+                            || other_config.tag_prefix.as_ref() == Some(prefix))
+                },
+            );
+            if let Some((other, _)) = other {
+                return Err(DistError::AmbiguousTagPrefix {
+                    package: package.name.clone(),
+                    prefix: prefix.clone(),
+                    other: other.name.clone(),
+                });
+            }
+        }
 
         let requires_precise = !packages_with_mismatched_features.is_empty();
         let precise_builds = if let Some(precise_builds) = *precise_builds {
@@ -690,9 +1012,158 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
 
         let templates = Templates::new()?;
         let publish_jobs = workspace_metadata.publish_jobs.clone().unwrap_or(vec![]);
+        let crates_io_packages = if publish_jobs.contains(&PublishStyle::CratesIo) {
+            crates_io_publish_order(workspace)?
+        } else {
+            vec![]
+        };
 
         let allow_dirty = allow_dirty.clone().unwrap_or(vec![]);
 
+        let publish_job_conditions = publish_job_conditions.clone().unwrap_or_default();
+        for job in publish_job_conditions.keys() {
+            if !publish_jobs.contains(job) {
+                warn!("publish-job-conditions has a condition for the {job} publish job, but that job isn't enabled\n  consider adding \"{job}\" to publish-jobs in Cargo.toml");
+            }
+        }
+        let publish_job_secrets = publish_job_secrets.clone().unwrap_or_default();
+        for (job, secret) in &publish_job_secrets {
+            if !publish_jobs.contains(job) {
+                warn!("publish-job-secrets has a secret for the {job} publish job, but that job isn't enabled\n  consider adding \"{job}\" to publish-jobs in Cargo.toml");
+            }
+            if !is_valid_secret_name(secret) {
+                return Err(DistError::InvalidSecretName {
+                    job: job.to_string(),
+                    secret: secret.clone(),
+                });
+            }
+        }
+        if cargo_registries.is_some() && !publish_jobs.contains(&PublishStyle::CratesIo) {
+            warn!("cargo-registries is set, but the crates-io publish job isn't enabled\n  consider adding \"crates-io\" to publish-jobs in Cargo.toml");
+        }
+        let cargo_registries = CargoRegistry::from_config(cargo_registries);
+        if let Some(strategy) = panic_strategy {
+            if let Some((profile, panic)) =
+                profile_panic_setting(&workspace.manifest_path, PROFILE_DIST)
+            {
+                warn!("[profile.{profile}] already sets panic = \"{panic}\", but panic-strategy = \"{strategy}\" will be used instead (you probably only want to set one of these)");
+            }
+        }
+        if let Some(template) = download_url_template {
+            if !template.contains("{filename}") {
+                return Err(DistError::DownloadUrlTemplateNoFilename {
+                    template: template.clone(),
+                });
+            }
+        }
+        for (idx, registry) in cargo_registries.iter().enumerate() {
+            // These end up in job names and environment variable names
+            let is_valid = !registry.name.is_empty()
+                && registry
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !is_valid {
+                return Err(DistError::InvalidCargoRegistryName {
+                    name: registry.name.clone(),
+                });
+            }
+            if cargo_registries[..idx]
+                .iter()
+                .any(|r| r.env_prefix() == registry.env_prefix())
+            {
+                return Err(DistError::DuplicateCargoRegistry {
+                    name: registry.name.clone(),
+                });
+            }
+            if let Some(secret) = &registry.token_secret {
+                if !is_valid_secret_name(secret) {
+                    return Err(DistError::InvalidCargoRegistrySecret {
+                        registry: registry.name.clone(),
+                        secret: secret.clone(),
+                    });
+                }
+            }
+            if registry.is_crates_io() && registry.index.is_some() {
+                warn!("cargo-registries sets an index for crates-io, but crates.io's index can't be changed (value is being ignored)");
+            }
+        }
+
+        let artifact_uploads = ArtifactUploadSettings {
+            retention_days: *artifact_retention_days,
+            compression_level: *artifact_compression_level,
+        };
+        let artifact_upload_overrides = artifact_upload_overrides.clone().unwrap_or_default();
+        // upload-artifact only accepts zlib's levels
+        let check_compression_level =
+            |setting: String, settings: &ArtifactUploadSettings| match settings.compression_level {
+                Some(level) if level > 9 => {
+                    Err(DistError::InvalidCompressionLevel { setting, level })
+                }
+                _ => Ok(()),
+            };
+        check_compression_level("artifact-compression-level".to_owned(), &artifact_uploads)?;
+        for (job, settings) in &artifact_upload_overrides {
+            let setting = format!("artifact-upload-overrides.{job}.compression-level");
+            check_compression_level(setting, settings)?;
+        }
+
+        let bundles = bundles.clone().unwrap_or_default();
+        for (bundle_name, bundle) in &bundles {
+            if bundle.binaries.is_empty() {
+                return Err(DistError::EmptyBundle {
+                    bundle: bundle_name.clone(),
+                });
+            }
+            // Executable-zips are named after their package, so this would clobber one
+            if workspace
+                .package_info
+                .iter()
+                .any(|package| &package.name == bundle_name)
+            {
+                return Err(DistError::BundleNameCollision {
+                    bundle: bundle_name.clone(),
+                });
+            }
+            let mut names_in_bundle = SortedSet::new();
+            for binary_name in &bundle.binaries {
+                let name = bundle.name_in_bundle(binary_name);
+                if !names_in_bundle.insert(name) {
+                    return Err(DistError::BundleBinaryCollision {
+                        bundle: bundle_name.clone(),
+                        name: name.to_owned(),
+                    });
+                }
+            }
+        }
+
+        let cargo_dist_archives = cargo_dist_archives.clone().unwrap_or_default();
+        for (target, archive) in &cargo_dist_archives {
+            let is_sha256 =
+                archive.sha256.len() == 64 && archive.sha256.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_sha256 {
+                return Err(DistError::InvalidDistArchiveChecksum {
+                    target: target.clone(),
+                    sha256: archive.sha256.clone(),
+                });
+            }
+        }
+
+        let install_dist_methods = install_dist_methods.clone().unwrap_or_default();
+        for (&os, &method) in &install_dist_methods {
+            let supported = match method {
+                InstallDistMethod::Shell => os != RunnerOs::Windows,
+                InstallDistMethod::Powershell => os == RunnerOs::Windows,
+                InstallDistMethod::Binstall => true,
+            };
+            if !supported {
+                return Err(DistError::UnsupportedInstallDistMethod {
+                    os: os.to_string(),
+                    method: method.to_string(),
+                });
+            }
+        }
+
         Ok(Self {
             inner: DistGraph {
                 is_init: dist_profile.is_some(),
@@ -700,9 +1171,67 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 workspace_dir,
                 dist_dir,
                 precise_builds,
+                cargo_lock_mode: *cargo_lock_mode,
                 fail_fast,
                 merge_tasks,
                 create_release,
+                release_notes_table,
+                concurrency: *concurrency,
+                github_setup_steps: github_setup_steps.clone().unwrap_or_default(),
+                cargo_dist_archives,
+                test_shards: test_shards.unwrap_or(1),
+                github_custom_runners: github_custom_runners.clone().unwrap_or_default(),
+                github_action_renames: github_action_renames.clone().unwrap_or_default(),
+                github_reusable_workflow,
+                github_build_action,
+                github_workflow_dispatch,
+                git_author_name,
+                git_author_email,
+                prune_prerelease_assets,
+                deny_deprecated_runners,
+                github_matrix_extra,
+                global_build,
+                build_matrix,
+                cosign,
+                cosign_identity_regexp: None,
+                fetch_secrets_command: fetch_secrets_command.clone(),
+                workflow_name: workflow_name
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_WORKFLOW_NAME.to_owned()),
+                skip_forks: skip_forks.unwrap_or(false),
+                cargo_target_dir: cargo_target_dir.clone(),
+                free_disk_space: free_disk_space.unwrap_or(false),
+                early_draft_release: early_draft_release.unwrap_or(false),
+                announcement_title_format: announcement_title.clone(),
+                optional_installers: optional_installers.clone().unwrap_or_default(),
+                all_installers: vec![],
+                artifact_uploads,
+                artifact_upload_overrides,
+                dependencies: dependencies.clone().unwrap_or_default(),
+                dependency_overrides: dependency_overrides.clone().unwrap_or_default(),
+                cache_key_files: cache_key_files.clone().unwrap_or_default(),
+                cache_prefix_key: cache_prefix_key.clone(),
+                cache: cache.unwrap_or(true),
+                binary_dirs: binary_dirs.clone().unwrap_or_default(),
+                run_tests: run_tests.unwrap_or(false),
+                github_action_pins: github_action_pins.clone().unwrap_or_default(),
+                install_dist_methods,
+                build_overrides: build_overrides.clone().unwrap_or_default(),
+                timeout_minutes: timeout_minutes.unwrap_or_default(),
+                artifact_transport: artifact_transport.clone().unwrap_or_default(),
+                github_fallback_runners: github_fallback_runners.clone().unwrap_or_default(),
+                windows_signing: windows_signing.unwrap_or(false),
+                windows_signing_certificate_secret: windows_signing_certificate_secret.clone(),
+                windows_signing_password_secret: windows_signing_password_secret.clone(),
+                github_build_job_name: github_build_job_name.clone(),
+                manifest_provenance: manifest_provenance.unwrap_or(false),
+                github_attestations: github_attestations.unwrap_or(false),
+                github_permissions: github_permissions.clone().unwrap_or_default(),
+                github_env: github_env.clone().unwrap_or_default(),
+                github_target_env: github_target_env.clone().unwrap_or_default(),
+                cargo_target_config: cargo_target_config.clone().unwrap_or_default(),
+                github_split_workflows: github_split_workflows.unwrap_or(false),
+                bundles,
                 desired_cargo_dist_version,
                 desired_rust_toolchain,
                 tools,
@@ -713,6 +1242,10 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 announcement_github_body: None,
                 announcement_title: None,
                 artifact_download_url: None,
+                download_url_template: download_url_template.clone(),
+                panic_strategy: *panic_strategy,
+                github_repo: None,
+                all_targets: vec![],
                 ci_style: vec![],
                 build_steps: vec![],
                 artifacts: vec![],
@@ -721,8 +1254,12 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 releases: vec![],
                 ci: CiInfo::default(),
                 pr_run_mode: workspace_metadata.pr_run_mode.clone().unwrap_or_default(),
-                tap: workspace_metadata.tap.clone(),
+                taps: HomebrewTap::from_config(&workspace_metadata.tap, &workspace_metadata.taps),
                 publish_jobs,
+                publish_job_conditions,
+                publish_job_secrets,
+                crates_io_packages,
+                cargo_registries,
                 allow_dirty,
             },
             package_metadata,
@@ -730,6 +1267,8 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             workspace,
             binaries_by_id: FastMap::new(),
             artifact_mode,
+            build_override: BuildOverride::default(),
+            quiet: false,
         })
     }
 
@@ -737,11 +1276,31 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         &self.package_metadata[idx.0]
     }
 
+    /// Get the config we ended up with for the workspace and each package
+    fn resolved_config(&self) -> ResolvedConfig {
+        let mut workspace = self.workspace_metadata.clone();
+        workspace.fill_defaults(true);
+        workspace.precise_builds = Some(self.inner.precise_builds);
+        let packages = self
+            .workspace
+            .packages()
+            .map(|(pkg_idx, package)| {
+                let mut package_config = self.package_metadata(pkg_idx).clone();
+                package_config.fill_defaults(false);
+                (package.name.clone(), package_config)
+            })
+            .collect();
+        ResolvedConfig {
+            workspace,
+            packages,
+        }
+    }
+
     fn set_ci_style(&mut self, style: Vec<CiStyle>) {
         self.inner.ci_style = style;
     }
 
-    fn add_release(&mut self, pkg_idx: PackageIdx) -> ReleaseIdx {
+    pub(crate) fn add_release(&mut self, pkg_idx: PackageIdx) -> ReleaseIdx {
         let package_info = self.workspace().package(pkg_idx);
         let package_config = self.package_metadata(pkg_idx);
 
@@ -758,26 +1317,37 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             .install_path
             .clone()
             .unwrap_or(InstallPathStrategy::CargoHome);
-        let tap = package_config.tap.clone();
+        let installer_shell = package_config.installer_shell.unwrap_or_default();
+        let install_success_url = package_config.install_success_url.clone();
+        let taps = HomebrewTap::from_config(&package_config.tap, &package_config.taps);
+        let tag_prefix = package_config
+            .tag_prefix
+            .clone()
+            .unwrap_or_else(|| app_name.clone());
 
         let windows_archive = package_config.windows_archive.unwrap_or(ZipStyle::Zip);
         let unix_archive = package_config
             .unix_archive
             .unwrap_or(ZipStyle::Tar(CompressionImpl::Xzip));
+        let target_archive = package_config.target_archive.clone().unwrap_or_default();
         let checksum = package_config.checksum.unwrap_or(ChecksumStyle::Sha256);
 
         // Add static assets
         let mut static_assets = vec![];
         let auto_includes_enabled = package_config.auto_includes.unwrap_or(true);
         if auto_includes_enabled {
-            if let Some(readme) = &package_info.readme_file {
-                static_assets.push((StaticAssetKind::Readme, readme.clone()));
+            if package_config.include_readme.unwrap_or(true) {
+                if let Some(readme) = &package_info.readme_file {
+                    static_assets.push((StaticAssetKind::Readme, readme.clone()));
+                }
             }
             if let Some(changelog) = &package_info.changelog_file {
                 static_assets.push((StaticAssetKind::Changelog, changelog.clone()));
             }
-            for license in &package_info.license_files {
-                static_assets.push((StaticAssetKind::License, license.clone()));
+            if package_config.include_license.unwrap_or(true) {
+                for license in license_files_for_package(package_info) {
+                    static_assets.push((StaticAssetKind::License, license));
+                }
             }
         }
         if let Some(include) = &package_config.include {
@@ -807,16 +1377,24 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             changelog_title: None,
             windows_archive,
             unix_archive,
+            target_archive,
             static_assets,
             checksum,
             npm_scope,
             install_path,
-            tap,
+            installer_shell,
+            install_success_url,
+            taps,
+            tag_prefix,
         });
         idx
     }
 
-    fn add_variant(&mut self, to_release: ReleaseIdx, target: TargetTriple) -> ReleaseVariantIdx {
+    pub(crate) fn add_variant(
+        &mut self,
+        to_release: ReleaseIdx,
+        target: TargetTriple,
+    ) -> ReleaseVariantIdx {
         let idx = ReleaseVariantIdx(self.inner.variants.len());
         let Release {
             id: release_id,
@@ -835,6 +1413,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
 
         // Add all the binaries of the release to this variant
         let mut binaries = vec![];
+        let mut feature_variants = SortedMap::<String, Vec<BinaryIdx>>::new();
         for (pkg_idx, binary_name) in bins.clone() {
             let package = self.workspace.package(pkg_idx);
             let package_metadata = self.package_metadata(pkg_idx);
@@ -847,39 +1426,70 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             let pkg_spec = package.name.clone();
             let id = format!("{binary_name}-v{version}-{target}");
 
+            // Feature flags from the CLI win over the package's config
+            let BuildOverride {
+                features,
+                no_default_features,
+                all_features,
+            } = &self.build_override;
+            let default_features = no_default_features
+                .map(|no_default| !no_default)
+                .or(package_metadata.default_features);
+            let all_features = all_features.or(package_metadata.all_features);
+            let features = features.as_ref().or(package_metadata.features.as_ref());
             let features = CargoTargetFeatures {
-                default_features: package_metadata.default_features.unwrap_or(true),
-                features: if let Some(true) = package_metadata.all_features {
+                default_features: default_features.unwrap_or(true),
+                features: if let Some(true) = all_features {
                     CargoTargetFeatureList::All
                 } else {
-                    CargoTargetFeatureList::List(
-                        package_metadata.features.clone().unwrap_or_default(),
-                    )
+                    CargoTargetFeatureList::List(features.cloned().unwrap_or_default())
                 },
             };
 
-            // If we already are building this binary we don't need to do it again!
-            let idx = if let Some(&idx) = self.binaries_by_id.get(&id) {
-                idx
-            } else {
-                info!("added binary {id}");
-                let idx = BinaryIdx(self.inner.binaries.len());
-                let binary = Binary {
-                    id,
-                    pkg_id,
-                    pkg_spec,
-                    name: binary_name,
-                    target: target.clone(),
-                    copy_exe_to: vec![],
-                    copy_symbols_to: vec![],
-                    symbols_artifact: None,
-                    features,
+            // Each feature-variant builds its own copy of the binary, so its id needs the
+            // variant's name to keep their outputs (like symbols) from colliding
+            let mut builds = vec![(None, id, features)];
+            for (variant_name, variant) in package_metadata.feature_variants.iter().flatten() {
+                let features = CargoTargetFeatures {
+                    default_features: variant.default_features.unwrap_or(true),
+                    features: if let Some(true) = variant.all_features {
+                        CargoTargetFeatureList::All
+                    } else {
+                        CargoTargetFeatureList::List(variant.features.clone().unwrap_or_default())
+                    },
+                };
+                let id = format!("{binary_name}-{variant_name}-v{version}-{target}");
+                builds.push((Some(variant_name.clone()), id, features));
+            }
+
+            for (variant_name, id, features) in builds {
+                // If we already are building this binary we don't need to do it again!
+                let idx = if let Some(&idx) = self.binaries_by_id.get(&id) {
+                    idx
+                } else {
+                    info!("added binary {id}");
+                    let idx = BinaryIdx(self.inner.binaries.len());
+                    let binary = Binary {
+                        id,
+                        pkg_id: pkg_id.clone(),
+                        pkg_spec: pkg_spec.clone(),
+                        name: binary_name.clone(),
+                        target: target.clone(),
+                        copy_exe_to: vec![],
+                        copy_symbols_to: vec![],
+                        symbols_artifact: None,
+                        features,
+                    };
+                    self.inner.binaries.push(binary);
+                    idx
                 };
-                self.inner.binaries.push(binary);
-                idx
-            };
 
-            binaries.push(idx);
+                if let Some(variant_name) = variant_name {
+                    feature_variants.entry(variant_name).or_default().push(idx);
+                } else {
+                    binaries.push(idx);
+                }
+            }
         }
 
         self.inner.variants.push(ReleaseVariant {
@@ -887,17 +1497,23 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             id,
             local_artifacts: vec![],
             binaries,
+            feature_variants,
             static_assets,
         });
         idx
     }
 
-    fn add_binary(&mut self, to_release: ReleaseIdx, pkg_idx: PackageIdx, binary_name: String) {
+    pub(crate) fn add_binary(
+        &mut self,
+        to_release: ReleaseIdx,
+        pkg_idx: PackageIdx,
+        binary_name: String,
+    ) {
         let release = self.release_mut(to_release);
         release.bins.push((pkg_idx, binary_name));
     }
 
-    fn add_executable_zip(&mut self, to_release: ReleaseIdx) {
+    pub(crate) fn add_executable_zip(&mut self, to_release: ReleaseIdx) {
         if !self.local_artifacts_enabled() {
             return;
         }
@@ -922,6 +1538,139 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             if checksum != ChecksumStyle::False {
                 self.add_artifact_checksum(variant_idx, zip_artifact_idx, checksum);
             }
+
+            // Feature-variants get their own executable-zips alongside the normal one
+            // (installers only ever look at the normal one)
+            let feature_variants = self.variant(variant_idx).feature_variants.clone();
+            for (variant_name, binaries) in feature_variants {
+                let variant = self.variant(variant_idx);
+                let artifact_dir_name = format!(
+                    "{}-{variant_name}-{}",
+                    self.release(to_release).id,
+                    variant.target
+                );
+                let (zip_artifact, built_assets) =
+                    self.make_executable_zip(to_release, variant_idx, artifact_dir_name, &binaries);
+
+                let zip_artifact_idx = self.add_local_artifact(variant_idx, zip_artifact);
+                for (binary, dest_path) in built_assets {
+                    self.require_binary(zip_artifact_idx, variant_idx, binary, dest_path);
+                }
+
+                if checksum != ChecksumStyle::False {
+                    self.add_artifact_checksum(variant_idx, zip_artifact_idx, checksum);
+                }
+            }
+        }
+    }
+
+    /// Add an archive for each bundle of binaries on each target we're building
+    ///
+    /// Bundles can contain binaries from several Releases, so they're added once
+    /// all the Releases and their Variants are known. The archive gets attached to
+    /// the Variant of the first binary in the bundle (which also decides things like
+    /// the archive format and static assets).
+    pub(crate) fn add_bundles(&mut self) {
+        if !self.local_artifacts_enabled() {
+            return;
+        }
+        let bundles = self.inner.bundles.clone();
+        let targets = self
+            .inner
+            .variants
+            .iter()
+            .map(|variant| variant.target.clone())
+            .collect::<SortedSet<_>>();
+        for (bundle_name, bundle) in &bundles {
+            for target in &targets {
+                // Find every binary of the bundle among the variants for this target
+                let mut found = vec![];
+                let mut missing = vec![];
+                for binary_name in &bundle.binaries {
+                    let binary = self.inner.variants.iter().enumerate().find_map(
+                        |(variant_idx, variant)| {
+                            if &variant.target != target {
+                                return None;
+                            }
+                            variant
+                                .binaries
+                                .iter()
+                                .find(|&&idx| &self.binary(idx).name == binary_name)
+                                .map(|&idx| (ReleaseVariantIdx(variant_idx), idx))
+                        },
+                    );
+                    if let Some(binary) = binary {
+                        found.push((binary_name, binary));
+                    } else {
+                        missing.push(binary_name);
+                    }
+                }
+                if found.is_empty() {
+                    info!("skipping bundle {bundle_name} for {target}: none of its binaries are being released");
+                    continue;
+                }
+                if !missing.is_empty() {
+                    warn!("skipping bundle {bundle_name} for {target}: these binaries aren't being released for it: {missing:?}");
+                    continue;
+                }
+
+                let (_, (owner_variant_idx, _)) = found[0];
+                let owner_release_idx = ReleaseIdx(
+                    self.inner
+                        .releases
+                        .iter()
+                        .position(|release| release.variants.contains(&owner_variant_idx))
+                        .expect("variant wasn't part of any release!?"),
+                );
+                let owner_release = self.release(owner_release_idx);
+                let checksum = owner_release.checksum;
+                let zip_style = owner_release.archive_for_target(target);
+                let static_assets = self.variant(owner_variant_idx).static_assets.clone();
+                let platform_exe_ext = if target.contains("windows") {
+                    ".exe"
+                } else {
+                    ""
+                };
+
+                let dist_dir = &self.inner.dist_dir;
+                let artifact_dir_name = format!("{bundle_name}-{target}");
+                let artifact_dir_path = dist_dir.join(&artifact_dir_name);
+                let artifact_name = format!("{artifact_dir_name}{}", zip_style.ext());
+                let artifact_path = dist_dir.join(&artifact_name);
+                // Same layout as executable-zips
+                let with_root = if let ZipStyle::Zip = zip_style {
+                    None
+                } else {
+                    Some(Utf8PathBuf::from(artifact_dir_name.clone()))
+                };
+
+                info!("adding bundle {artifact_name}");
+                let bundle_artifact = Artifact {
+                    id: artifact_name,
+                    target_triples: vec![target.clone()],
+                    file_path: artifact_path,
+                    required_binaries: FastMap::new(),
+                    archive: Some(Archive {
+                        with_root,
+                        dir_path: artifact_dir_path.clone(),
+                        zip_style,
+                        static_assets,
+                    }),
+                    kind: ArtifactKind::ExecutableZip(ExecutableZip {}),
+                    checksum: None,
+                    is_global: false,
+                };
+                let bundle_idx = self.add_local_artifact(owner_variant_idx, bundle_artifact);
+                for (binary_name, (_, binary_idx)) in found {
+                    let exe_name = bundle.name_in_bundle(binary_name);
+                    let dest_path = artifact_dir_path.join(format!("{exe_name}{platform_exe_ext}"));
+                    self.require_binary(bundle_idx, owner_variant_idx, binary_idx, dest_path);
+                }
+
+                if checksum != ChecksumStyle::False {
+                    self.add_artifact_checksum(owner_variant_idx, bundle_idx, checksum);
+                }
+            }
         }
     }
 
@@ -931,8 +1680,32 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         artifact_idx: ArtifactIdx,
         checksum: ChecksumStyle,
     ) -> ArtifactIdx {
+        let checksum_artifact = self.make_artifact_checksum(artifact_idx, checksum);
+        let checksum_idx = self.add_local_artifact(to_variant, checksum_artifact);
+        self.artifact_mut(artifact_idx).checksum = Some(checksum_idx);
+        checksum_idx
+    }
+
+    fn add_global_artifact_checksum(
+        &mut self,
+        to_release: ReleaseIdx,
+        artifact_idx: ArtifactIdx,
+        checksum: ChecksumStyle,
+    ) -> ArtifactIdx {
+        let checksum_artifact = self.make_artifact_checksum(artifact_idx, checksum);
+        let checksum_idx = self.add_global_artifact(to_release, checksum_artifact);
+        self.artifact_mut(artifact_idx).checksum = Some(checksum_idx);
+        checksum_idx
+    }
+
+    /// Make a checksum for an artifact, but don't yet integrate it into the graph
+    fn make_artifact_checksum(
+        &self,
+        artifact_idx: ArtifactIdx,
+        checksum: ChecksumStyle,
+    ) -> Artifact {
         let artifact = self.artifact(artifact_idx);
-        let checksum_artifact = {
+        {
             let checksum_ext = checksum.ext();
             let checksum_id = format!("{}.{}", artifact.id, checksum_ext);
             let checksum_path = artifact.file_path.parent().unwrap().join(&checksum_id);
@@ -950,12 +1723,9 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 required_binaries: Default::default(),
                 // Who checksums the checksummers...
                 checksum: None,
-                is_global: false,
+                is_global: artifact.is_global,
             }
-        };
-        let checksum_idx = self.add_local_artifact(to_variant, checksum_artifact);
-        self.artifact_mut(artifact_idx).checksum = Some(checksum_idx);
-        checksum_idx
+        }
     }
 
     /// Make an executable zip for a variant, but don't yet integrate it into the graph
@@ -965,6 +1735,23 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         &self,
         release_idx: ReleaseIdx,
         variant_idx: ReleaseVariantIdx,
+    ) -> (Artifact, Vec<(BinaryIdx, Utf8PathBuf)>) {
+        let variant = self.variant(variant_idx);
+        self.make_executable_zip(
+            release_idx,
+            variant_idx,
+            variant.id.clone(),
+            &variant.binaries,
+        )
+    }
+
+    /// Make an executable-zip of the given binaries of a Variant, named artifact_dir_name
+    fn make_executable_zip(
+        &self,
+        release_idx: ReleaseIdx,
+        variant_idx: ReleaseVariantIdx,
+        artifact_dir_name: String,
+        binaries: &[BinaryIdx],
     ) -> (Artifact, Vec<(BinaryIdx, Utf8PathBuf)>) {
         // This is largely just a lot of path/name computation
         let dist_dir = &self.inner.dist_dir;
@@ -972,14 +1759,9 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         let variant = self.variant(variant_idx);
 
         let target_is_windows = variant.target.contains("windows");
-        let zip_style = if target_is_windows {
-            release.windows_archive
-        } else {
-            release.unix_archive
-        };
+        let zip_style = release.archive_for_target(&variant.target);
         let platform_exe_ext = if target_is_windows { ".exe" } else { "" };
 
-        let artifact_dir_name = variant.id.clone();
         let artifact_dir_path = dist_dir.join(&artifact_dir_name);
         let artifact_ext = zip_style.ext();
         let artifact_name = format!("{artifact_dir_name}{artifact_ext}");
@@ -987,16 +1769,16 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
 
         let static_assets = variant.static_assets.clone();
         let mut built_assets = Vec::new();
-        for &binary_idx in &variant.binaries {
+        for &binary_idx in binaries {
             let binary = self.binary(binary_idx);
             let exe_name = &binary.name;
             let exe_filename = format!("{exe_name}{platform_exe_ext}");
             built_assets.push((binary_idx, artifact_dir_path.join(exe_filename)));
         }
 
-        // When unpacking we currently rely on zips being flat, but --strip-prefix=1 tarballs.
-        // This is kinda inconsistent, so maybe we should make both flat?
-        // (It's hard to strip-prefix zips, so making them both have an extra dir is annoying)
+        // Zips are flat but tarballs have an extra root dir. This is kinda inconsistent,
+        // so maybe we should make both flat? (The installers don't care either way,
+        // they look for the binaries wherever they were unpacked to)
         let with_root = if let ZipStyle::Zip = zip_style {
             None
         } else {
@@ -1088,7 +1870,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             .insert(binary_idx, dest_path);
     }
 
-    fn add_installer(&mut self, to_release: ReleaseIdx, installer: &InstallerStyle) {
+    pub(crate) fn add_installer(&mut self, to_release: ReleaseIdx, installer: &InstallerStyle) {
         match installer {
             InstallerStyle::Shell => self.add_shell_installer(to_release),
             InstallerStyle::Powershell => self.add_powershell_installer(to_release),
@@ -1110,7 +1892,8 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         let artifact_name = format!("{release_id}-installer.sh");
         let artifact_path = self.inner.dist_dir.join(&artifact_name);
         let installer_url = format!("{download_url}/{artifact_name}");
-        let hint = format!("curl --proto '=https' --tlsv1.2 -LsSf {installer_url} | sh");
+        let shell = release.installer_shell;
+        let hint = format!("curl --proto '=https' --tlsv1.2 -LsSf {installer_url} | {shell}");
         let desc = "Install prebuilt binaries via shell script".to_owned();
 
         // If they have an x64 macos build but not an arm64 one, add a fallback entry
@@ -1184,6 +1967,8 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 app_name: release.app_name.clone(),
                 app_version: release.version.to_string(),
                 install_path: release.install_path.clone().into_jinja(),
+                shell: release.installer_shell,
+                success_url: release.install_success_url.clone(),
                 base_url: download_url.clone(),
                 artifacts,
                 hint,
@@ -1200,24 +1985,11 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
             return;
         }
         let release = self.release(to_release);
-        let release_id = &release.id;
-        let Some(download_url) = &self.inner.artifact_download_url else {
+        let Some(download_url) = self.inner.artifact_download_url.clone() else {
             warn!("skipping Homebrew formula: couldn't compute a URL to download artifacts from");
             return;
         };
 
-        let artifact_name = format!("{release_id}.rb");
-        let artifact_path = self.inner.dist_dir.join(&artifact_name);
-
-        // If tap is specified, include that in the `brew install` message
-        let mut install_target = release.app_name.clone();
-        if let Some(tap) = &self.inner.tap {
-            install_target = format!("{tap}/{install_target}").to_owned();
-        }
-
-        let hint = format!("brew install {}", install_target);
-        let desc = "Install prebuilt binaries via Homebrew".to_owned();
-
         // If they have an x64 macos build but not an arm64 one, add a fallback entry
         // to try to install x64 on arm64 and let rosetta2 deal with it.
         //
@@ -1290,54 +2062,105 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         };
 
         let release = self.release(to_release);
-        let app_name = release.app_name.clone();
-        let app_desc = release.app_desc.clone();
-        let app_license = release.app_license.clone();
-        let app_homepage_url = release.app_homepage_url.clone();
-        let tap = release.tap.clone();
+        let taps = release.taps.clone();
 
-        if tap.is_some() && !self.inner.publish_jobs.contains(&PublishStyle::Homebrew) {
+        if !taps.is_empty() && !self.inner.publish_jobs.contains(&PublishStyle::Homebrew) {
             warn!("A Homebrew tap was specified but the Homebrew publish job is disabled\n  consider adding \"homebrew\" to publish-jobs in Cargo.toml");
         }
-        if self.inner.publish_jobs.contains(&PublishStyle::Homebrew) && tap.is_none() {
+        if self.inner.publish_jobs.contains(&PublishStyle::Homebrew) && taps.is_empty() {
             warn!("The Homebrew publish job is enabled but no tap was specified\n  consider setting the tap field in Cargo.toml");
         }
 
-        let formula_name = to_class_case(&app_name);
+        // We always make a formula unless every tap wants a cask, and make a cask
+        // if any tap wants one
+        let mut kinds = vec![];
+        if taps.is_empty() || taps.iter().any(|t| t.kind == HomebrewKind::Formula) {
+            kinds.push(HomebrewKind::Formula);
+        }
+        if taps.iter().any(|t| t.kind == HomebrewKind::Cask) {
+            kinds.push(HomebrewKind::Cask);
+        }
 
-        let installer_artifact = Artifact {
-            id: artifact_name,
-            target_triples: target_triples.into_iter().collect(),
-            archive: None,
-            file_path: artifact_path.clone(),
-            required_binaries: FastMap::new(),
-            checksum: None,
-            kind: ArtifactKind::Installer(InstallerImpl::Homebrew(HomebrewInstallerInfo {
-                arm64,
-                arm64_sha256: None,
-                x86_64,
-                x86_64_sha256: None,
-                name: app_name,
-                formula_class: formula_name,
-                desc: app_desc,
-                license: app_license,
-                homepage: app_homepage_url,
-                tap,
-                inner: InstallerInfo {
-                    dest_path: artifact_path,
-                    app_name: release.app_name.clone(),
-                    app_version: release.version.to_string(),
-                    install_path: release.install_path.clone().into_jinja(),
-                    base_url: download_url.clone(),
-                    artifacts,
-                    hint,
-                    desc,
-                },
-            })),
-            is_global: true,
-        };
+        for kind in kinds {
+            let release = self.release(to_release);
+            let release_id = &release.id;
+            let app_name = release.app_name.clone();
+            let tap = taps.iter().find(|t| t.kind == kind).map(|t| t.repo.clone());
 
-        self.add_global_artifact(to_release, installer_artifact);
+            let (artifact_name, hint) = match kind {
+                HomebrewKind::Formula => (format!("{release_id}.rb"), "brew install"),
+                HomebrewKind::Cask => (format!("{release_id}.cask.rb"), "brew install --cask"),
+            };
+            let artifact_path = self.inner.dist_dir.join(&artifact_name);
+
+            // If tap is specified, include that in the `brew install` message
+            let mut install_target = app_name.clone();
+            if let Some(tap) = &tap {
+                install_target = format!("{tap}/{install_target}").to_owned();
+            }
+            let hint = format!("{hint} {install_target}");
+            let desc = "Install prebuilt binaries via Homebrew".to_owned();
+
+            // Casks don't strip the root dir of a tarball for us, so point at the
+            // binaries inside of it
+            let (mut arm64, mut x86_64, mut artifacts) =
+                (arm64.clone(), x86_64.clone(), artifacts.clone());
+            if kind == HomebrewKind::Cask {
+                for fragment in arm64
+                    .iter_mut()
+                    .chain(x86_64.iter_mut())
+                    .chain(artifacts.iter_mut())
+                {
+                    if fragment.zip_style != ZipStyle::Zip {
+                        let root = fragment
+                            .id
+                            .strip_suffix(fragment.zip_style.ext())
+                            .unwrap_or(&fragment.id)
+                            .to_owned();
+                        for binary in &mut fragment.binaries {
+                            *binary = format!("{root}/{binary}");
+                        }
+                    }
+                }
+            }
+
+            let installer_artifact = Artifact {
+                id: artifact_name,
+                target_triples: target_triples.iter().cloned().collect(),
+                archive: None,
+                file_path: artifact_path.clone(),
+                required_binaries: FastMap::new(),
+                checksum: None,
+                kind: ArtifactKind::Installer(InstallerImpl::Homebrew(HomebrewInstallerInfo {
+                    kind,
+                    arm64,
+                    arm64_sha256: None,
+                    x86_64,
+                    x86_64_sha256: None,
+                    name: app_name.clone(),
+                    formula_class: to_class_case(&app_name),
+                    desc: release.app_desc.clone(),
+                    license: release.app_license.clone(),
+                    homepage: release.app_homepage_url.clone(),
+                    tap,
+                    inner: InstallerInfo {
+                        dest_path: artifact_path,
+                        app_name,
+                        app_version: release.version.to_string(),
+                        install_path: release.install_path.clone().into_jinja(),
+                        shell: release.installer_shell,
+                        success_url: release.install_success_url.clone(),
+                        base_url: download_url.clone(),
+                        artifacts,
+                        hint,
+                        desc,
+                    },
+                })),
+                is_global: true,
+            };
+
+            self.add_global_artifact(to_release, installer_artifact);
+        }
     }
 
     fn add_powershell_installer(&mut self, to_release: ReleaseIdx) {
@@ -1402,6 +2225,8 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 app_name: release.app_name.clone(),
                 app_version: release.version.to_string(),
                 install_path: release.install_path.clone().into_jinja(),
+                shell: release.installer_shell,
+                success_url: release.install_success_url.clone(),
                 base_url: download_url.clone(),
                 artifacts,
                 hint,
@@ -1485,7 +2310,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         }
 
         if has_sketchy_archives {
-            warn!("the npm installer currently only knows how to unpack .tar.gz archives\n  consider setting windows-archive and unix-archive to .tar.gz in your config");
+            warn!("the npm installer currently only knows how to unpack .tar.gz archives\n  consider setting windows-archive, unix-archive, and any target-archive entries to .tar.gz in your config");
         }
         if artifacts.is_empty() {
             warn!("skipping npm installer: not building any supported platforms (use --artifacts=global)");
@@ -1521,6 +2346,8 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                     app_name: release.app_name.clone(),
                     app_version: release.version.to_string(),
                     install_path: release.install_path.clone().into_jinja(),
+                    shell: release.installer_shell,
+                    success_url: release.install_success_url.clone(),
                     base_url: download_url.clone(),
                     artifacts,
                     hint,
@@ -1533,6 +2360,38 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         self.add_global_artifact(to_release, installer_artifact);
     }
 
+    fn add_build_matrix(&mut self, to_release: ReleaseIdx) {
+        if !self.inner.build_matrix || !self.global_artifacts_enabled() {
+            return;
+        }
+        if !self.inner.ci_style.contains(&CiStyle::Github) {
+            warn!("skipping build matrix: it's only supported for Github CI");
+            return;
+        }
+        let release = self.release(to_release);
+        let checksum = release.checksum;
+        let artifact_name = format!("{}-build-matrix.json", release.id);
+        let artifact_path = self.inner.dist_dir.join(&artifact_name);
+
+        let matrix_artifact = Artifact {
+            id: artifact_name,
+            target_triples: release.targets.clone(),
+            archive: None,
+            file_path: artifact_path.clone(),
+            required_binaries: FastMap::new(),
+            checksum: None,
+            kind: ArtifactKind::BuildMatrix(BuildMatrixImpl {
+                dest_path: artifact_path,
+            }),
+            is_global: true,
+        };
+
+        let matrix_idx = self.add_global_artifact(to_release, matrix_artifact);
+        if checksum != ChecksumStyle::False {
+            self.add_global_artifact_checksum(to_release, matrix_idx, checksum);
+        }
+    }
+
     fn add_local_artifact(
         &mut self,
         to_variant: ReleaseVariantIdx,
@@ -1563,7 +2422,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         idx
     }
 
-    fn compute_build_steps(&mut self) {
+    pub(crate) fn compute_build_steps(&mut self) {
         // FIXME: more intelligently schedule these in a proper graph?
 
         let mut build_steps = vec![];
@@ -1618,6 +2477,9 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 ArtifactKind::Checksum(checksum) => {
                     build_steps.push(BuildStep::Checksum(checksum.clone()));
                 }
+                ArtifactKind::BuildMatrix(matrix) => {
+                    build_steps.push(BuildStep::GenerateBuildMatrix(matrix.clone()));
+                }
             }
 
             if let Some(archive) = &artifact.archive {
@@ -1682,6 +2544,31 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 rustflags.push_str(" -Ctarget-feature=+crt-static");
             }
 
+            // Github CI sets this on runners that can't build some of their targets
+            // natively, so that those targets get built with a wrapper like cross
+            let build_tool = std::env::var(BUILD_TOOL_ENV_VAR)
+                .ok()
+                .filter(|tool| !tool.is_empty());
+            let cargo = match build_tool {
+                Some(tool) if target != self.inner.tools.cargo.host_target => tool,
+                _ => self.inner.tools.cargo.cmd.clone(),
+            };
+
+            // Unusual build setups (like a build script that relocates the binaries)
+            // can tell us where to find them instead of trusting cargo
+            let binary_dir = self.inner.binary_dirs.get(&target).cloned();
+            let mut cargo_config = self
+                .inner
+                .cargo_target_config
+                .get(&target)
+                .map(|config| config.cargo_config_args(&target))
+                .unwrap_or_default();
+            // Setting this on the profile (instead of passing -Cpanic in RUSTFLAGS)
+            // lets cargo keep build scripts and proc-macros unwinding, like they need
+            if let Some(strategy) = self.inner.panic_strategy {
+                cargo_config.push(format!("profile.{PROFILE_DIST}.panic=\"{strategy}\""));
+            }
+
             // If we're trying to cross-compile on macOS, ensure the rustup toolchain
             // is setup!
             if target.ends_with("apple-darwin")
@@ -1716,6 +2603,9 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                         package: CargoTargetPackages::Package(pkg_spec),
                         features,
                         rustflags: rustflags.clone(),
+                        cargo_config: cargo_config.clone(),
+                        cargo: cargo.clone(),
+                        binary_dir: binary_dir.clone(),
                         profile: String::from(PROFILE_DIST),
                         expected_binaries,
                     }));
@@ -1731,6 +2621,9 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                     package: CargoTargetPackages::Workspace,
                     features,
                     rustflags,
+                    cargo_config,
+                    cargo,
+                    binary_dir,
                     profile: String::from(PROFILE_DIST),
                     expected_binaries: binaries,
                 }));
@@ -1744,14 +2637,34 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         self.inner.announcement_title = self.inner.announcement_tag.clone();
 
         self.compute_announcement_changelog(announcing_version);
+        self.compute_announcement_title(announcing_version);
         self.compute_announcement_github();
     }
 
-    /// If we're publishing to Github, generate some Github notes
+    /// If the title has a custom format, fill it in (replacing any title from the changelog)
+    fn compute_announcement_title(&mut self, announcing_version: Option<&Version>) {
+        let Some(format) = &self.inner.announcement_title_format else {
+            return;
+        };
+        let tag = self.inner.announcement_tag.clone().unwrap_or_default();
+        let version = announcing_version
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| tag.clone());
+        let title = format.replace("{tag}", &tag).replace("{version}", &version);
+        self.inner.announcement_title = Some(title);
+    }
+
+    /// If we're publishing to Github (or Gitlab), generate some Github notes
+    ///
+    /// Gitlab Releases render the same markdown, so they reuse these notes.
+    /// CircleCI publishes to a Github Release too.
     fn compute_announcement_github(&mut self) {
         use std::fmt::Write;
 
-        if !self.inner.ci_style.contains(&CiStyle::Github) {
+        if !self.inner.ci_style.contains(&CiStyle::Github)
+            && !self.inner.ci_style.contains(&CiStyle::Gitlab)
+            && !self.inner.ci_style.contains(&CiStyle::CircleCi)
+        {
             info!("not publishing to Github, skipping Github Release Notes");
             return;
         }
@@ -1786,7 +2699,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 match &artifact.kind {
                     ArtifactKind::ExecutableZip(zip) => bundles.push((artifact, zip)),
                     ArtifactKind::Symbols(syms) => symbols.push((artifact, syms)),
-                    ArtifactKind::Checksum(_) => {}
+                    ArtifactKind::Checksum(_) | ArtifactKind::BuildMatrix(_) => {}
                     ArtifactKind::Installer(installer) => {
                         global_installers.push((artifact, installer))
                     }
@@ -1800,7 +2713,7 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                     match &artifact.kind {
                         ArtifactKind::ExecutableZip(zip) => bundles.push((artifact, zip)),
                         ArtifactKind::Symbols(syms) => symbols.push((artifact, syms)),
-                        ArtifactKind::Checksum(_) => {}
+                        ArtifactKind::Checksum(_) | ArtifactKind::BuildMatrix(_) => {}
                         ArtifactKind::Installer(installer) => {
                             local_installers.push((artifact, installer))
                         }
@@ -1827,8 +2740,11 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
                 .chain(local_installers.iter().map(|i| i.0))
                 .chain(symbols.iter().map(|i| i.0))
                 .collect();
-            if !other_artifacts.is_empty() && download_url.is_some() {
-                let download_url = download_url.as_ref().unwrap();
+            // With release-notes-table, CI adds a more complete version of this table
+            // once it knows the sizes and checksums of everything
+            let download_url = download_url
+                .filter(|_| !other_artifacts.is_empty() && !self.inner.release_notes_table);
+            if let Some(download_url) = download_url {
                 writeln!(gh_body, "## Download {heading_suffix}\n",).unwrap();
                 gh_body.push_str("|  File  | Platform | Checksum |\n");
                 gh_body.push_str("|--------|----------|----------|\n");
@@ -1872,14 +2788,24 @@ impl<'pkg_graph> DistGraphBuilder<'pkg_graph> {
         self.inner.announcement_github_body = Some(gh_body);
     }
 
-    fn compute_ci(&mut self) {
+    fn compute_ci(&mut self) -> DistResult<()> {
         for ci in &self.inner.ci_style {
             match ci {
                 CiStyle::Github => {
-                    self.inner.ci.github = Some(GithubCiInfo::new(&self.inner));
+                    self.inner.ci.github = Some(GithubCiInfo::new(&self.inner)?);
+                }
+                CiStyle::Gitlab => {
+                    self.inner.ci.gitlab = Some(GitlabCiInfo::new(&self.inner)?);
+                }
+                CiStyle::Azure => {
+                    self.inner.ci.azure = Some(AzureCiInfo::new(&self.inner)?);
+                }
+                CiStyle::CircleCi => {
+                    self.inner.ci.circleci = Some(CircleCiInfo::new(&self.inner)?);
                 }
             }
         }
+        Ok(())
     }
 
     fn workspace(&self) -> &'pkg_graph WorkspaceInfo {
@@ -1944,14 +2870,32 @@ impl DistGraph {
     pub fn variant(&self, idx: ReleaseVariantIdx) -> &ReleaseVariant {
         &self.variants[idx.0]
     }
+    /// Get the URL that dist-manifest.json should say an artifact can be downloaded from
+    pub fn artifact_url(&self, filename: &str) -> Option<String> {
+        if let Some(template) = &self.download_url_template {
+            let tag = self.announcement_tag.as_deref()?;
+            Some(
+                template
+                    .replace("{tag}", tag)
+                    .replace("{filename}", filename),
+            )
+        } else {
+            let download_url = self.artifact_download_url.as_deref()?;
+            Some(format!("{download_url}/{filename}"))
+        }
+    }
 }
 
 /// Precompute all the work this invocation will need to do
 pub fn gather_work(cfg: &Config) -> Result<DistGraph> {
-    eprintln!("analyzing workspace:");
+    if !cfg.quiet {
+        eprintln!("analyzing workspace:");
+    }
     let tools = tool_info()?;
     let workspace = crate::config::get_project()?;
     let mut graph = DistGraphBuilder::new(tools, &workspace, cfg.artifact_mode)?;
+    graph.build_override = cfg.build_override.clone();
+    graph.quiet = cfg.quiet;
 
     // Prefer the CLI (cfg) if it's non-empty, but only select a subset
     // of what the workspace supports if it's non-empty
@@ -1977,6 +2921,16 @@ pub fn gather_work(cfg: &Config) -> Result<DistGraph> {
         .into_iter()
         .cloned()
         .collect::<Vec<_>>();
+    graph.inner.all_targets = all_target_triples.clone();
+    let mut all_installers = vec![];
+    for (id, _) in graph.workspace.packages() {
+        for installer in graph.package_metadata(id).installers.iter().flatten() {
+            if !all_installers.contains(installer) {
+                all_installers.push(*installer);
+            }
+        }
+    }
+    graph.inner.all_installers = all_installers;
 
     // Choose which set of target triples we're building for
     let mut bypass_package_target_prefs = false;
@@ -2012,9 +2966,35 @@ pub fn gather_work(cfg: &Config) -> Result<DistGraph> {
 
     graph.inner.announcement_tag = Some(announcing.tag.clone());
     graph.inner.announcement_is_prerelease = announcing.prerelease;
-    if let Some(repo_url) = workspace.web_url()?.as_ref() {
-        let tag = graph.inner.announcement_tag.as_ref().unwrap();
-        graph.inner.artifact_download_url = Some(format!("{repo_url}/releases/download/{tag}"));
+    // CircleCI publishes to a Github Release, so it counts as Github here
+    let no_github = !graph.inner.ci_style.contains(&CiStyle::Github)
+        && !graph.inner.ci_style.contains(&CiStyle::CircleCi);
+    let gitlab_only = no_github && graph.inner.ci_style.contains(&CiStyle::Gitlab);
+    let azure_only = no_github && graph.inner.ci_style.contains(&CiStyle::Azure);
+    let tag = &announcing.tag;
+    if azure_only {
+        // Azure Artifacts feeds need credentials to download from, so there's
+        // no public url for installers to fetch artifacts from
+    } else if gitlab_only {
+        // axoproject only understands Github URLs, so Gitlab gets its own handling
+        if let Some(repo_url) = workspace.repository_url.as_deref() {
+            let repo_url = gitlab::web_url(repo_url);
+            graph.inner.artifact_download_url =
+                Some(format!("{repo_url}/-/releases/{tag}/downloads"));
+        }
+    } else {
+        if let Some(repo) = workspace.github_repo()? {
+            graph.inner.github_repo = Some(format!("{}/{}", repo.owner, repo.name));
+        }
+        if let Some(repo_url) = workspace.web_url()?.as_ref() {
+            graph.inner.artifact_download_url = Some(format!("{repo_url}/releases/download/{tag}"));
+            if graph.inner.cosign {
+                // Keyless signatures from Github CI are issued to the workflow that made them
+                let repo_url = repo_url.replace('.', "\\.");
+                graph.inner.cosign_identity_regexp =
+                    Some(format!("^{repo_url}/\\.github/workflows/"));
+            }
+        }
     }
 
     // Create a Release for each package
@@ -2042,6 +3022,11 @@ pub fn gather_work(cfg: &Config) -> Result<DistGraph> {
                     .iter()
                     .any(|t| t == target);
             if !use_target {
+                info!(
+                    app_name = %graph.release(release).app_name,
+                    target_triple = %target,
+                    "skipping target that the package doesn't list in its targets"
+                );
                 continue;
             }
 
@@ -2075,19 +3060,63 @@ pub fn gather_work(cfg: &Config) -> Result<DistGraph> {
             // Create the variant
             graph.add_installer(release, installer);
         }
+
+        // Add a record of the build matrix to the Release
+        graph.add_build_matrix(release);
     }
 
+    // Add archives for any bundles of binaries (which may span Releases)
+    graph.add_bundles();
+
     // Prep the announcement's release notes and whatnot
     graph.compute_announcement_info(announcing.version.as_ref());
 
     // Finally compute all the build steps!
     graph.compute_build_steps();
 
-    graph.compute_ci();
+    if cfg.needs_ci {
+        graph.compute_ci()?;
+    }
 
     Ok(graph.inner)
 }
 
+/// Get the fully-resolved config of the workspace (impl of `cargo dist print-config`)
+///
+/// This is useful for debugging why a setting isn't taking effect.
+pub fn resolve_config() -> Result<ResolvedConfig> {
+    let tools = tool_info()?;
+    let workspace = crate::config::get_project()?;
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All)?;
+    Ok(graph.resolved_config())
+}
+
+/// Check if a name matches a simple glob, where `*` matches any run of characters
+/// and `?` matches any one character
+pub(crate) fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut g, mut n) = (0, 0);
+    // Where to resume if the most recent `*` needs to match one more character
+    let mut backtrack = None;
+    while n < name.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == name[n]) {
+            g += 1;
+            n += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            backtrack = Some((g, n));
+            g += 1;
+        } else if let Some((star_g, star_n)) = backtrack {
+            backtrack = Some((star_g, star_n + 1));
+            g = star_g + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
 /// See if we should dist this package
 ///
 /// Some(disabled_reason) is returned if it shouldn't be
@@ -2104,6 +3133,19 @@ fn check_dist_package(
         return Some("no binaries".to_owned());
     }
 
+    // Respect the workspace's package selection globs
+    let workspace_metadata = &graph.workspace_metadata;
+    if let Some(include) = &workspace_metadata.include_packages {
+        if !include.iter().any(|glob| glob_matches(glob, &pkg.name)) {
+            return Some("not in include-packages".to_owned());
+        }
+    }
+    if let Some(exclude) = &workspace_metadata.exclude_packages {
+        if let Some(glob) = exclude.iter().find(|glob| glob_matches(glob, &pkg.name)) {
+            return Some(format!("excluded by exclude-packages \"{glob}\""));
+        }
+    }
+
     // If [metadata.dist].dist is explicitly set, respect it!
     let override_publish = if let Some(do_dist) = graph.package_metadata(pkg_id).dist {
         if !do_dist {
@@ -2343,16 +3385,22 @@ pub(crate) fn parse_tag(
         let sty;
         if let Some(reason) = &disabled_reason {
             sty = &disabled_sty;
-            eprintln!("  {}", sty.apply_to(format!("{pkg_name} ({reason})")));
+            if !graph.quiet {
+                eprintln!("  {}", sty.apply_to(format!("{pkg_name} ({reason})")));
+            }
         } else {
             sty = &enabled_sty;
-            eprintln!("  {}", sty.apply_to(pkg_name));
+            if !graph.quiet {
+                eprintln!("  {}", sty.apply_to(pkg_name));
+            }
         }
 
         // Report each binary and potentially add it to the Release for this package
         let mut rust_binaries = vec![];
         for binary in &pkg.binaries {
-            eprintln!("    {}", sty.apply_to(format!("[bin] {}", binary)));
+            if !graph.quiet {
+                eprintln!("    {}", sty.apply_to(format!("[bin] {}", binary)));
+            }
             // In the future might want to allow this to be granular for each binary
             if disabled_reason.is_none() {
                 rust_binaries.push(binary.to_owned());
@@ -2364,7 +3412,9 @@ pub(crate) fn parse_tag(
             rust_releases.push((pkg_id, rust_binaries));
         }
     }
-    eprintln!();
+    if !graph.quiet {
+        eprintln!();
+    }
 
     // Don't proceed if this doesn't make sense
     if rust_releases.is_empty() {
@@ -2439,8 +3489,8 @@ pub(crate) fn parse_tag(
     })
 }
 
-/// Try to strip-prefix a package name from the given input, preferring whichever one is longest
-/// (to disambiguate situations where you have `my-app` and `my-app-helper`).
+/// Try to strip-prefix a package name (or tag-prefix) from the given input, preferring
+/// whichever one is longest (to disambiguate situations where you have `my-app` and `my-app-helper`).
 ///
 /// If a match is found, then the return value is:
 /// * the idx of the package
@@ -2451,14 +3501,175 @@ fn strip_prefix_package<'a>(
 ) -> Option<(PackageIdx, &'a str)> {
     let mut result: Option<(PackageIdx, &'a str)> = None;
     for (pkg_id, package) in graph.workspace().packages() {
-        if let Some(rest) = input.strip_prefix(&package.name) {
-            if let Some((_, best)) = result {
-                if best.len() <= rest.len() {
-                    continue;
+        let tag_prefix = graph.package_metadata(pkg_id).tag_prefix.as_deref();
+        for prefix in std::iter::once(&*package.name).chain(tag_prefix) {
+            if let Some(rest) = input.strip_prefix(prefix) {
+                if let Some((_, best)) = result {
+                    if best.len() <= rest.len() {
+                        continue;
+                    }
                 }
+                result = Some((pkg_id, rest))
             }
-            result = Some((pkg_id, rest))
         }
     }
     result
 }
+
+/// Get every license file that applies to this package
+///
+/// axoproject only reports `license-file` if it's set, but dual-licensed packages
+/// (e.g. `MIT OR Apache-2.0`) usually have a file per license sitting next to it,
+/// so we also pick up any LICENSE files there that match a license in the SPDX expression.
+fn license_files_for_package(pkg: &axoproject::PackageInfo) -> Vec<Utf8PathBuf> {
+    // `license-file` is relative to the package (joining leaves absolute paths alone)
+    let mut license_files: Vec<Utf8PathBuf> = pkg
+        .license_files
+        .iter()
+        .map(|path| pkg.package_root.join(path))
+        .collect();
+
+    // The licenses in the expression, lowercased and without versions ("Apache-2.0" => "apache")
+    let Some(license) = &pkg.license else {
+        return license_files;
+    };
+    let license_names = license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '/')
+        .filter(|id| !id.is_empty() && !["OR", "AND", "WITH"].contains(id))
+        .map(|id| id.split('-').next().unwrap_or(id).to_ascii_lowercase())
+        .collect::<SortedSet<_>>();
+    if license_names.len() < 2 {
+        return license_files;
+    }
+
+    let dirs = license_files
+        .iter()
+        .filter_map(|path| path.parent().map(ToOwned::to_owned))
+        .collect::<SortedSet<_>>();
+    for dir in dirs {
+        let Ok(entries) = dir.read_dir_utf8() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_ascii_lowercase();
+            let is_license = file_name.starts_with("license") || file_name.starts_with("unlicense");
+            let is_file = entry.file_type().map(|t| t.is_file()).unwrap_or(false);
+            if !is_license || !is_file {
+                continue;
+            }
+            let path = entry.path().to_owned();
+            let matches = license_names
+                .iter()
+                .any(|name| file_name.contains(name.as_str()));
+            if matches && !license_files.contains(&path) {
+                license_files.push(path);
+            }
+        }
+    }
+    license_files
+}
+
+/// Find the `panic` setting a cargo profile gets from the workspace's Cargo.toml, along
+/// with the profile that set it (which may be one it inherits from)
+fn profile_panic_setting(manifest_path: &Utf8Path, profile: &str) -> Option<(String, String)> {
+    let toml = config::load_cargo_toml(manifest_path).ok()?;
+    let profiles = toml.get("profile")?;
+    let mut profile = profile.to_owned();
+    // Guard against inheritance cycles, which cargo would reject anyway
+    let mut seen = SortedSet::new();
+    while seen.insert(profile.clone()) {
+        let table = profiles.get(&profile)?;
+        if let Some(panic) = table.get("panic").and_then(|p| p.as_str()) {
+            return Some((profile, panic.to_owned()));
+        }
+        profile = match table.get("inherits").and_then(|p| p.as_str()) {
+            Some(parent) => parent.to_owned(),
+            // Custom profiles must say what they inherit, so this is dev or release
+            None => return None,
+        };
+    }
+    None
+}
+
+/// Whether Github would accept this as the name of a secret
+///
+/// Github only allows alphanumerics and underscores, and these get spliced into CI
+fn is_valid_secret_name(secret: &str) -> bool {
+    !secret.is_empty()
+        && !secret.starts_with(|c: char| c.is_ascii_digit())
+        && secret
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Get the names of the workspace's publishable packages, in an order crates.io can
+/// publish them in (every package comes after the workspace packages it depends on)
+///
+/// Only normal and build dependencies are considered, as `cargo publish` strips
+/// path-only dev-dependencies. Ties are broken by the workspace's own package order.
+fn crates_io_publish_order(workspace: &WorkspaceInfo) -> DistResult<Vec<String>> {
+    let packages = workspace
+        .packages()
+        .map(|(_, pkg)| pkg)
+        .filter(|pkg| pkg.publish)
+        .collect::<Vec<_>>();
+    let names = packages
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect::<SortedSet<_>>();
+
+    // The publishable workspace packages each package depends on
+    let mut deps_of = SortedMap::new();
+    for pkg in &packages {
+        let manifest = axoasset::SourceFile::load_local(&pkg.manifest_path)?;
+        let toml = manifest.deserialize_toml_edit()?;
+        let mut tables = vec![];
+        for key in ["dependencies", "build-dependencies"] {
+            tables.extend(toml.get(key).and_then(|t| t.as_table_like()));
+        }
+        if let Some(targets) = toml.get("target").and_then(|t| t.as_table_like()) {
+            for (_, target) in targets.iter() {
+                for key in ["dependencies", "build-dependencies"] {
+                    tables.extend(target.get(key).and_then(|t| t.as_table_like()));
+                }
+            }
+        }
+        let mut deps = SortedSet::new();
+        for table in tables {
+            for (key, dep) in table.iter() {
+                // Renamed dependencies say what they really are with `package = "..."`
+                let name = dep
+                    .as_table_like()
+                    .and_then(|dep| dep.get("package"))
+                    .and_then(|name| name.as_str())
+                    .unwrap_or(key);
+                if name != pkg.name && names.contains(name) {
+                    deps.insert(name.to_owned());
+                }
+            }
+        }
+        deps_of.insert(pkg.name.clone(), deps);
+    }
+
+    let mut order = Vec::<String>::new();
+    while order.len() < packages.len() {
+        let ready = packages.iter().find(|pkg| {
+            !order.contains(&pkg.name) && deps_of[&pkg.name].iter().all(|dep| order.contains(dep))
+        });
+        let Some(ready) = ready else {
+            // Cargo rejects cycles between normal dependencies, so this shouldn't happen,
+            // but publish whatever's left as-is rather than hang
+            warn!("couldn't work out an order to publish your crates in, publishing them in workspace order");
+            order.extend(
+                packages
+                    .iter()
+                    .filter(|pkg| !order.contains(&pkg.name))
+                    .map(|pkg| pkg.name.clone())
+                    .collect::<Vec<_>>(),
+            );
+            break;
+        };
+        order.push(ready.name.clone());
+    }
+    Ok(order)
+}
@@ -0,0 +1,110 @@
+//! Tests for bundling binaries from several packages into one archive
+
+use super::mock::*;
+use serde_json::json;
+
+use crate::{config::ArtifactMode, errors::DistError, parse_tag, DistGraphBuilder};
+
+const TARGET: &str = "x86_64-unknown-linux-gnu";
+
+#[test]
+fn bundle_empty() {
+    let workspace = workspace_unified_bundles(json!({
+        "suite": { "binaries": [] }
+    }));
+
+    let tools = mock_tools();
+    let err = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All)
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, DistError::EmptyBundle { bundle } if bundle == "suite"));
+}
+
+#[test]
+fn bundle_named_like_package() {
+    // The bundle's archive would clobber axolotlsay's own executable-zip
+    let workspace = workspace_unified_bundles(json!({
+        BIN_AXO_NAME: { "binaries": [BIN_AXO_NAME, BIN_HELPER_NAME] }
+    }));
+
+    let tools = mock_tools();
+    let err = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All)
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, DistError::BundleNameCollision { bundle } if bundle == BIN_AXO_NAME));
+}
+
+#[test]
+fn bundle_binary_collision() {
+    // Renaming helper-bin to axolotlsay leaves two binaries with one name
+    let workspace = workspace_unified_bundles(json!({
+        "suite": {
+            "binaries": [BIN_AXO_NAME, BIN_HELPER_NAME],
+            "rename": { BIN_HELPER_NAME: BIN_AXO_NAME }
+        }
+    }));
+
+    let tools = mock_tools();
+    let err = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All)
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(err, DistError::BundleBinaryCollision { bundle, name } if bundle == "suite" && name == BIN_AXO_NAME)
+    );
+}
+
+#[test]
+fn bundle_across_packages() {
+    // axolotlsay and helper-bin come from different packages, but share an archive
+    let workspace = workspace_unified_bundles(json!({
+        "suite": {
+            "binaries": [BIN_AXO_NAME, BIN_HELPER_NAME],
+            "rename": { BIN_HELPER_NAME: "helper" }
+        }
+    }));
+    let tag = format!("v{BIN_AXO_VER}");
+
+    let tools = mock_tools();
+    let mut graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+    for (pkg_idx, binaries) in &announcing.rust_releases {
+        let release = graph.add_release(*pkg_idx);
+        for binary in binaries {
+            graph.add_binary(release, *pkg_idx, binary.clone());
+        }
+        graph.add_variant(release, TARGET.to_owned());
+        graph.add_executable_zip(release);
+    }
+    graph.add_bundles();
+
+    let bundle_name = format!("suite-{TARGET}");
+    let bundle = graph
+        .inner
+        .artifacts
+        .iter()
+        .find(|artifact| artifact.id.starts_with(&format!("{bundle_name}.")))
+        .unwrap();
+    let mut contents = bundle
+        .required_binaries
+        .iter()
+        .map(|(&idx, dest)| {
+            let binary = graph.inner.binary(idx);
+            let file_name = dest.file_name().unwrap().to_owned();
+            assert_eq!(dest.parent().unwrap().file_name(), Some(&*bundle_name));
+            (binary.name.clone(), file_name)
+        })
+        .collect::<Vec<_>>();
+    contents.sort();
+
+    assert_eq!(
+        contents,
+        vec![
+            (BIN_AXO_NAME.to_owned(), BIN_AXO_NAME.to_owned()),
+            (BIN_HELPER_NAME.to_owned(), "helper".to_owned()),
+        ]
+    );
+    assert!(bundle.checksum.is_some());
+}
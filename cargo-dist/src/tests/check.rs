@@ -0,0 +1,81 @@
+//! Tests for what `generate --check` finds out of date
+
+use super::mock::*;
+use axoasset::LocalAsset;
+use axoproject::WorkspaceInfo;
+use camino::Utf8PathBuf;
+use serde_json::json;
+
+use crate::{
+    backend::installer::InstallerImpl,
+    check_stale_files,
+    config::{ArtifactMode, InstallerStyle},
+    errors::DistError,
+    parse_tag, render_installer, stale_installers,
+    tasks::BuildStep,
+    DistGraphBuilder,
+};
+
+const TARGET: &str = "x86_64-unknown-linux-gnu";
+
+#[test]
+fn check_stale_shell_installer() {
+    let target_dir = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+        .unwrap()
+        .join(format!("cargo-dist-check-{}", std::process::id()));
+    let workspace = WorkspaceInfo {
+        target_dir: target_dir.clone(),
+        cargo_metadata_table: Some(json!({
+            "dist": { "installers": ["shell"], "targets": [TARGET] }
+        })),
+        ..workspace_just_axo()
+    };
+    let tag = format!("v{BIN_AXO_VER}");
+
+    let tools = mock_tools();
+    let mut graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    graph.inner.artifact_download_url = Some(format!("{REPO_URL}/releases/download/{tag}"));
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+    for (pkg_idx, binaries) in &announcing.rust_releases {
+        let release = graph.add_release(*pkg_idx);
+        for binary in binaries {
+            graph.add_binary(release, *pkg_idx, binary.clone());
+        }
+        graph.add_variant(release, TARGET.to_owned());
+        graph.add_executable_zip(release);
+        graph.add_installer(release, &InstallerStyle::Shell);
+    }
+    graph.compute_build_steps();
+    let dist = graph.inner;
+    let installer = dist
+        .build_steps
+        .iter()
+        .find_map(|step| match step {
+            BuildStep::GenerateInstaller(installer @ InstallerImpl::Shell(_)) => Some(installer),
+            _ => None,
+        })
+        .unwrap();
+    let InstallerImpl::Shell(info) = installer else {
+        unreachable!()
+    };
+
+    // Nothing's been built yet, so there's nothing to be out of date
+    assert!(stale_installers(&dist).unwrap().is_empty());
+
+    // An installer from an older build is out of date
+    LocalAsset::write_new_all("#!/bin/sh\necho old installer\n", &info.dest_path).unwrap();
+    let stale = stale_installers(&dist).unwrap();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].path, info.dest_path.as_str());
+    let err = check_stale_files(stale).unwrap_err();
+    assert!(
+        matches!(err, DistError::CheckFileMismatch { file, .. } if file == info.dest_path.as_str())
+    );
+
+    // ...until it's rebuilt
+    let rendered = render_installer(&dist, installer).unwrap().unwrap();
+    LocalAsset::write_new_all(&rendered, &info.dest_path).unwrap();
+    assert!(stale_installers(&dist).unwrap().is_empty());
+
+    std::fs::remove_dir_all(target_dir).unwrap();
+}
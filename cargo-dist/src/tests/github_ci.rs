@@ -0,0 +1,340 @@
+//! Tests for what GithubCiInfo::new derives from the config
+
+use super::mock::*;
+use serde_json::json;
+
+use crate::{
+    backend::ci::github::{GithubCiInfo, GithubCiWarning},
+    config::ArtifactMode,
+    parse_tag, DistGraph, DistGraphBuilder,
+};
+
+const LINUX: &str = "x86_64-unknown-linux-gnu";
+const MACOS_X64: &str = "x86_64-apple-darwin";
+
+/// Plan a release of axolotlsay built for `targets`, with the given settings in
+/// [workspace.metadata.dist]
+fn dist_graph(config: serde_json::Value, targets: &[&str]) -> DistGraph {
+    let mut config = config;
+    config["targets"] = json!(targets);
+    config["include-packages"] = json!([BIN_AXO_NAME]);
+    let workspace = workspace_unified_selection(config);
+    let tag = format!("v{BIN_AXO_VER}");
+
+    let tools = mock_tools();
+    let mut graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    graph.inner.all_targets = targets.iter().map(|t| (*t).to_owned()).collect();
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+    for (pkg_idx, binaries) in &announcing.rust_releases {
+        let release = graph.add_release(*pkg_idx);
+        for binary in binaries {
+            graph.add_binary(release, *pkg_idx, binary.clone());
+        }
+        for target in targets {
+            graph.add_variant(release, (*target).to_owned());
+        }
+        graph.add_executable_zip(release);
+    }
+    graph.inner
+}
+
+/// Compute the Github CI for axolotlsay built for `targets`, with the given
+/// settings in [workspace.metadata.dist]
+fn github_ci(config: serde_json::Value, targets: &[&str]) -> GithubCiInfo {
+    GithubCiInfo::new(&dist_graph(config, targets)).unwrap()
+}
+
+fn config_warnings(ci: &GithubCiInfo) -> Vec<&str> {
+    ci.warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            GithubCiWarning::Config(message) => Some(&**message),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the permissions `job` gets, as (scope, level) pairs
+fn permissions<'a>(ci: &'a GithubCiInfo, job: &str) -> Vec<(&'a str, &'a str)> {
+    ci.permissions[job]
+        .iter()
+        .map(|(scope, level)| (&**scope, &**level))
+        .collect()
+}
+
+fn deprecated_runners(ci: &GithubCiInfo) -> Vec<(&str, &str)> {
+    ci.warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            GithubCiWarning::DeprecatedRunner {
+                runner,
+                replacement,
+            } => Some((&**runner, &**replacement)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn deprecated_default_runner() {
+    // The default x64 macos runner is macos-11
+    let ci = github_ci(json!({}), &[MACOS_X64]);
+
+    assert_eq!(deprecated_runners(&ci), vec![("macos-11", "macos-13")]);
+}
+
+#[test]
+fn deprecated_default_runner_replaced() {
+    // Replacing a default runner with a custom one gets rid of its warning,
+    // but the other defaults still get checked
+    let ci = github_ci(
+        json!({
+            "github-custom-runners": { MACOS_X64: "macos-13" }
+        }),
+        &[MACOS_X64, LINUX],
+    );
+
+    assert_eq!(
+        deprecated_runners(&ci),
+        vec![("ubuntu-20.04", "ubuntu-22.04")]
+    );
+}
+
+#[test]
+fn test_shards_split_build_tasks() {
+    let ci = github_ci(
+        json!({ "run-tests": true, "test-shards": 3, "github-build-job-name": "build {targets}" }),
+        &[LINUX],
+    );
+
+    // The shards replace the single test job
+    assert!(ci.test_task.is_none());
+    assert_eq!(ci.test_shards.as_ref().unwrap().count, 3);
+    let shards = ci
+        .artifacts_matrix
+        .include
+        .iter()
+        .map(|task| (task.shard, task.shard_count, task.job_name.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        shards,
+        vec![
+            (
+                Some(0),
+                Some(3),
+                Some("build x86_64-unknown-linux-gnu (shard 1/3)")
+            ),
+            (
+                Some(1),
+                Some(3),
+                Some("build x86_64-unknown-linux-gnu (shard 2/3)")
+            ),
+            (
+                Some(2),
+                Some(3),
+                Some("build x86_64-unknown-linux-gnu (shard 3/3)")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_shards_without_run_tests() {
+    // There are no tests to shard, so the build tasks are left alone
+    let ci = github_ci(json!({ "test-shards": 3 }), &[LINUX]);
+
+    assert!(ci.test_task.is_none() && ci.test_shards.is_none());
+    assert_eq!(ci.artifacts_matrix.include.len(), 1);
+    assert_eq!(ci.artifacts_matrix.include[0].shard, None);
+    assert!(ci.warnings.iter().any(
+        |warning| matches!(warning, GithubCiWarning::Config(message) if message.contains("test-shards"))
+    ));
+}
+
+#[test]
+fn homebrew_tap_token_secrets() {
+    // Taps without their own secret share the homebrew job's
+    let ci = github_ci(
+        json!({
+            "installers": ["homebrew"],
+            "publish-jobs": ["homebrew"],
+            "publish-job-secrets": { "homebrew": "SHARED_TAP_TOKEN" },
+            "tap": "axodotdev/homebrew-formulae",
+            "taps": [
+                { "repo": "axodotdev/homebrew-casks", "kind": "cask", "token-secret": "CASKS_TAP_TOKEN" },
+            ],
+        }),
+        &[MACOS_X64],
+    );
+
+    let taps = ci
+        .homebrew_taps
+        .iter()
+        .map(|tap| (&*tap.job_name, &*tap.token_secret))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        taps,
+        vec![
+            ("publish-homebrew-formula", "SHARED_TAP_TOKEN"),
+            ("publish-homebrew-cask", "CASKS_TAP_TOKEN"),
+        ]
+    );
+}
+
+#[test]
+fn test_shards_render() {
+    let dist = dist_graph(json!({ "run-tests": true, "test-shards": 2 }), &[LINUX]);
+    let ci = GithubCiInfo::new(&dist).unwrap();
+    let rendered = ci.generate_github_ci(&dist, &ci.workflows[0], &[]).unwrap();
+
+    // Every shard gets told which one it is, runs its part of the tests with a
+    // pinned nextest, and only shard 0 uploads anything
+    assert!(rendered.contains("CARGO_DIST_SHARD: ${{ matrix.shard }}"));
+    assert!(rendered.contains("CARGO_DIST_SHARD_COUNT: ${{ matrix.shard_count }}"));
+    assert!(rendered.contains("cargo install cargo-nextest --version 0.9.67 --locked"));
+    assert!(rendered.contains(
+        "cargo nextest run --workspace --partition count:$((CARGO_DIST_SHARD + 1))/$CARGO_DIST_SHARD_COUNT"
+    ));
+    assert!(rendered.contains("if: ${{ !matrix.shard }}"));
+    assert!(!rendered.contains("cargo test --workspace"));
+}
+
+#[test]
+fn concurrency_defaults_to_queue() {
+    let ci = github_ci(json!({}), &[LINUX]);
+
+    assert_eq!(ci.concurrency.as_deref(), Some("queue"));
+}
+
+#[test]
+fn concurrency_none() {
+    let ci = github_ci(json!({ "concurrency": "none" }), &[LINUX]);
+
+    assert_eq!(ci.concurrency, None);
+}
+
+#[test]
+fn concurrency_reusable_workflow() {
+    // The caller's concurrency group would deadlock with ours, so there's no default
+    let ci = github_ci(json!({ "github-reusable-workflow": true }), &[LINUX]);
+    assert_eq!(ci.concurrency, None);
+
+    // But asking for it explicitly still works
+    let ci = github_ci(
+        json!({ "github-reusable-workflow": true, "concurrency": "cancel" }),
+        &[LINUX],
+    );
+    assert_eq!(ci.concurrency.as_deref(), Some("cancel"));
+}
+
+#[test]
+fn job_permissions_default() {
+    let ci = github_ci(json!({}), &[LINUX]);
+
+    assert_eq!(
+        ci.permissions.keys().map(|job| &**job).collect::<Vec<_>>(),
+        vec![
+            "plan",
+            "publish-release",
+            "should-publish",
+            "upload-local-artifacts"
+        ]
+    );
+    assert_eq!(permissions(&ci, "plan"), vec![("contents", "read")]);
+    assert_eq!(
+        permissions(&ci, "upload-local-artifacts"),
+        vec![("contents", "read")]
+    );
+    assert_eq!(
+        permissions(&ci, "publish-release"),
+        vec![("contents", "write")]
+    );
+}
+
+#[test]
+fn job_permissions_for_enabled_features() {
+    let ci = github_ci(
+        json!({
+            "run-tests": true,
+            "early-draft-release": true,
+            "github-attestations": true,
+            "global-build": true,
+        }),
+        &[LINUX],
+    );
+
+    // Uploading to an early draft release needs write access
+    assert_eq!(permissions(&ci, "plan"), vec![("contents", "write")]);
+    assert_eq!(
+        permissions(&ci, "upload-local-artifacts"),
+        vec![("contents", "write")]
+    );
+    assert_eq!(
+        permissions(&ci, "upload-global-artifacts"),
+        vec![("contents", "write")]
+    );
+    assert_eq!(permissions(&ci, "test"), vec![("contents", "read")]);
+    assert_eq!(
+        permissions(&ci, "publish-release"),
+        vec![
+            ("attestations", "write"),
+            ("contents", "write"),
+            ("id-token", "write")
+        ]
+    );
+}
+
+#[test]
+fn job_permissions_overrides() {
+    let ci = github_ci(
+        json!({
+            "github-permissions": {
+                "publish-release": { "packages": "write" },
+                "publish-nothing": { "contents": "write" },
+                "plan": { "contents": "admin" },
+            },
+        }),
+        &[LINUX],
+    );
+
+    assert_eq!(
+        permissions(&ci, "publish-release"),
+        vec![("contents", "write"), ("packages", "write")]
+    );
+    // Bad levels are still passed along (Github will be the one to reject them),
+    // but jobs that don't exist aren't made up
+    assert_eq!(permissions(&ci, "plan"), vec![("contents", "admin")]);
+    assert!(!ci.permissions.contains_key("publish-nothing"));
+    let warnings = config_warnings(&ci);
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("github-permissions gives plan \"admin\" access"));
+    assert!(warnings[1].starts_with("github-permissions has an entry for publish-nothing"));
+}
+
+#[test]
+fn config_warnings_for_ignored_settings() {
+    let ci = github_ci(
+        json!({
+            "create-release": false,
+            "early-draft-release": true,
+            "windows-signing": true,
+            "github-action-pins": { "actions/checkout": "v4" },
+        }),
+        &[LINUX],
+    );
+
+    // The settings that can't do anything are turned off, with a warning each
+    assert!(!ci.early_draft_release);
+    let warnings = config_warnings(&ci);
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings[0].starts_with("early-draft-release is set"));
+    assert!(warnings[1].starts_with("github-action-pins pins actions/checkout to v4"));
+    assert!(warnings[2].starts_with("windows-signing is enabled"));
+}
+
+#[test]
+fn no_config_warnings_by_default() {
+    let ci = github_ci(json!({}), &[LINUX, MACOS_X64]);
+
+    assert!(config_warnings(&ci).is_empty());
+}
@@ -26,6 +26,7 @@ pub const LIB_OTHER_VER: &str = "0.5.0";
 pub const BIN_ODDBALL_NAME: &str = "oddball-bin";
 pub const BIN_ODDBALL_VER: &str = "0.1.0";
 pub const BIN_ODDBALL_IDX: PackageIdx = PackageIdx(4);
+pub const BIN_ODDBALL_TAG_PREFIX: &str = "odd";
 
 pub const BIN_FORCED_NAME: &str = "forced-bin";
 pub const BIN_FORCED_VER: &str = BIN_AXO_VER;
@@ -69,7 +70,7 @@ pub fn mock_package(name: &str, ver: &str) -> PackageInfo {
         cstaticlibs: vec![],
         cdylibs: vec![],
         cargo_metadata_table: None,
-        cargo_package_id: None,
+        cargo_package_id: Some(guppy::PackageId::new(format!("{name} {ver}"))),
     }
 }
 
@@ -145,6 +146,19 @@ pub fn pkg_oddball_bin() -> PackageInfo {
         ..mock_package(BIN_ODDBALL_NAME, BIN_ODDBALL_VER)
     }
 }
+/// oddball-bin 0.1.0
+///
+/// has tag-prefix="odd" set
+pub fn pkg_oddball_bin_tag_prefix() -> PackageInfo {
+    PackageInfo {
+        cargo_metadata_table: Some(json!({
+            "dist": {
+                "tag-prefix": BIN_ODDBALL_TAG_PREFIX
+            }
+        })),
+        ..pkg_oddball_bin()
+    }
+}
 pub fn entry_oddball_bin() -> (PackageIdx, Vec<String>) {
     (BIN_ODDBALL_IDX, vec![BIN_ODDBALL_NAME.to_owned()])
 }
@@ -226,3 +240,38 @@ pub fn workspace_disjoint() -> WorkspaceInfo {
         pkg_test_bin2(),
     ])
 }
+
+/// workspace_disjoint, but oddball-bin has tag-prefix="odd" set
+pub fn workspace_disjoint_tag_prefix() -> WorkspaceInfo {
+    mock_workspace(vec![
+        pkg_axo_bin(),
+        pkg_some_lib(),
+        pkg_helper_bin(),
+        pkg_other_lib(),
+        pkg_oddball_bin_tag_prefix(),
+        pkg_forced_bin(),
+        pkg_test_bin1(),
+        pkg_test_bin2(),
+    ])
+}
+
+/// workspace_unified, with the given bundles set in its [workspace.metadata.dist]
+pub fn workspace_unified_bundles(bundles: serde_json::Value) -> WorkspaceInfo {
+    WorkspaceInfo {
+        cargo_metadata_table: Some(json!({
+            "dist": {
+                "bundles": bundles
+            }
+        })),
+        ..workspace_unified()
+    }
+}
+
+/// workspace_unified, with the given include-packages/exclude-packages (or other settings)
+/// in its [workspace.metadata.dist]
+pub fn workspace_unified_selection(selection: serde_json::Value) -> WorkspaceInfo {
+    WorkspaceInfo {
+        cargo_metadata_table: Some(json!({ "dist": selection })),
+        ..workspace_unified()
+    }
+}
@@ -1,2 +1,7 @@
+mod bundles;
+mod check;
+mod github_ci;
 mod mock;
+mod packages;
+mod runners;
 mod tag;
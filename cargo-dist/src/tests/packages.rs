@@ -0,0 +1,106 @@
+//! Tests for picking packages with include-packages and exclude-packages
+
+use super::mock::*;
+use serde_json::json;
+
+use crate::{config::ArtifactMode, errors::DistError, glob_matches, parse_tag, DistGraphBuilder};
+
+#[test]
+fn glob_exact() {
+    assert!(glob_matches(BIN_AXO_NAME, BIN_AXO_NAME));
+    assert!(glob_matches("", ""));
+}
+
+#[test]
+fn glob_star() {
+    assert!(glob_matches("*-bin", BIN_HELPER_NAME));
+    assert!(glob_matches("axo*say", BIN_AXO_NAME));
+    assert!(glob_matches("*", BIN_AXO_NAME));
+    assert!(glob_matches("*", ""));
+    // A star can match nothing at all
+    assert!(glob_matches("helper*-bin", BIN_HELPER_NAME));
+    // Matching has to back up when the first place a star could stop is wrong
+    assert!(glob_matches("*-bin", "helper-bin-bin"));
+    assert!(glob_matches("h*l*r-bin", BIN_HELPER_NAME));
+}
+
+#[test]
+fn glob_question() {
+    assert!(glob_matches("helper-bi?", BIN_HELPER_NAME));
+    assert!(glob_matches("??????????", BIN_HELPER_NAME));
+    // ? always matches exactly one character
+    assert!(!glob_matches("helper-bin?", BIN_HELPER_NAME));
+    assert!(!glob_matches("helper-b?", BIN_HELPER_NAME));
+}
+
+#[test]
+fn glob_trailing_star() {
+    assert!(glob_matches("helper-bin*", BIN_HELPER_NAME));
+    assert!(glob_matches("helper-bin*", BIN_HELPER_NAME2));
+    assert!(glob_matches("helper-bin**", BIN_HELPER_NAME));
+    assert!(!glob_matches("helper-bin*", BIN_AXO_NAME));
+}
+
+#[test]
+fn glob_no_match() {
+    // Globs have to match the whole name, not just part of it
+    assert!(!glob_matches("axolotl", BIN_AXO_NAME));
+    assert!(!glob_matches("lotl*", BIN_AXO_NAME));
+    assert!(!glob_matches("*-bi", BIN_HELPER_NAME));
+    assert!(!glob_matches("", BIN_AXO_NAME));
+    assert!(!glob_matches(BIN_AXO_NAME, ""));
+}
+
+#[test]
+fn select_include() {
+    let workspace = workspace_unified_selection(json!({
+        "include-packages": ["axo*"]
+    }));
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, None, true).unwrap();
+
+    assert_eq!(announcing.rust_releases, vec![entry_axo_bin()]);
+}
+
+#[test]
+fn select_exclude() {
+    let workspace = workspace_unified_selection(json!({
+        "exclude-packages": ["helper-*"]
+    }));
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, None, true).unwrap();
+
+    assert_eq!(announcing.rust_releases, vec![entry_axo_bin()]);
+}
+
+#[test]
+fn select_include_and_exclude() {
+    // Packages have to be included and not excluded
+    let workspace = workspace_unified_selection(json!({
+        "include-packages": ["*-bin", "axo*"],
+        "exclude-packages": ["axolotl?ay"]
+    }));
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, None, true).unwrap();
+
+    assert_eq!(announcing.rust_releases, vec![entry_helper_bin()]);
+}
+
+#[test]
+fn select_no_match() {
+    let workspace = workspace_unified_selection(json!({
+        "include-packages": ["axolotl"]
+    }));
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let err = parse_tag(&graph, None, true).err().unwrap();
+
+    assert!(matches!(err, DistError::NothingToRelease));
+}
@@ -0,0 +1,86 @@
+//! Tests for how merge-tasks groups targets onto Github Runners
+
+use cargo_dist_schema::GithubRunnerLabels;
+
+use crate::{
+    backend::ci::github::{
+        distribute_targets_to_runners_merged, macos_targets_cross_compiled_on_intel,
+    },
+    SortedMap, SortedSet, TargetTriple,
+};
+
+const MACOS_X64: &str = "x86_64-apple-darwin";
+const MACOS_ARM64: &str = "aarch64-apple-darwin";
+
+fn macos_targets() -> Vec<TargetTriple> {
+    vec![MACOS_X64.to_owned(), MACOS_ARM64.to_owned()]
+}
+
+#[test]
+fn merged_macos_default_runners() {
+    // With the default runners each arch gets a runner that builds it natively
+    let targets = macos_targets();
+    let custom_runners = SortedMap::new();
+    let fallback_runners = SortedMap::new();
+    let runs = distribute_targets_to_runners_merged(
+        targets.iter().collect::<SortedSet<_>>(),
+        &custom_runners,
+        &fallback_runners,
+    )
+    .unwrap()
+    .collect::<Vec<_>>();
+
+    assert_eq!(runs.len(), 2);
+    for (runner, targets) in runs {
+        assert_eq!(targets.len(), 1);
+        assert!(macos_targets_cross_compiled_on_intel(runner, &targets).is_empty());
+    }
+}
+
+#[test]
+fn merged_macos_intel_custom_runner() {
+    // The arches still aren't merged onto the same custom Intel runner, but arm64's
+    // run has to cross-compile on it
+    let targets = macos_targets();
+    let custom_runners = targets
+        .iter()
+        .map(|t| (t.clone(), GithubRunnerLabels::Label("macos-12".to_owned())))
+        .collect::<SortedMap<_, _>>();
+    let fallback_runners = SortedMap::new();
+    let runs = distribute_targets_to_runners_merged(
+        targets.iter().collect::<SortedSet<_>>(),
+        &custom_runners,
+        &fallback_runners,
+    )
+    .unwrap()
+    .collect::<Vec<_>>();
+
+    assert_eq!(runs.len(), 2);
+    let cross = runs
+        .iter()
+        .flat_map(|(runner, targets)| macos_targets_cross_compiled_on_intel(*runner, targets))
+        .collect::<Vec<_>>();
+    assert_eq!(cross, vec![MACOS_ARM64]);
+}
+
+#[test]
+fn merged_macos_arm64_custom_runner() {
+    // An arm64 custom runner builds both arches without cross-compiling arm64
+    let targets = macos_targets();
+    let custom_runners = targets
+        .iter()
+        .map(|t| (t.clone(), GithubRunnerLabels::Label("macos-14".to_owned())))
+        .collect::<SortedMap<_, _>>();
+    let fallback_runners = SortedMap::new();
+    let runs = distribute_targets_to_runners_merged(
+        targets.iter().collect::<SortedSet<_>>(),
+        &custom_runners,
+        &fallback_runners,
+    )
+    .unwrap()
+    .collect::<Vec<_>>();
+
+    for (runner, targets) in runs {
+        assert!(macos_targets_cross_compiled_on_intel(runner, &targets).is_empty());
+    }
+}
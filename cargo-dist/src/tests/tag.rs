@@ -395,3 +395,54 @@ fn parse_disjoint_lib() {
     assert_eq!(announcing.version, None);
     assert_eq!(announcing.rust_releases, vec![]);
 }
+
+#[test]
+fn parse_disjoint_tag_prefix() {
+    // selecting the oddball package by its tag-prefix in a disjoint workspace
+    let workspace = workspace_disjoint_tag_prefix();
+    let version: Version = BIN_ODDBALL_VER.parse().unwrap();
+    let tag = format!("{BIN_ODDBALL_TAG_PREFIX}-v{version}");
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+
+    assert!(!announcing.prerelease);
+    assert_eq!(announcing.tag, tag);
+    assert_eq!(announcing.version, None);
+    assert_eq!(announcing.rust_releases, vec![entry_oddball_bin()]);
+}
+
+#[test]
+fn parse_disjoint_tag_prefix_slash() {
+    // selecting the oddball package by its tag-prefix in a disjoint workspace
+    let workspace = workspace_disjoint_tag_prefix();
+    let version: Version = BIN_ODDBALL_VER.parse().unwrap();
+    let tag = format!("{BIN_ODDBALL_TAG_PREFIX}/{version}");
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+
+    assert!(!announcing.prerelease);
+    assert_eq!(announcing.tag, tag);
+    assert_eq!(announcing.version, None);
+    assert_eq!(announcing.rust_releases, vec![entry_oddball_bin()]);
+}
+
+#[test]
+fn parse_disjoint_tag_prefix_name() {
+    // the oddball package's name still works after it sets a tag-prefix
+    let workspace = workspace_disjoint_tag_prefix();
+    let version: Version = BIN_ODDBALL_VER.parse().unwrap();
+    let tag = format!("{BIN_ODDBALL_NAME}-v{version}");
+
+    let tools = mock_tools();
+    let graph = DistGraphBuilder::new(tools, &workspace, ArtifactMode::All).unwrap();
+    let announcing = parse_tag(&graph, Some(&tag), true).unwrap();
+
+    assert!(!announcing.prerelease);
+    assert_eq!(announcing.tag, tag);
+    assert_eq!(announcing.version, None);
+    assert_eq!(announcing.rust_releases, vec![entry_oddball_bin()]);
+}
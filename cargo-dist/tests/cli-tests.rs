@@ -108,7 +108,7 @@ fn test_lib_manifest() {
         .arg("--no-local-paths")
         .arg("--output-format=json")
         .arg("--tag")
-        .arg(&format!("cargo-dist-schema-v{}", version))
+        .arg(format!("cargo-dist-schema-v{}", version))
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .output()
@@ -132,7 +132,7 @@ fn test_lib_manifest_slash() {
         .arg("--no-local-paths")
         .arg("--output-format=json")
         .arg("--tag")
-        .arg(&format!("cargo-dist-schema/v{}", version))
+        .arg(format!("cargo-dist-schema/v{}", version))
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .output()
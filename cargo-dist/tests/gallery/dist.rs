@@ -49,6 +49,8 @@ pub struct Tools {
     pub cargo_dist: CommandInfo,
     pub shellcheck: Option<CommandInfo>,
     pub psanalyzer: Option<CommandInfo>,
+    // only used on macos
+    #[allow(dead_code)]
     pub homebrew: Option<CommandInfo>,
 }
 